@@ -43,6 +43,7 @@ pub const fn bytes32(val: u32) -> (u8, u8, u8, u8) {
 }
 
 /// Make a 64-bit value from eight 8-bit values.
+#[allow(clippy::too_many_arguments)]
 pub const fn make64(b0: u8, b1: u8, b2: u8, b3: u8, b4: u8, b5: u8, b6: u8, b7: u8) -> u64 {
     (b0 as u64) | ((b1 as u64) << 8) | ((b2 as u64) << 16) | ((b3 as u64) << 24) |
     ((b4 as u64) << 32) | ((b5 as u64) << 40) | ((b6 as u64) << 48) | ((b7 as u64) << 56)
@@ -54,6 +55,38 @@ pub const fn bytes64(val: u64) -> (u8, u8, u8, u8, u8, u8, u8, u8) {
     (val >> 32) as u8, (val >> 40) as u8, (val >> 48) as u8, (val >> 56) as u8)
 }
 
+/// Make a 16-bit value from two 8-bit values.
+/// The bytes are in order from most significant to least significant.
+pub const fn make16_be(hi: u8, lo: u8) -> u16 {
+    make16(lo, hi)
+}
+
+/// Make a 32-bit value from four 8-bit values.
+/// The bytes are in order from most significant to least significant.
+pub const fn make32_be(b0: u8, b1: u8, b2: u8, b3: u8) -> u32 {
+    make32(b3, b2, b1, b0)
+}
+
+/// Get the bytes from a 32-bit value.
+/// The bytes are in order from most significant to least significant.
+pub const fn bytes32_be(val: u32) -> (u8, u8, u8, u8) {
+    ((val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8)
+}
+
+/// Make a 64-bit value from eight 8-bit values.
+/// The bytes are in order from most significant to least significant.
+#[allow(clippy::too_many_arguments)]
+pub const fn make64_be(b0: u8, b1: u8, b2: u8, b3: u8, b4: u8, b5: u8, b6: u8, b7: u8) -> u64 {
+    make64(b7, b6, b5, b4, b3, b2, b1, b0)
+}
+
+/// Get the bytes from a 64-bit value.
+/// The bytes are in order from most significant to least significant.
+pub const fn bytes64_be(val: u64) -> (u8, u8, u8, u8, u8, u8, u8, u8) {
+    ((val >> 56) as u8, (val >> 48) as u8, (val >> 40) as u8, (val >> 32) as u8,
+    (val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8)
+}
+
 /// Get the low word of a 64-bit value.
 pub const fn lo64(val: u64) -> u32 {
     val as u32
@@ -80,4 +113,158 @@ pub const fn sign_extend_16(val: u16) -> u32 {
 /// Returns a 64-bit signed value.
 pub const fn sign_extend_32(val: u32) -> i64 {
     (val as i32) as i64
-}
\ No newline at end of file
+}
+
+/// Convert a 16-bit branch offset, in instructions, into a byte offset.
+/// Returns a 32-bit two's complement value, to be added to the address of the delay slot with wrapping.
+/// 
+/// The offset is sign-extended before it is scaled, so only sign bits are shifted out.
+pub const fn branch_offset(offset: u16) -> u32 {
+    sign_extend_16(offset) << 2
+}
+
+// ARITHMETIC
+
+/// The result of a signed operation didn't fit in 32 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+/// Add two values as signed 32-bit integers.
+/// Returns an error if the result overflows.
+pub const fn checked_add_signed(a: u32, b: u32) -> Result<u32, Overflow> {
+    match (a as i32).checked_add(b as i32) {
+        Some(result) => Ok(result as u32),
+        None => Err(Overflow),
+    }
+}
+
+/// Subtract `b` from `a` as signed 32-bit integers.
+/// Returns an error if the result overflows.
+pub const fn checked_sub_signed(a: u32, b: u32) -> Result<u32, Overflow> {
+    match (a as i32).checked_sub(b as i32) {
+        Some(result) => Ok(result as u32),
+        None => Err(Overflow),
+    }
+}
+
+/// Divide `a` by `b` as signed 32-bit integers.
+/// Returns the quotient and remainder, or `None` if `b` is zero.
+///
+/// `i32::MIN / -1` wraps, giving a quotient of `i32::MIN` and a remainder of 0.
+pub const fn checked_div_signed(a: u32, b: u32) -> Option<(u32, u32)> {
+    let a = a as i32;
+    let b = b as i32;
+    if b == 0 {
+        None
+    } else if a == i32::MIN && b == -1 {
+        Some((i32::MIN as u32, 0))
+    } else {
+        Some(((a / b) as u32, (a % b) as u32))
+    }
+}
+
+/// Divide `a` by `b` as unsigned 32-bit integers.
+/// Returns the quotient and remainder, or `None` if `b` is zero.
+pub const fn checked_div_unsigned(a: u32, b: u32) -> Option<(u32, u32)> {
+    match a.checked_div(b) {
+        Some(quotient) => Some((quotient, a % b)),
+        None => None,
+    }
+}
+
+// ADDRESSES
+
+/// Get the cached kseg0 address of a physical address in the low 512MB.
+pub const fn kseg0(phys: u32) -> u32 {
+    0x8000_0000 | (phys & 0x1FFF_FFFF)
+}
+
+/// Get the uncached kseg1 address of a physical address in the low 512MB.
+pub const fn kseg1(phys: u32) -> u32 {
+    0xA000_0000 | (phys & 0x1FFF_FFFF)
+}
+
+/// Get the physical address of a kseg0 or kseg1 address.
+/// Other addresses are mapped by the TLB, so they are returned unchanged.
+pub const fn to_physical(vaddr: u32) -> u32 {
+    match vaddr {
+        0x8000_0000..=0xBFFF_FFFF => vaddr & 0x1FFF_FFFF,
+        _ => vaddr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_order() {
+        assert_eq!(make32_be(0x12, 0x34, 0x56, 0x78), 0x1234_5678);
+        assert_eq!(make32(0x12, 0x34, 0x56, 0x78), 0x7856_3412);
+        assert_eq!(bytes32_be(0x1234_5678), (0x12, 0x34, 0x56, 0x78));
+        assert_eq!(bytes32(0x1234_5678), (0x78, 0x56, 0x34, 0x12));
+        assert_eq!(make16_be(0x12, 0x34), 0x1234);
+        assert_eq!(make64_be(1, 2, 3, 4, 5, 6, 7, 8), 0x0102_0304_0506_0708);
+        assert_eq!(bytes64_be(0x0102_0304_0506_0708), (1, 2, 3, 4, 5, 6, 7, 8));
+    }
+
+    #[test]
+    fn sign_extend() {
+        assert_eq!(sign_extend_8(0x7F), 0x0000_007F);
+        assert_eq!(sign_extend_8(0x80), 0xFFFF_FF80);
+        assert_eq!(sign_extend_8(0xFF), 0xFFFF_FFFF);
+        assert_eq!(sign_extend_16(0x7FFF), 0x0000_7FFF);
+        assert_eq!(sign_extend_16(0x8000), 0xFFFF_8000);
+        assert_eq!(sign_extend_16(0xFFFF), 0xFFFF_FFFF);
+        assert_eq!(sign_extend_32(0x8000_0000), -0x8000_0000);
+    }
+
+    #[test]
+    fn branch_offsets() {
+        assert_eq!(branch_offset(0x0001), 4);
+        assert_eq!(branch_offset(0x7FFF), 0x0001_FFFC);
+        assert_eq!(branch_offset(0xFFFF), (-4i32) as u32);
+        assert_eq!(branch_offset(0x8000), (-0x2_0000i32) as u32);
+        assert_eq!(0x10u32.wrapping_add(branch_offset(0xFFF8)), 0xFFFF_FFF0);
+    }
+
+    #[test]
+    fn checked_add() {
+        assert_eq!(checked_add_signed(1, 2), Ok(3));
+        assert_eq!(checked_add_signed(0xFFFF_FFFF, 5), Ok(4));
+        assert_eq!(checked_add_signed(0x7FFF_FFFF, 1), Err(Overflow));
+        assert_eq!(checked_add_signed(0x8000_0000, 0xFFFF_FFFF), Err(Overflow));
+    }
+
+    #[test]
+    fn checked_sub() {
+        assert_eq!(checked_sub_signed(5, 7), Ok(0xFFFF_FFFE));
+        assert_eq!(checked_sub_signed(0x7FFF_FFFF, 0xFFFF_FFFF), Err(Overflow));
+        assert_eq!(checked_sub_signed(0x8000_0000, 1), Err(Overflow));
+    }
+
+    #[test]
+    fn checked_div() {
+        assert_eq!(checked_div_signed(7, 0xFFFF_FFFE), Some((0xFFFF_FFFD, 1)));
+        assert_eq!(checked_div_signed(0xFFFF_FFF9, 2), Some((0xFFFF_FFFD, 0xFFFF_FFFF)));
+        assert_eq!(checked_div_signed(5, 0), None);
+        assert_eq!(checked_div_signed(0x8000_0000, 0xFFFF_FFFF), Some((0x8000_0000, 0)));
+        assert_eq!(checked_div_unsigned(0xFFFF_FFF9, 2), Some((0x7FFF_FFFC, 1)));
+        assert_eq!(checked_div_unsigned(5, 0), None);
+    }
+
+    #[test]
+    fn kseg() {
+        assert_eq!(kseg0(0x1FC0_0000), 0x9FC0_0000);
+        assert_eq!(kseg1(0x1FC0_0000), 0xBFC0_0000);
+        assert_eq!(kseg1(0x0000_1000), 0xA000_1000);
+        assert_eq!(to_physical(0x8000_0080), 0x0000_0080);
+        assert_eq!(to_physical(0xBFC0_0000), 0x1FC0_0000);
+        assert_eq!(to_physical(0x0040_0000), 0x0040_0000);
+        assert_eq!(to_physical(0xC000_0000), 0xC000_0000);
+        for phys in [0, 0x1234_5678, 0x1FFF_FFFF] {
+            assert_eq!(to_physical(kseg0(phys)), phys);
+            assert_eq!(to_physical(kseg1(phys)), phys);
+        }
+    }
+}
@@ -0,0 +1,620 @@
+// Standard coprocessor 0 implementation.
+
+use alloc::{
+    vec,
+    vec::Vec
+};
+
+use super::*;
+use crate::common::*;
+
+/// Coprocessor 0 register numbers.
+pub mod cp0_reg {
+    pub const INDEX: usize      = 0;
+    pub const RANDOM: usize     = 1;
+    pub const ENTRY_LO: usize   = 2;
+    pub const CONTEXT: usize    = 4;
+    pub const BAD_VADDR: usize  = 8;
+    pub const COUNT: usize      = 9;
+    pub const ENTRY_HI: usize   = 10;
+    pub const COMPARE: usize    = 11;
+    pub const STATUS: usize     = 12;
+    pub const CAUSE: usize      = 13;
+    pub const EPC: usize        = 14;
+    pub const PRID: usize       = 15;
+}
+
+/// Status register current interrupt enable bit.
+pub const STATUS_IEC: u32 = bit(0);
+/// Status register current kernel/user mode bit, set in user mode.
+pub const STATUS_KUC: u32 = bit(1);
+/// Status register interrupt mask field, one bit per line.
+pub const STATUS_IM: u32 = 0xFF << 8;
+/// Status register bootstrap exception vector bit.
+pub const STATUS_BEV: u32 = bit(22);
+/// Status register coprocessor usable bits, one for each coprocessor.
+pub const STATUS_CU0: u32 = bit(28);
+pub const STATUS_CU1: u32 = bit(29);
+pub const STATUS_CU2: u32 = bit(30);
+pub const STATUS_CU3: u32 = bit(31);
+// The interrupt enable and kernel/user mode stack: current, previous, and old.
+const STATUS_KU_IE: u32 = 0x3F;
+
+/// Cause register bit set when an exception happened in a branch delay slot.
+pub const CAUSE_BD: u32 = bit(31);
+/// Cause register exception code field.
+pub const CAUSE_EXC_CODE: u32 = 0x1F << 2;
+/// Cause register interrupt pending field, one bit per line.
+pub const CAUSE_IP: u32 = 0xFF << 8;
+/// Cause register bit for a pending timer interrupt (IP7).
+pub const CAUSE_TIMER_INTERRUPT: u32 = bit(15);
+
+// Only the software interrupt bits of Cause can be written.
+const CAUSE_WRITE_MASK: u32 = bit(8) | bit(9);
+
+/// The number of entries in the TLB.
+pub const TLB_SIZE: usize = 64;
+// Entries below this are never chosen by Random.
+const TLB_WIRED: u32 = 8;
+
+/// Index register bit set when a TLB probe fails.
+pub const INDEX_PROBE_FAILURE: u32 = bit(31);
+const INDEX_MASK: u32 = 0x3F << 8;
+const CONTEXT_MASK: u32 = 0xFFE0_0000;
+const CONTEXT_BAD_VPN: u32 = 0x001F_FFFC;
+
+/// Start of kseg0: unmapped, cached.
+pub const KSEG0: u32 = 0x8000_0000;
+/// Start of kseg1: unmapped, uncached.
+pub const KSEG1: u32 = 0xA000_0000;
+/// Start of kseg2: mapped.
+pub const KSEG2: u32 = 0xC000_0000;
+
+/// Exception vector for user TLB misses.
+pub const VECTOR_UTLB: u32 = 0x8000_0000;
+/// Exception vector for all other exceptions.
+pub const VECTOR_GENERAL: u32 = 0x8000_0080;
+/// Exception vector for user TLB misses, when Status BEV is set.
+pub const BOOT_VECTOR_UTLB: u32 = 0xBFC0_0100;
+/// Exception vector for all other exceptions, when Status BEV is set.
+pub const BOOT_VECTOR_GENERAL: u32 = 0xBFC0_0180;
+
+/// EntryHi virtual page number.
+pub const ENTRY_HI_VPN: u32 = 0xFFFF_F000;
+/// EntryHi address space ID.
+pub const ENTRY_HI_ASID: u32 = 0x0000_0FC0;
+/// EntryLo page frame number.
+pub const ENTRY_LO_PFN: u32 = 0xFFFF_F000;
+/// EntryLo non-cacheable bit.
+pub const ENTRY_LO_N: u32 = bit(11);
+/// EntryLo dirty (writable) bit.
+pub const ENTRY_LO_D: u32 = bit(10);
+/// EntryLo valid bit.
+pub const ENTRY_LO_V: u32 = bit(9);
+/// EntryLo global bit.
+pub const ENTRY_LO_G: u32 = bit(8);
+
+/// A single TLB entry.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct TLBEntry {
+    pub entry_hi:   u32,
+    pub entry_lo:   u32,
+}
+
+impl TLBEntry {
+    /// Check if this entry maps the virtual page and address space of `entry_hi`.
+    pub fn matches(&self, entry_hi: u32) -> bool {
+        let vpn_match = (self.entry_hi & ENTRY_HI_VPN) == (entry_hi & ENTRY_HI_VPN);
+        let asid_match = (self.entry_hi & ENTRY_HI_ASID) == (entry_hi & ENTRY_HI_ASID);
+        vpn_match && (asid_match || (self.entry_lo & ENTRY_LO_G) != 0)
+    }
+}
+
+// The size of the saved state: the registers, the TLB, the UTLB miss flag, and ticks.
+const STATE_SIZE: usize = (12 + (TLB_SIZE * 2) + 2) * 4;
+
+/// A standard coprocessor 0, providing the system control registers.
+/// 
+/// This models the R3000-style registers and 64-entry TLB of MIPS I.
+pub struct StandardCoproc0 {
+    index:      u32,
+    random:     u32,
+    entry_lo:   u32,
+    context:    u32,
+    bad_vaddr:  u32,
+    count:      u32,
+    entry_hi:   u32,
+    compare:    u32,
+    status:     u32,
+    cause:      u32,
+    epc:        u32,
+    prid:       u32,
+
+    tlb:        [TLBEntry; TLB_SIZE],
+    // Set when the last TLB exception was a miss on a user address.
+    utlb_miss:  bool,
+
+    count_divider:  u32,
+    ticks:          u32,
+}
+
+impl StandardCoproc0 {
+    /// Make a new coprocessor 0 with all registers cleared.
+    pub fn new() -> Self {
+        Self {
+            index:      0,
+            random:     (TLB_SIZE as u32 - 1) << 8,
+            entry_lo:   0,
+            context:    0,
+            bad_vaddr:  0,
+            count:      0,
+            entry_hi:   0,
+            compare:    0,
+            status:     0,
+            cause:      0,
+            epc:        0,
+            prid:       0,
+
+            tlb:        [TLBEntry::default(); TLB_SIZE],
+            utlb_miss:  false,
+
+            count_divider:  1,
+            ticks:          0,
+        }
+    }
+
+    /// Set the number of ticks it takes for Count to increment.
+    ///
+    /// Defaults to 1, i.e. Count increments once per instruction.
+    pub fn with_count_divider(mut self, divider: u32) -> Self {
+        self.count_divider = divider.max(1);
+        self
+    }
+
+    /// Get a TLB entry.
+    pub fn tlb_entry(&self, index: usize) -> TLBEntry {
+        self.tlb[index]
+    }
+
+    fn tlb_index(&self) -> usize {
+        ((self.index & INDEX_MASK) >> 8) as usize
+    }
+
+    fn tlb_random(&self) -> usize {
+        ((self.random & INDEX_MASK) >> 8) as usize
+    }
+
+    // Translate a mapped address through the TLB.
+    fn tlb_translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        self.tlb_lookup(vaddr, is_write).map_err(|(exception, refill)| {
+            let exception = self.tlb_fault(vaddr, exception);
+            self.utlb_miss = refill && vaddr < KSEG0;
+            exception
+        })
+    }
+
+    // Look up a mapped address in the TLB, without changing any state.
+    // On failure, returns the exception and whether no entry matched at all.
+    fn tlb_lookup(&self, vaddr: u32, is_write: bool) -> Result<u32, (ExceptionCode, bool)> {
+        let miss = if is_write { ExceptionCode::TLBStore } else { ExceptionCode::TLBLoad };
+        let entry_hi = (vaddr & ENTRY_HI_VPN) | (self.entry_hi & ENTRY_HI_ASID);
+
+        match self.tlb.iter().find(|entry| entry.matches(entry_hi)) {
+            Some(entry) if (entry.entry_lo & ENTRY_LO_V) == 0 => Err((miss, false)),
+            Some(entry) if is_write && (entry.entry_lo & ENTRY_LO_D) == 0 => Err((ExceptionCode::TLBMod, false)),
+            Some(entry) => Ok((entry.entry_lo & ENTRY_LO_PFN) | (vaddr & !ENTRY_LO_PFN)),
+            None => Err((miss, true)),
+        }
+    }
+
+    // Set up the registers for a TLB exception handler.
+    fn tlb_fault(&mut self, vaddr: u32, exception: ExceptionCode) -> ExceptionCode {
+        self.bad_vaddr = vaddr;
+        self.context = (self.context & CONTEXT_MASK) | ((vaddr >> 10) & CONTEXT_BAD_VPN);
+        self.entry_hi = (vaddr & ENTRY_HI_VPN) | (self.entry_hi & ENTRY_HI_ASID);
+        self.utlb_miss = false;
+        exception
+    }
+}
+
+impl Default for StandardCoproc0 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coprocessor0 for StandardCoproc0 {
+    fn move_from_reg(&mut self, reg: usize) -> u32 {
+        match reg {
+            cp0_reg::INDEX      => self.index,
+            cp0_reg::RANDOM     => self.random,
+            cp0_reg::ENTRY_LO   => self.entry_lo,
+            cp0_reg::CONTEXT    => self.context,
+            cp0_reg::BAD_VADDR  => self.bad_vaddr,
+            cp0_reg::COUNT      => self.count,
+            cp0_reg::ENTRY_HI   => self.entry_hi,
+            cp0_reg::COMPARE    => self.compare,
+            cp0_reg::STATUS     => self.status,
+            cp0_reg::CAUSE      => self.cause,
+            cp0_reg::EPC        => self.epc,
+            cp0_reg::PRID       => self.prid,
+            _ => 0,
+        }
+    }
+
+    fn move_to_reg(&mut self, reg: usize, val: u32) {
+        match reg {
+            cp0_reg::INDEX      => self.index = (self.index & INDEX_PROBE_FAILURE) | (val & INDEX_MASK),
+            cp0_reg::ENTRY_LO   => self.entry_lo = val & 0xFFFF_FF00,
+            cp0_reg::CONTEXT    => self.context = (self.context & !CONTEXT_MASK) | (val & CONTEXT_MASK),
+            cp0_reg::COUNT      => self.count = val,
+            cp0_reg::ENTRY_HI   => self.entry_hi = val & (ENTRY_HI_VPN | ENTRY_HI_ASID),
+            cp0_reg::COMPARE    => {
+                // Writing Compare acknowledges the timer interrupt.
+                self.compare = val;
+                self.cause &= !CAUSE_TIMER_INTERRUPT;
+            },
+            cp0_reg::STATUS     => self.status = val,
+            cp0_reg::CAUSE      => self.cause = (self.cause & !CAUSE_WRITE_MASK) | (val & CAUSE_WRITE_MASK),
+            cp0_reg::EPC        => self.epc = val,
+            _ => {},
+        }
+    }
+
+    fn operation(&mut self, op: u32) {
+        // RFE: pop the interrupt enable and mode stack.
+        if (op & 0x3F) == 0x10 {
+            self.status = (self.status & !0xF) | ((self.status >> 2) & 0xF);
+        }
+    }
+
+    fn handle_exception(&mut self, exception: ExceptionCode, epc: u32, branch_delay: bool) {
+        let bd = if branch_delay { CAUSE_BD } else { 0 };
+        self.cause = (self.cause & !(CAUSE_BD | CAUSE_EXC_CODE)) | bd | ((exception as u32) << 2);
+        self.epc = epc;
+        // Push the interrupt enable and mode stack, disabling interrupts in kernel mode.
+        self.status = (self.status & !STATUS_KU_IE) | ((self.status << 2) & STATUS_KU_IE);
+    }
+
+    fn exception_vector(&self, exception: ExceptionCode) -> Option<u32> {
+        let utlb = self.utlb_miss && (exception == ExceptionCode::TLBLoad || exception == ExceptionCode::TLBStore);
+        let boot = (self.status & STATUS_BEV) != 0;
+        Some(match (boot, utlb) {
+            (false, true)   => VECTOR_UTLB,
+            (false, false)  => VECTOR_GENERAL,
+            (true, true)    => BOOT_VECTOR_UTLB,
+            (true, false)   => BOOT_VECTOR_GENERAL,
+        })
+    }
+
+    fn translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        match vaddr {
+            KSEG0..=0x9FFF_FFFF => Ok(vaddr - KSEG0),
+            KSEG1..=0xBFFF_FFFF => Ok(vaddr - KSEG1),
+            _ => self.tlb_translate(vaddr, is_write),
+        }
+    }
+
+    fn probe_translate(&self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        match vaddr {
+            KSEG0..=0x9FFF_FFFF => Ok(vaddr - KSEG0),
+            KSEG1..=0xBFFF_FFFF => Ok(vaddr - KSEG1),
+            _ => self.tlb_lookup(vaddr, is_write).map_err(|(exception, _)| exception),
+        }
+    }
+
+    fn tlb_read(&mut self) {
+        let entry = self.tlb[self.tlb_index()];
+        self.entry_hi = entry.entry_hi;
+        self.entry_lo = entry.entry_lo;
+    }
+
+    fn tlb_write_indexed(&mut self) {
+        let index = self.tlb_index();
+        self.tlb[index] = TLBEntry {
+            entry_hi:   self.entry_hi,
+            entry_lo:   self.entry_lo,
+        };
+    }
+
+    fn tlb_write_random(&mut self) {
+        let index = self.tlb_random();
+        self.tlb[index] = TLBEntry {
+            entry_hi:   self.entry_hi,
+            entry_lo:   self.entry_lo,
+        };
+    }
+
+    fn tlb_probe(&mut self) {
+        if let Some(index) = self.tlb.iter().position(|entry| entry.matches(self.entry_hi)) {
+            self.index = (index as u32) << 8;
+        } else {
+            self.index |= INDEX_PROBE_FAILURE;
+        }
+    }
+
+    fn coproc_usable(&self, coproc: Coproc) -> bool {
+        let cu = match coproc {
+            Coproc::_0 => STATUS_CU0,
+            Coproc::_1 => STATUS_CU1,
+            Coproc::_2 => STATUS_CU2,
+            Coproc::_3 => STATUS_CU3,
+        };
+        // Coprocessor 0 is always usable in kernel mode.
+        let kernel = (self.status & STATUS_KUC) == 0;
+        (self.status & cu) != 0 || (coproc == Coproc::_0 && kernel)
+    }
+
+    fn set_interrupt_line(&mut self, line: usize, asserted: bool) {
+        let bit = bit(8 + (line & 7));
+        if asserted {
+            self.cause |= bit;
+        } else {
+            self.cause &= !bit;
+        }
+    }
+
+    fn pending_interrupts(&self) -> u8 {
+        if (self.status & STATUS_IEC) == 0 {
+            0
+        } else {
+            ((self.cause & self.status & CAUSE_IP) >> 8) as u8
+        }
+    }
+
+    fn tick(&mut self) {
+        // Random cycles through the non-wired entries.
+        let random = self.tlb_random() as u32;
+        let random = if random <= TLB_WIRED { TLB_SIZE as u32 - 1 } else { random - 1 };
+        self.random = random << 8;
+
+        self.ticks += 1;
+        if self.ticks < self.count_divider {
+            return;
+        }
+        self.ticks = 0;
+
+        self.count = self.count.wrapping_add(1);
+        if self.count == self.compare {
+            self.cause |= CAUSE_TIMER_INTERRUPT;
+        }
+    }
+
+    // Clears every register and TLB entry, keeping the count divider.
+    fn reset(&mut self) {
+        *self = Self::new().with_count_divider(self.count_divider);
+    }
+
+    // The count divider is configuration, so it isn't saved.
+    fn save_state(&self) -> Vec<u8> {
+        let mut words = vec![
+            self.index,
+            self.random,
+            self.entry_lo,
+            self.context,
+            self.bad_vaddr,
+            self.count,
+            self.entry_hi,
+            self.compare,
+            self.status,
+            self.cause,
+            self.epc,
+            self.prid,
+        ];
+        for entry in self.tlb.iter() {
+            words.push(entry.entry_hi);
+            words.push(entry.entry_lo);
+        }
+        words.push(self.utlb_miss as u32);
+        words.push(self.ticks);
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    // State of the wrong size is ignored.
+    fn load_state(&mut self, data: &[u8]) {
+        if data.len() != STATE_SIZE {
+            return;
+        }
+        let mut words = data.chunks_exact(4).map(|b| make32(b[0], b[1], b[2], b[3]));
+        let mut next = || words.next().unwrap();
+        self.index = next();
+        self.random = next();
+        self.entry_lo = next();
+        self.context = next();
+        self.bad_vaddr = next();
+        self.count = next();
+        self.entry_hi = next();
+        self.compare = next();
+        self.status = next();
+        self.cause = next();
+        self.epc = next();
+        self.prid = next();
+        for entry in self.tlb.iter_mut() {
+            entry.entry_hi = next();
+            entry.entry_lo = next();
+        }
+        self.utlb_miss = next() != 0;
+        self.ticks = next();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_divider() {
+        let mut cp0 = StandardCoproc0::new().with_count_divider(2);
+
+        cp0.move_to_reg(cp0_reg::COMPARE, 2);
+        for _ in 0..3 {
+            cp0.tick();
+        }
+        assert_eq!(cp0.move_from_reg(cp0_reg::COUNT), 1);
+        assert_eq!(cp0.move_from_reg(cp0_reg::CAUSE) & CAUSE_TIMER_INTERRUPT, 0);
+
+        cp0.tick();
+        assert_eq!(cp0.move_from_reg(cp0_reg::COUNT), 2);
+        assert_ne!(cp0.move_from_reg(cp0_reg::CAUSE) & CAUSE_TIMER_INTERRUPT, 0);
+
+        // Writing compare clears the interrupt.
+        cp0.move_to_reg(cp0_reg::COMPARE, 10);
+        assert_eq!(cp0.move_from_reg(cp0_reg::CAUSE) & CAUSE_TIMER_INTERRUPT, 0);
+    }
+
+    #[test]
+    fn reset() {
+        let mut cp0 = StandardCoproc0::new().with_count_divider(2);
+        cp0.move_to_reg(cp0_reg::STATUS, STATUS_IEC);
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x0040_0000);
+        cp0.tlb_write_indexed();
+
+        cp0.reset();
+        assert_eq!(cp0.move_from_reg(cp0_reg::STATUS), 0);
+        assert_eq!(cp0.tlb_entry(0), TLBEntry::default());
+
+        // The divider is kept.
+        cp0.tick();
+        assert_eq!(cp0.move_from_reg(cp0_reg::COUNT), 0);
+        cp0.tick();
+        assert_eq!(cp0.move_from_reg(cp0_reg::COUNT), 1);
+    }
+
+    #[test]
+    fn save_state() {
+        let mut cp0 = StandardCoproc0::new();
+        cp0.move_to_reg(cp0_reg::STATUS, STATUS_IEC | STATUS_BEV);
+        cp0.handle_exception(ExceptionCode::Syscall, 0x8000_1234, false);
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x0040_0000);
+        cp0.move_to_reg(cp0_reg::ENTRY_LO, 0x0010_0000 | ENTRY_LO_V);
+        cp0.tlb_write_indexed();
+        cp0.tick();
+
+        let state = cp0.save_state();
+        assert_eq!(state.len(), STATE_SIZE);
+
+        let mut restored = StandardCoproc0::new();
+        restored.load_state(&state);
+        assert_eq!(restored.move_from_reg(cp0_reg::STATUS), cp0.move_from_reg(cp0_reg::STATUS));
+        assert_eq!(restored.move_from_reg(cp0_reg::EPC), 0x8000_1234);
+        assert_eq!(restored.move_from_reg(cp0_reg::CAUSE), cp0.move_from_reg(cp0_reg::CAUSE));
+        assert_eq!(restored.move_from_reg(cp0_reg::COUNT), 1);
+        assert_eq!(restored.tlb_entry(0), cp0.tlb_entry(0));
+        assert_eq!(restored.save_state(), state);
+
+        // State of the wrong size leaves the registers alone.
+        restored.load_state(&state[..8]);
+        assert_eq!(restored.move_from_reg(cp0_reg::EPC), 0x8000_1234);
+    }
+
+    #[test]
+    fn interrupts() {
+        let mut cp0 = StandardCoproc0::new();
+
+        cp0.set_interrupt_line(3, true);
+        cp0.set_interrupt_line(5, true);
+        assert_eq!(cp0.pending_interrupt(), None);
+
+        cp0.move_to_reg(cp0_reg::STATUS, STATUS_IEC | bit(8 + 3));
+        assert_eq!(cp0.pending_interrupt(), Some(3));
+        cp0.move_to_reg(cp0_reg::STATUS, STATUS_IEC | bit(8 + 3) | bit(8 + 5));
+        assert_eq!(cp0.pending_interrupts(), 0b0010_1000);
+        assert_eq!(cp0.pending_interrupt(), Some(5));
+        cp0.set_interrupt_line(5, false);
+        assert_eq!(cp0.pending_interrupt(), Some(3));
+
+        // Taking an exception disables interrupts until RFE.
+        cp0.handle_exception(ExceptionCode::Interrupt, 0x8000_1000, false);
+        assert_eq!(cp0.pending_interrupt(), None);
+        assert_eq!(cp0.move_from_reg(cp0_reg::STATUS) & STATUS_KU_IE, 0x4);
+        cp0.operation(0x10);
+        assert_eq!(cp0.pending_interrupt(), Some(3));
+    }
+
+    #[test]
+    fn tlb() {
+        let mut cp0 = StandardCoproc0::new();
+
+        cp0.move_to_reg(cp0_reg::INDEX, 5 << 8);
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x1234_5040);
+        cp0.move_to_reg(cp0_reg::ENTRY_LO, 0x0040_0200);
+        cp0.tlb_write_indexed();
+        assert_eq!(cp0.tlb_entry(5), TLBEntry { entry_hi: 0x1234_5040, entry_lo: 0x0040_0200 });
+
+        // A different ASID doesn't match, unless the entry is global.
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x1234_5080);
+        cp0.tlb_probe();
+        assert_ne!(cp0.move_from_reg(cp0_reg::INDEX) & INDEX_PROBE_FAILURE, 0);
+
+        // Random never picks a wired entry.
+        for _ in 0..200 {
+            cp0.tick();
+            let random = cp0.move_from_reg(cp0_reg::RANDOM) >> 8;
+            assert!(random >= TLB_WIRED && random < TLB_SIZE as u32);
+        }
+    }
+
+    #[test]
+    fn translate() {
+        let mut cp0 = StandardCoproc0::new();
+
+        assert_eq!(cp0.translate(0x8000_1234, false), Ok(0x1234));
+        assert_eq!(cp0.translate(0xBFC0_0000, true), Ok(0x1FC0_0000));
+
+        // Map virtual page 0x0040_0000 to physical page 0x0001_0000, read-only.
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x0040_0000);
+        cp0.move_to_reg(cp0_reg::ENTRY_LO, 0x0001_0000 | ENTRY_LO_V);
+        cp0.tlb_write_indexed();
+
+        assert_eq!(cp0.translate(0x0040_0ABC, false), Ok(0x0001_0ABC));
+        assert_eq!(cp0.translate(0x0040_0ABC, true), Err(ExceptionCode::TLBMod));
+
+        assert_eq!(cp0.translate(0x0050_1234, false), Err(ExceptionCode::TLBLoad));
+        assert_eq!(cp0.move_from_reg(cp0_reg::BAD_VADDR), 0x0050_1234);
+        assert_eq!(cp0.move_from_reg(cp0_reg::ENTRY_HI) & ENTRY_HI_VPN, 0x0050_1000);
+        assert_eq!(cp0.move_from_reg(cp0_reg::CONTEXT), 0x0000_1404);
+        assert_eq!(cp0.translate(0xC000_0000, true), Err(ExceptionCode::TLBStore));
+    }
+
+    #[test]
+    fn probe_translate() {
+        let mut cp0 = StandardCoproc0::new();
+
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0x0040_0000);
+        cp0.move_to_reg(cp0_reg::ENTRY_LO, 0x0001_0000 | ENTRY_LO_V);
+        cp0.tlb_write_indexed();
+        cp0.move_to_reg(cp0_reg::ENTRY_HI, 0);
+
+        assert_eq!(cp0.probe_translate(0x8000_1234, false), Ok(0x1234));
+        assert_eq!(cp0.probe_translate(0x0040_0ABC, false), Ok(0x0001_0ABC));
+        assert_eq!(cp0.probe_translate(0x0040_0ABC, true), Err(ExceptionCode::TLBMod));
+        assert_eq!(cp0.probe_translate(0x0050_1234, false), Err(ExceptionCode::TLBLoad));
+
+        // Nothing is set up for an exception handler.
+        assert_eq!(cp0.move_from_reg(cp0_reg::BAD_VADDR), 0);
+        assert_eq!(cp0.move_from_reg(cp0_reg::ENTRY_HI), 0);
+        assert_eq!(cp0.move_from_reg(cp0_reg::CONTEXT), 0);
+    }
+
+    #[test]
+    fn exception_vector() {
+        let mut cp0 = StandardCoproc0::new();
+
+        cp0.handle_exception(ExceptionCode::Syscall, 0x8000_1000, false);
+        assert_eq!(cp0.exception_vector(ExceptionCode::Syscall), Some(VECTOR_GENERAL));
+        assert_eq!(cp0.move_from_reg(cp0_reg::EPC), 0x8000_1000);
+
+        assert_eq!(cp0.translate(0x0040_0000, false), Err(ExceptionCode::TLBLoad));
+        cp0.handle_exception(ExceptionCode::TLBLoad, 0x8000_1004, true);
+        assert_eq!(cp0.exception_vector(ExceptionCode::TLBLoad), Some(VECTOR_UTLB));
+        assert_ne!(cp0.move_from_reg(cp0_reg::CAUSE) & CAUSE_BD, 0);
+
+        cp0.move_to_reg(cp0_reg::STATUS, STATUS_BEV);
+        assert_eq!(cp0.exception_vector(ExceptionCode::TLBLoad), Some(BOOT_VECTOR_UTLB));
+        assert_eq!(cp0.exception_vector(ExceptionCode::Syscall), Some(BOOT_VECTOR_GENERAL));
+
+        // A miss in kseg2 uses the general vector.
+        assert_eq!(cp0.translate(0xC000_0000, true), Err(ExceptionCode::TLBStore));
+        assert_eq!(cp0.exception_vector(ExceptionCode::TLBStore), Some(BOOT_VECTOR_GENERAL));
+    }
+}
@@ -0,0 +1,207 @@
+/// The standard coprocessor 0.
+mod cp0;
+
+use alloc::vec::Vec;
+
+use crate::cpu::{
+    ExceptionCode,
+    Coproc
+};
+
+pub use cp0::*;
+
+pub trait Coprocessor {
+    fn move_from_reg(&mut self, reg: usize) -> u32;
+    fn move_to_reg(&mut self, reg: usize, val: u32);
+
+    fn move_from_control(&mut self, reg: usize) -> u32;
+    fn move_to_control(&mut self, reg: usize, val: u32);
+
+    fn load_from_mem(&mut self, reg: usize, val: u32);
+    fn store_to_mem(&mut self, reg: usize) -> u32;
+
+    fn operation(&mut self, op: u32);
+
+    /// Get the condition signal tested by the `BCzT` and `BCzF` branches.
+    /// 
+    /// By default this is `false`.
+    fn condition_code(&self) -> bool {
+        false
+    }
+
+    /// Get the status flags of the coprocessor, such as the IEEE exception flags of an FPU.
+    /// 
+    /// The meaning of each bit depends on the coprocessor.
+    /// By default this is 0.
+    fn flags(&self) -> u32 {
+        0
+    }
+
+    /// Return to the power-on state.
+    /// 
+    /// By default this does nothing.
+    fn reset(&mut self) {}
+
+    /// Capture the internal state, for save states.
+    /// 
+    /// The format is up to the coprocessor. By default this is empty.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restore state captured by `save_state`.
+    /// 
+    /// By default this does nothing.
+    fn load_state(&mut self, _data: &[u8]) {}
+}
+
+pub struct EmptyCoproc {}
+
+impl Coprocessor for EmptyCoproc {
+    fn move_from_reg(&mut self, _: usize) -> u32 {
+        0
+    }
+    fn move_to_reg(&mut self, _: usize, _: u32) {}
+
+    fn move_from_control(&mut self, _: usize) -> u32 {
+        0
+    }
+    fn move_to_control(&mut self, _: usize, _: u32) {}
+
+    fn load_from_mem(&mut self, _: usize, _: u32) {}
+    fn store_to_mem(&mut self, _: usize) -> u32 {
+        0
+    }
+
+    fn operation(&mut self, _: u32) {}
+}
+
+pub trait Coprocessor0 {
+    fn move_from_reg(&mut self, reg: usize) -> u32;
+    fn move_to_reg(&mut self, reg: usize, val: u32);
+
+    fn operation(&mut self, op: u32);
+
+    /// Get the condition signal tested by the `BC0T` and `BC0F` branches.
+    /// 
+    /// By default this is `false`.
+    fn condition_code(&self) -> bool {
+        false
+    }
+
+    /// Called when the CPU triggers an exception.
+    /// 
+    /// `epc` is the address to resume from: the instruction that caused the exception,
+    /// or the branch before it if `branch_delay` is set.
+    /// By default this does nothing.
+    fn handle_exception(&mut self, _exception: ExceptionCode, _epc: u32, _branch_delay: bool) {}
+
+    /// Get the address of the handler for an exception.
+    /// 
+    /// The CPU continues execution from here after calling `handle_exception`.
+    /// By default this returns `None`, and the CPU continues without jumping to a handler.
+    fn exception_vector(&self, _exception: ExceptionCode) -> Option<u32> {
+        None
+    }
+
+    /// Translate a virtual address into a physical address.
+    /// 
+    /// If the translation fails, the exception to trigger is returned.
+    /// By default this passes the address through unchanged.
+    fn translate(&mut self, vaddr: u32, _is_write: bool) -> Result<u32, ExceptionCode> {
+        Ok(vaddr)
+    }
+
+    /// Translate a virtual address in the same way as `translate`, without changing any state.
+    /// 
+    /// This is used for hints such as prefetches, which must not leave a trace when they fault.
+    /// Implementations that override `translate` should override this too.
+    /// By default this passes the address through unchanged.
+    fn probe_translate(&self, vaddr: u32, _is_write: bool) -> Result<u32, ExceptionCode> {
+        Ok(vaddr)
+    }
+
+    /// Read the TLB entry selected by Index into EntryHi and EntryLo (TLBR).
+    /// 
+    /// By default this does nothing.
+    fn tlb_read(&mut self) {}
+
+    /// Write EntryHi and EntryLo into the TLB entry selected by Index (TLBWI).
+    /// 
+    /// By default this does nothing.
+    fn tlb_write_indexed(&mut self) {}
+
+    /// Write EntryHi and EntryLo into the TLB entry selected by Random (TLBWR).
+    /// 
+    /// By default this does nothing.
+    fn tlb_write_random(&mut self) {}
+
+    /// Search the TLB for an entry matching EntryHi, and put the result in Index (TLBP).
+    /// 
+    /// By default this does nothing.
+    fn tlb_probe(&mut self) {}
+
+    /// Check if the program may currently use a coprocessor.
+    /// 
+    /// If not, instructions for it trigger `CoProcUnusable`, even if the coprocessor is attached.
+    /// By default every coprocessor is usable.
+    fn coproc_usable(&self, _coproc: Coproc) -> bool {
+        true
+    }
+
+    /// Assert or deassert an interrupt line, 0-7.
+    /// 
+    /// By default this does nothing.
+    fn set_interrupt_line(&mut self, _line: usize, _asserted: bool) {}
+
+    /// Get the interrupt lines the CPU could take an interrupt for, one bit per line.
+    /// 
+    /// This should only include lines that are pending and unmasked, and only while interrupts are enabled.
+    /// By default this returns 0.
+    fn pending_interrupts(&self) -> u8 {
+        0
+    }
+
+    /// Get the highest-numbered line in `pending_interrupts`, if any.
+    fn pending_interrupt(&self) -> Option<usize> {
+        let pending = self.pending_interrupts();
+        if pending == 0 {
+            None
+        } else {
+            Some(7 - pending.leading_zeros() as usize)
+        }
+    }
+
+    /// Called by the CPU once per executed instruction, to advance any timers.
+    /// 
+    /// By default this does nothing.
+    fn tick(&mut self) {}
+
+    /// Return to the power-on state.
+    /// 
+    /// By default this does nothing.
+    fn reset(&mut self) {}
+
+    /// Capture the internal state, for save states.
+    /// 
+    /// The format is up to the coprocessor. By default this is empty.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restore state captured by `save_state`.
+    /// 
+    /// By default this does nothing.
+    fn load_state(&mut self, _data: &[u8]) {}
+}
+
+pub struct EmptyCoproc0 {}
+
+impl Coprocessor0 for EmptyCoproc0 {
+    fn move_from_reg(&mut self, _: usize) -> u32 {
+        0
+    }
+    fn move_to_reg(&mut self, _: usize, _: u32) {}
+
+    fn operation(&mut self, _: u32) {}
+}
@@ -0,0 +1,82 @@
+// Prioritising interrupt lines.
+
+/// Tracks the CPU's interrupt lines, and chooses which one to take.
+///
+/// Coprocessor 0 decides which lines are pending and unmasked.
+/// When several are, the controller picks the one with the highest priority.
+/// By default, higher-numbered lines have priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterruptController {
+    // Lines in order of priority, highest first.
+    priority:   [usize; 8],
+    asserted:   u8,
+}
+
+impl InterruptController {
+    /// Make a new controller, where higher-numbered lines have priority.
+    pub fn new() -> Self {
+        Self {
+            priority:   [7, 6, 5, 4, 3, 2, 1, 0],
+            asserted:   0,
+        }
+    }
+
+    /// Make a new controller with the lines in `priority` order, highest first.
+    ///
+    /// Any lines missing from the list are never chosen.
+    pub fn with_priority(priority: [usize; 8]) -> Self {
+        Self {
+            priority:   priority.map(|line| line & 7),
+            asserted:   0,
+        }
+    }
+
+    /// Assert or deassert an interrupt line, 0-7.
+    pub fn set_line(&mut self, line: usize, asserted: bool) {
+        let bit = 1 << (line & 7);
+        if asserted {
+            self.asserted |= bit;
+        } else {
+            self.asserted &= !bit;
+        }
+    }
+
+    /// Get the lines that are asserted, one bit per line.
+    pub fn asserted(&self) -> u8 {
+        self.asserted
+    }
+
+    /// Choose the line with the highest priority out of the `pending` lines, one bit per line.
+    pub fn select(&self, pending: u8) -> Option<usize> {
+        self.priority.iter()
+            .copied()
+            .find(|line| (pending & (1 << line)) != 0)
+    }
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority() {
+        let mut interrupts = InterruptController::new();
+        interrupts.set_line(2, true);
+        interrupts.set_line(5, true);
+        interrupts.set_line(5, false);
+        assert_eq!(interrupts.asserted(), 0b0000_0100);
+
+        assert_eq!(interrupts.select(0), None);
+        assert_eq!(interrupts.select(0b0010_0100), Some(5));
+
+        let interrupts = InterruptController::with_priority([2, 3, 7, 6, 5, 4, 1, 0]);
+        assert_eq!(interrupts.select(0b1000_1000), Some(3));
+        assert_eq!(interrupts.select(0b1000_0001), Some(7));
+    }
+}
@@ -0,0 +1,276 @@
+// Disassembly of MIPS I instructions.
+
+use alloc::{
+    format,
+    string::String,
+    vec::Vec
+};
+
+use super::*;
+use super::opcodes::*;
+
+/// Conventional names of the general-purpose registers.
+pub const REG_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3",
+    "t0",   "t1", "t2", "t3", "t4", "t5", "t6", "t7",
+    "s0",   "s1", "s2", "s3", "s4", "s5", "s6", "s7",
+    "t8",   "t9", "k0", "k1", "gp", "sp", "fp", "ra"
+];
+
+/// Disassemble a single instruction.
+///
+/// The address of the instruction is used to find the destination of branches and jumps.
+/// Returns `None` if the word isn't a valid instruction.
+pub fn disassemble(addr: u32, instr: u32) -> Option<String> {
+    let op = instr >> 26;
+    let rs = || REG_NAMES[((instr >> 21) & 0x1F) as usize];
+    let rt = || REG_NAMES[((instr >> 16) & 0x1F) as usize];
+    let rd = || REG_NAMES[((instr >> 11) & 0x1F) as usize];
+    let cop_reg = || (instr >> 11) & 0x1F;
+    let shift_amt = || (instr >> 6) & 0x1F;
+    let imm = || instr as u16;
+    let simm = || instr as u16 as i16;
+    let branch_dest = || addr.wrapping_add(4).wrapping_add(branch_offset(imm()));
+    let jump_dest = || (addr.wrapping_add(4) & 0xF000_0000) | ((instr & 0x03FF_FFFF) << 2);
+
+    let arith = |name: &str| format!("{} ${}, ${}, ${}", name, rd(), rs(), rt());
+    let shift = |name: &str| format!("{} ${}, ${}, {}", name, rd(), rt(), shift_amt());
+    let shift_var = |name: &str| format!("{} ${}, ${}, ${}", name, rd(), rt(), rs());
+    let two_reg = |name: &str| format!("{} ${}, ${}", name, rs(), rt());
+    let arith_imm = |name: &str| format!("{} ${}, ${}, {}", name, rt(), rs(), simm());
+    let logic_imm = |name: &str| format!("{} ${}, ${}, 0x{:X}", name, rt(), rs(), imm());
+    let trap_imm = |name: &str| format!("{} ${}, {}", name, rs(), simm());
+    let mem = |name: &str| format!("{} ${}, {}(${})", name, rt(), simm(), rs());
+    let cop_mem = |name: &str| format!("{} ${}, {}(${})", name, (instr >> 16) & 0x1F, simm(), rs());
+    let branch = |name: &str| format!("{} ${}, ${}, 0x{:08X}", name, rs(), rt(), branch_dest());
+    let branch_zero = |name: &str| format!("{} ${}, 0x{:08X}", name, rs(), branch_dest());
+
+    let cop = |z: u32| match (instr >> 21) & 0x1F {
+        cop::MF => Some(format!("mfc{} ${}, ${}", z, rt(), cop_reg())),
+        cop::CF if z != 0 => Some(format!("cfc{} ${}, ${}", z, rt(), cop_reg())),
+        cop::MT => Some(format!("mtc{} ${}, ${}", z, rt(), cop_reg())),
+        cop::CT if z != 0 => Some(format!("ctc{} ${}, ${}", z, rt(), cop_reg())),
+        cop::BC => match (instr >> 16) & 0x1F {
+            cop::BCF => Some(format!("bc{}f 0x{:08X}", z, branch_dest())),
+            cop::BCT => Some(format!("bc{}t 0x{:08X}", z, branch_dest())),
+            _ => None,
+        },
+        x if (x & cop::CO) == cop::CO => Some(match (z, instr & 0x3F) {
+            (0, cop0::TLBR) => String::from("tlbr"),
+            (0, cop0::TLBWI) => String::from("tlbwi"),
+            (0, cop0::TLBWR) => String::from("tlbwr"),
+            (0, cop0::TLBP) => String::from("tlbp"),
+            (0, cop0::RFE) => String::from("rfe"),
+            (0, cop0::WAIT) => String::from("wait"),
+            _ => format!("cop{} 0x{:X}", z, instr & 0x01FF_FFFF),
+        }),
+        _ => None,
+    };
+
+    let text = match op {
+        SPECIAL => match instr & 0x3F {
+            funct::SLL if instr == 0 => String::from("nop"),
+            funct::SLL => shift("sll"),
+            funct::SRL if ((instr >> 21) & 0x1F) == 1 => shift("rotr"),
+            funct::SRL => shift("srl"),
+            funct::SRA => shift("sra"),
+            funct::SLLV => shift_var("sllv"),
+            funct::SRLV if shift_amt() == 1 => shift_var("rotrv"),
+            funct::SRLV => shift_var("srlv"),
+            funct::SRAV => shift_var("srav"),
+
+            funct::MOVZ => arith("movz"),
+            funct::MOVN => arith("movn"),
+
+            funct::JR => format!("jr ${}", rs()),
+            funct::JALR => format!("jalr ${}, ${}", rd(), rs()),
+
+            funct::SYSCALL => String::from("syscall"),
+            funct::BREAK => String::from("break"),
+            funct::SYNC => String::from("sync"),
+
+            funct::MFHI => format!("mfhi ${}", rd()),
+            funct::MTHI => format!("mthi ${}", rs()),
+            funct::MFLO => format!("mflo ${}", rd()),
+            funct::MTLO => format!("mtlo ${}", rs()),
+
+            funct::MULT => two_reg("mult"),
+            funct::MULTU => two_reg("multu"),
+            funct::DIV => two_reg("div"),
+            funct::DIVU => two_reg("divu"),
+
+            funct::ADD => arith("add"),
+            funct::ADDU => arith("addu"),
+            funct::SUB => arith("sub"),
+            funct::SUBU => arith("subu"),
+            funct::AND => arith("and"),
+            funct::OR => arith("or"),
+            funct::XOR => arith("xor"),
+            funct::NOR => arith("nor"),
+            funct::SLT => arith("slt"),
+            funct::SLTU => arith("sltu"),
+
+            funct::TGE => two_reg("tge"),
+            funct::TGEU => two_reg("tgeu"),
+            funct::TLT => two_reg("tlt"),
+            funct::TLTU => two_reg("tltu"),
+            funct::TEQ => two_reg("teq"),
+            funct::TNE => two_reg("tne"),
+
+            _ => return None,
+        },
+        REGIMM => match (instr >> 16) & 0x1F {
+            regimm::BLTZ => branch_zero("bltz"),
+            regimm::BGEZ => branch_zero("bgez"),
+            regimm::BLTZAL => branch_zero("bltzal"),
+            regimm::BGEZAL => branch_zero("bgezal"),
+
+            regimm::TGEI => trap_imm("tgei"),
+            regimm::TGEIU => trap_imm("tgeiu"),
+            regimm::TLTI => trap_imm("tlti"),
+            regimm::TLTIU => trap_imm("tltiu"),
+            regimm::TEQI => trap_imm("teqi"),
+            regimm::TNEI => trap_imm("tnei"),
+
+            _ => return None,
+        },
+        J => format!("j 0x{:08X}", jump_dest()),
+        JAL => format!("jal 0x{:08X}", jump_dest()),
+
+        BEQ => branch("beq"),
+        BNE => branch("bne"),
+        BLEZ => branch_zero("blez"),
+        BGTZ => branch_zero("bgtz"),
+
+        ADDI => arith_imm("addi"),
+        ADDIU => arith_imm("addiu"),
+        SLTI => arith_imm("slti"),
+        SLTIU => arith_imm("sltiu"),
+        ANDI => logic_imm("andi"),
+        ORI => logic_imm("ori"),
+        XORI => logic_imm("xori"),
+        LUI => format!("lui ${}, 0x{:X}", rt(), imm()),
+
+        COP0..=COP3 => return cop(op - COP0),
+
+        SPECIAL2 => match instr & 0x3F {
+            special2::MADD => two_reg("madd"),
+            special2::MADDU => two_reg("maddu"),
+            special2::MUL => arith("mul"),
+            special2::MSUB => two_reg("msub"),
+            special2::MSUBU => two_reg("msubu"),
+            special2::CLZ => format!("clz ${}, ${}", rd(), rs()),
+            special2::CLO => format!("clo ${}, ${}", rd(), rs()),
+            special2::SDBBP => String::from("sdbbp"),
+            _ => return None,
+        },
+
+        SPECIAL3 => match (instr & 0x3F, shift_amt()) {
+            (special3::EXT, pos) => format!("ext ${}, ${}, {}, {}", rt(), rs(), pos, cop_reg() + 1),
+            (special3::INS, pos) => format!("ins ${}, ${}, {}, {}", rt(), rs(), pos, (cop_reg() + 1).saturating_sub(pos)),
+            (special3::BSHFL, special3::WSBH) => format!("wsbh ${}, ${}", rd(), rt()),
+            (special3::BSHFL, special3::SEB) => format!("seb ${}, ${}", rd(), rt()),
+            (special3::BSHFL, special3::SEH) => format!("seh ${}, ${}", rd(), rt()),
+            _ => return None,
+        },
+
+        LB => mem("lb"),
+        LH => mem("lh"),
+        LWL => mem("lwl"),
+        LW => mem("lw"),
+        LBU => mem("lbu"),
+        LHU => mem("lhu"),
+        LWR => mem("lwr"),
+
+        SB => mem("sb"),
+        SH => mem("sh"),
+        SWL => mem("swl"),
+        SW => mem("sw"),
+        SWR => mem("swr"),
+        CACHE => format!("cache 0x{:X}, {}(${})", (instr >> 16) & 0x1F, simm(), rs()),
+
+        LL => mem("ll"),
+        SC => mem("sc"),
+
+        LWC1..=LWC3 => cop_mem(&format!("lwc{}", op - LWC1 + 1)),
+        SWC1..=SWC3 => cop_mem(&format!("swc{}", op - SWC1 + 1)),
+
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+/// Disassemble `count` sequential instructions, starting at `start`.
+///
+/// Returns the address, raw word, and text of each instruction.
+/// Words that aren't valid instructions are shown as `.word` directives.
+pub fn disassemble_range<M: Mem32<Addr = u32>>(mem: &mut M, start: u32, count: usize) -> Vec<(u32, u32, String)> {
+    (0..count).map(|i| {
+        let addr = start.wrapping_add((i as u32) << 2);
+        let instr = mem.read_word(addr);
+        let text = disassemble(addr, instr).unwrap_or_else(|| format!(".word 0x{:08X}", instr));
+        (addr, instr, text)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single() {
+        assert_eq!(disassemble(0, 0).as_deref(), Some("nop"));
+        assert_eq!(disassemble(0, 0x2442_FFFF).as_deref(), Some("addiu $v0, $v0, -1"));
+        assert_eq!(disassemble(0, 0x8FBF_0010).as_deref(), Some("lw $ra, 16($sp)"));
+        assert_eq!(disassemble(0x100, 0x1000_FFFF).as_deref(), Some("beq $zero, $zero, 0x00000100"));
+        assert_eq!(disassemble(0x8000_0000, 0x0C00_0040).as_deref(), Some("jal 0x80000100"));
+        assert_eq!(disassemble(0, 0x4082_6000).as_deref(), Some("mtc0 $v0, $12"));
+        assert_eq!(disassemble(0, 0x4200_0002).as_deref(), Some("tlbwi"));
+        assert_eq!(disassemble(0, 0x4200_0010).as_deref(), Some("rfe"));
+        assert_eq!(disassemble(0, 0x4200_0020).as_deref(), Some("wait"));
+        assert_eq!(disassemble(0x200, 0x4501_0010).as_deref(), Some("bc1t 0x00000244"));
+        assert_eq!(disassemble(0, 0x0000_000F).as_deref(), Some("sync"));
+        assert_eq!(disassemble(0, 0x7082_1020).as_deref(), Some("clz $v0, $a0"));
+        assert_eq!(disassemble(0, 0x7000_003F).as_deref(), Some("sdbbp"));
+        assert_eq!(disassemble(0, 0x0023_1202).as_deref(), Some("rotr $v0, $v1, 8"));
+        assert_eq!(disassemble(0, 0x0083_1046).as_deref(), Some("rotrv $v0, $v1, $a0"));
+        assert_eq!(disassemble(0, 0x7C03_1420).as_deref(), Some("seb $v0, $v1"));
+        assert_eq!(disassemble(0, 0x7C03_1620).as_deref(), Some("seh $v0, $v1"));
+        assert_eq!(disassemble(0, 0x7C03_10A0).as_deref(), Some("wsbh $v0, $v1"));
+        assert_eq!(disassemble(0, 0x7C62_3A00).as_deref(), Some("ext $v0, $v1, 8, 8"));
+        assert_eq!(disassemble(0, 0x7C62_7A04).as_deref(), Some("ins $v0, $v1, 8, 8"));
+        assert_eq!(disassemble(0, 0xBC95_0010).as_deref(), Some("cache 0x15, 16($a0)"));
+        assert_eq!(disassemble(0, 0xFFFF_FFFF), None);
+    }
+
+    #[test]
+    fn range() {
+        use crate::mem::RamMemory;
+
+        let mut mem = RamMemory::<0x100>::new();
+        let program = [
+            0x3C08_8000,    // lui $t0, 0x8000
+            0x2409_0003,    // addiu $t1, $zero, 3
+            0xAD09_0000,    // sw $t1, 0($t0)
+            0x1520_FFFF,    // bne $t1, $zero, -1
+            0x2529_FFFF,    // addiu $t1, $t1, -1
+            0xFFFF_FFFF,    // data
+        ];
+        for (i, word) in program.iter().enumerate() {
+            mem.write_word((i * 4) as u32, *word);
+        }
+
+        let dis = disassemble_range(&mut mem, 0, program.len());
+        assert_eq!(dis[3], (0xC, 0x1520_FFFF, String::from("bne $t1, $zero, 0x0000000C")));
+        let text = dis.into_iter().map(|(_, _, text)| text).collect::<Vec<_>>();
+        assert_eq!(text, [
+            "lui $t0, 0x8000",
+            "addiu $t1, $zero, 3",
+            "sw $t1, 0($t0)",
+            "bne $t1, $zero, 0x0000000C",
+            "addiu $t1, $t1, -1",
+            ".word 0xFFFFFFFF",
+        ]);
+    }
+}
@@ -1,7 +1,10 @@
 use super::*;
+use super::opcodes::*;
 
 /// The set of instructions defined in MIPS I.
 /// 
+/// Some instructions from later revisions are also included, and are marked with the revision that added them.
+/// 
 /// The arguments must have been decoded prior to calling these.
 /// If a register number argument has a value greater than 31, the result is undefined.
 pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
@@ -10,24 +13,44 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Add signed
     fn add(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        let _ = self.try_add(src_reg, tgt_reg, dst_reg);
+    }
+
+    /// Add signed, returning the exception if it trapped on overflow.
+    /// 
+    /// The exception is still triggered: this only lets direct callers see that it was.
+    fn try_add(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) -> Result<(), ExceptionCode> {
         let source = self.read_gp(src_reg);
         let target = self.read_gp(tgt_reg);
-        if let Some(result) = source.checked_add(target) {
+        if let Ok(result) = checked_add_signed(source, target) {
             self.write_gp(dst_reg, result);
+        } else if self.trap_mode() == TrapMode::Lenient {
+            self.write_gp(dst_reg, source.wrapping_add(target));
         } else {
             self.trigger_exception(ExceptionCode::ArithmeticOverflow);
+            return Err(ExceptionCode::ArithmeticOverflow);
         }
+        Ok(())
     }
 
     /// Add immediate signed
     fn addi(&mut self, src_reg: usize, tgt_reg: usize, imm: u16) {
+        let _ = self.try_addi(src_reg, tgt_reg, imm);
+    }
+
+    /// Add immediate signed, returning the exception if it trapped on overflow.
+    fn try_addi(&mut self, src_reg: usize, tgt_reg: usize, imm: u16) -> Result<(), ExceptionCode> {
         let source = self.read_gp(src_reg);
         let imm_32 = sign_extend_16(imm);
-        if let Some(result) = source.checked_add(imm_32) {
+        if let Ok(result) = checked_add_signed(source, imm_32) {
             self.write_gp(tgt_reg, result);
+        } else if self.trap_mode() == TrapMode::Lenient {
+            self.write_gp(tgt_reg, source.wrapping_add(imm_32));
         } else {
             self.trigger_exception(ExceptionCode::ArithmeticOverflow);
+            return Err(ExceptionCode::ArithmeticOverflow);
         }
+        Ok(())
     }
 
     /// Add unsigned
@@ -48,13 +71,22 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Sub signed
     fn sub(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        let _ = self.try_sub(src_reg, tgt_reg, dst_reg);
+    }
+
+    /// Sub signed, returning the exception if it trapped on overflow.
+    fn try_sub(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) -> Result<(), ExceptionCode> {
         let source = self.read_gp(src_reg);
         let target = self.read_gp(tgt_reg);
-        if let Some(result) = source.checked_sub(target) {
+        if let Ok(result) = checked_sub_signed(source, target) {
             self.write_gp(dst_reg, result);
+        } else if self.trap_mode() == TrapMode::Lenient {
+            self.write_gp(dst_reg, source.wrapping_sub(target));
         } else {
             self.trigger_exception(ExceptionCode::ArithmeticOverflow);
+            return Err(ExceptionCode::ArithmeticOverflow);
         }
+        Ok(())
     }
 
     /// Sub unsigned
@@ -65,6 +97,22 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.write_gp(dst_reg, result);
     }
 
+    // Conditional moves (MIPS IV)
+
+    /// Move if zero
+    fn movz(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        if self.read_gp(tgt_reg) == 0 {
+            self.write_gp(dst_reg, self.read_gp(src_reg));
+        }
+    }
+
+    /// Move if not zero
+    fn movn(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        if self.read_gp(tgt_reg) != 0 {
+            self.write_gp(dst_reg, self.read_gp(src_reg));
+        }
+    }
+
     // Multiplication/division
 
     /// Multiply signed
@@ -85,25 +133,75 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.write_lo(lo64(result));
     }
 
+    /// Multiply signed into register (MIPS32)
+    /// 
+    /// The hardware leaves hi and lo unpredictable after this. Here they are left unchanged.
+    fn mul(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        self.write_gp(dst_reg, source.wrapping_mul(target));
+    }
+
+    /// Multiply signed and add to hi/lo (MIPS32)
+    fn madd(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = sign_extend_32(self.read_gp(src_reg));
+        let target = sign_extend_32(self.read_gp(tgt_reg));
+        let result = self.read_hilo().wrapping_add((source * target) as u64);
+        self.write_hilo(result);
+    }
+
+    /// Multiply unsigned and add to hi/lo (MIPS32)
+    fn maddu(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg) as u64;
+        let target = self.read_gp(tgt_reg) as u64;
+        let result = self.read_hilo().wrapping_add(source * target);
+        self.write_hilo(result);
+    }
+
+    /// Multiply signed and subtract from hi/lo (MIPS32)
+    fn msub(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = sign_extend_32(self.read_gp(src_reg));
+        let target = sign_extend_32(self.read_gp(tgt_reg));
+        let result = self.read_hilo().wrapping_sub((source * target) as u64);
+        self.write_hilo(result);
+    }
+
+    /// Multiply unsigned and subtract from hi/lo (MIPS32)
+    fn msubu(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg) as u64;
+        let target = self.read_gp(tgt_reg) as u64;
+        let result = self.read_hilo().wrapping_sub(source * target);
+        self.write_hilo(result);
+    }
+
     /// Divide signed
+    /// 
+    /// Dividing by zero leaves the dividend in hi, and -1 or 1 in lo depending on its sign, as the R3000 does.
     fn div(&mut self, src_reg: usize, tgt_reg: usize) {
-        let source = self.read_gp(src_reg) as i32;
-        let target = self.read_gp(tgt_reg) as i32;
-        self.write_hi((source % target) as u32);
-        self.write_lo((source / target) as u32);
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        let (quotient, remainder) = checked_div_signed(source, target)
+            .unwrap_or((if (source as i32) < 0 { 1 } else { 0xFFFF_FFFF }, source));
+        self.write_hi(remainder);
+        self.write_lo(quotient);
     }
 
     /// Divide unsigned
+    /// 
+    /// Dividing by zero leaves the dividend in hi and all ones in lo, as the R3000 does.
     fn divu(&mut self, src_reg: usize, tgt_reg: usize) {
         let source = self.read_gp(src_reg);
         let target = self.read_gp(tgt_reg);
-        self.write_hi(source % target);
-        self.write_lo(source / target);
+        let (quotient, remainder) = checked_div_unsigned(source, target)
+            .unwrap_or((0xFFFF_FFFF, source));
+        self.write_hi(remainder);
+        self.write_lo(quotient);
     }
 
     /// Move from hi
     fn mfhi(&mut self, dst_reg: usize) {
-        self.write_gp(dst_reg, self.read_hi());
+        let hi = if self.hilo_ready() { self.read_hi() } else { HILO_UNDEFINED };
+        self.write_gp(dst_reg, hi);
     }
 
     /// Move to hi
@@ -113,7 +211,8 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Move from lo
     fn mflo(&mut self, dst_reg: usize) {
-        self.write_gp(dst_reg, self.read_lo());
+        let lo = if self.hilo_ready() { self.read_lo() } else { HILO_UNDEFINED };
+        self.write_gp(dst_reg, lo);
     }
 
     /// Move to lo
@@ -184,21 +283,21 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     /// Shift left logical
     fn sll(&mut self, tgt_reg: usize, sh_amt: usize, dst_reg: usize) {
         let target = self.read_gp(tgt_reg);
-        let result = target << sh_amt;
+        let result = target << (sh_amt & 0x1F);
         self.write_gp(dst_reg, result);
     }
 
     /// Shift right logical
     fn srl(&mut self, tgt_reg: usize, sh_amt: usize, dst_reg: usize) {
         let target = self.read_gp(tgt_reg);
-        let result = target >> sh_amt;
+        let result = target >> (sh_amt & 0x1F);
         self.write_gp(dst_reg, result);
     }
 
     /// Shift right arithmetic
     fn sra(&mut self, tgt_reg: usize, sh_amt: usize, dst_reg: usize) {
         let target = self.read_gp(tgt_reg) as i32;
-        let result = target >> sh_amt;
+        let result = target >> (sh_amt & 0x1F);
         self.write_gp(dst_reg, result as u32);
     }
 
@@ -226,6 +325,21 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.write_gp(dst_reg, result as u32);
     }
 
+    /// Rotate right (MIPS32r2)
+    fn rotr(&mut self, tgt_reg: usize, sh_amt: usize, dst_reg: usize) {
+        let target = self.read_gp(tgt_reg);
+        let result = target.rotate_right((sh_amt & 0x1F) as u32);
+        self.write_gp(dst_reg, result);
+    }
+
+    /// Rotate right variable (MIPS32r2)
+    fn rotrv(&mut self, src_reg: usize, tgt_reg: usize, dst_reg: usize) {
+        let source = self.read_gp(src_reg) & 0x1F;
+        let target = self.read_gp(tgt_reg);
+        let result = target.rotate_right(source);
+        self.write_gp(dst_reg, result);
+    }
+
     // Conditional sets
 
     /// Set on less than signed
@@ -260,6 +374,64 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.write_gp(tgt_reg, result);
     }
 
+    // Bit counting (MIPS32)
+
+    /// Count leading zeros
+    fn clz(&mut self, src_reg: usize, dst_reg: usize) {
+        let source = self.read_gp(src_reg);
+        self.write_gp(dst_reg, source.leading_zeros());
+    }
+
+    /// Count leading ones
+    fn clo(&mut self, src_reg: usize, dst_reg: usize) {
+        let source = self.read_gp(src_reg);
+        self.write_gp(dst_reg, source.leading_ones());
+    }
+
+    /// Extract bit field (MIPS32r2)
+    /// 
+    /// Copies `size` bits of the source, starting at bit `pos`, to the low bits of the target.
+    /// Bits beyond bit 31 of the source read as 0.
+    fn ext(&mut self, src_reg: usize, tgt_reg: usize, pos: usize, size: usize) {
+        let source = self.read_gp(src_reg) as u64;
+        let mask = (1_u64 << (size & 0x3F)) - 1;
+        let result = (source >> (pos & 0x1F)) & mask;
+        self.write_gp(tgt_reg, result as u32);
+    }
+
+    /// Insert bit field (MIPS32r2)
+    /// 
+    /// Replaces `size` bits of the target, starting at bit `pos`, with the low bits of the source.
+    /// Bits that would go beyond bit 31 are dropped.
+    fn ins(&mut self, src_reg: usize, tgt_reg: usize, pos: usize, size: usize) {
+        let source = self.read_gp(src_reg) as u64;
+        let target = self.read_gp(tgt_reg) as u64;
+        let mask = ((1_u64 << (size & 0x3F)) - 1) << (pos & 0x1F);
+        let result = (target & !mask) | ((source << (pos & 0x1F)) & mask);
+        self.write_gp(tgt_reg, result as u32);
+    }
+
+    /// Word swap bytes within halfwords (MIPS32r2)
+    /// 
+    /// Followed by `ROTR` by 16, this reverses the bytes of a word.
+    fn wsbh(&mut self, tgt_reg: usize, dst_reg: usize) {
+        let target = self.read_gp(tgt_reg);
+        let result = ((target & 0x00FF_00FF) << 8) | ((target >> 8) & 0x00FF_00FF);
+        self.write_gp(dst_reg, result);
+    }
+
+    /// Sign-extend byte (MIPS32r2)
+    fn seb(&mut self, tgt_reg: usize, dst_reg: usize) {
+        let target = self.read_gp(tgt_reg);
+        self.write_gp(dst_reg, sign_extend_8(target as u8));
+    }
+
+    /// Sign-extend halfword (MIPS32r2)
+    fn seh(&mut self, tgt_reg: usize, dst_reg: usize) {
+        let target = self.read_gp(tgt_reg);
+        self.write_gp(dst_reg, sign_extend_16(target as u16));
+    }
+
     // Memory access
 
     /// Load byte signed
@@ -267,8 +439,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let byte = self.mem().read_byte(addr.into());
-        self.write_gp(tgt_reg, sign_extend_8(byte));
+        if let Some(byte) = self.load_byte(addr) {
+            self.write_gp_loaded(tgt_reg, sign_extend_8(byte));
+        }
     }
 
     /// Load byte unsigned
@@ -276,8 +449,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let byte = self.mem().read_byte(addr.into());
-        self.write_gp(tgt_reg, byte as u32);
+        if let Some(byte) = self.load_byte(addr) {
+            self.write_gp_loaded(tgt_reg, byte as u32);
+        }
     }
 
     /// Load halfword signed
@@ -285,8 +459,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let halfword = self.mem().read_halfword(addr.into());
-        self.write_gp(tgt_reg, sign_extend_16(halfword));
+        if let Some(halfword) = self.load_halfword(addr) {
+            self.write_gp_loaded(tgt_reg, sign_extend_16(halfword));
+        }
     }
 
     /// Load halfword unsigned
@@ -294,8 +469,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let halfword = self.mem().read_halfword(addr.into());
-        self.write_gp(tgt_reg, halfword as u32);
+        if let Some(halfword) = self.load_halfword(addr) {
+            self.write_gp_loaded(tgt_reg, halfword as u32);
+        }
     }
 
     /// Load word
@@ -303,8 +479,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let word = self.mem().read_word(addr.into());
-        self.write_gp(tgt_reg, word);
+        if let Some(word) = self.load_word(addr) {
+            self.write_gp_loaded(tgt_reg, word);
+        }
     }
 
     /// Load word left
@@ -317,18 +494,18 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let byte_addr = addr & 3;
         let byte_offset = if self.mem().little_endian() { 3 - byte_addr } else { byte_addr };
 
-        let word = self.mem().read_word(word_addr.into());
+        let word = if let Some(word) = self.load_word(word_addr) { word } else { return };
         let old_word = match byte_offset {
             0 => 0,
             1 => 0xFFFF_FFFF >> 24,
             2 => 0xFFFF_FFFF >> 16,
             3 => 0xFFFF_FFFF >> 8,
             _ => unreachable!()
-        } & self.read_gp(tgt_reg);
+        } & self.read_gp_loading(tgt_reg);
 
         let shift = byte_offset * 8;
 
-        self.write_gp(tgt_reg, old_word | (word << shift));
+        self.write_gp_loaded(tgt_reg, old_word | (word << shift));
     }
 
     /// Load word right
@@ -341,18 +518,29 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let byte_addr = addr & 3;
         let byte_offset = if self.mem().little_endian() { byte_addr } else { 3 - byte_addr };
 
-        let word = self.mem().read_word(word_addr.into());
+        let word = if let Some(word) = self.load_word(word_addr) { word } else { return };
         let old_word = match byte_offset {
             0 => 0,
             1 => 0xFFFF_FFFF << 24,
             2 => 0xFFFF_FFFF << 16,
             3 => 0xFFFF_FFFF << 8,
             _ => unreachable!()
-        } & self.read_gp(tgt_reg);
+        } & self.read_gp_loading(tgt_reg);
 
         let shift = byte_offset * 8;
 
-        self.write_gp(tgt_reg, old_word | (word >> shift));
+        self.write_gp_loaded(tgt_reg, old_word | (word >> shift));
+    }
+
+    /// Load linked (MIPS II)
+    fn ll(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let base = self.read_gp(base_reg);
+        let offset32 = sign_extend_16(offset);
+        let addr = base.wrapping_add(offset32);
+        if let Some(word) = self.load_word(addr) {
+            self.write_link(Some(addr));
+            self.write_gp(tgt_reg, word);
+        }
     }
 
     /// Store byte
@@ -361,7 +549,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
         let data = self.read_gp(tgt_reg) as u8;
-        self.mem().write_byte(addr.into(), data);
+        self.store_byte(addr, data);
     }
 
     /// Store halfword
@@ -370,7 +558,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
         let data = self.read_gp(tgt_reg) as u16;
-        self.mem().write_halfword(addr.into(), data);
+        self.store_halfword(addr, data);
     }
 
     /// Store word
@@ -379,7 +567,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
         let data = self.read_gp(tgt_reg);
-        self.mem().write_word(addr.into(), data);
+        self.store_word(addr, data);
     }
 
     /// Store word left
@@ -393,17 +581,18 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let byte_offset = if self.mem().little_endian() { 3 - byte_addr } else { byte_addr };
 
         let word = self.read_gp(tgt_reg);
+        let mem_word = if let Some(word) = self.load_word(word_addr) { word } else { return };
         let old_word = match byte_offset {
             0 => 0,
             1 => 0xFFFF_FFFF << 24,
             2 => 0xFFFF_FFFF << 16,
             3 => 0xFFFF_FFFF << 8,
             _ => unreachable!()
-        } & self.mem().read_word(word_addr.into());
+        } & mem_word;
 
         let shift = byte_offset * 8;
 
-        self.mem().write_word(word_addr.into(), old_word | (word >> shift));
+        self.store_word(word_addr, old_word | (word >> shift));
     }
 
     /// Store word right
@@ -417,17 +606,18 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let byte_offset = if self.mem().little_endian() { byte_addr } else { 3 - byte_addr };
 
         let word = self.read_gp(tgt_reg);
+        let mem_word = if let Some(word) = self.load_word(word_addr) { word } else { return };
         let old_word = match byte_offset {
             0 => 0,
             1 => 0xFFFF_FFFF >> 24,
             2 => 0xFFFF_FFFF >> 16,
             3 => 0xFFFF_FFFF >> 8,
             _ => unreachable!()
-        } & self.mem().read_word(word_addr.into());
+        } & mem_word;
 
         let shift = byte_offset * 8;
 
-        self.mem().write_word(word_addr.into(), old_word | (word << shift));
+        self.store_word(word_addr, old_word | (word << shift));
     }
 
     /// Load upper immediate
@@ -436,6 +626,21 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.write_gp(tgt_reg, upper_imm);
     }
 
+    /// Store conditional (MIPS II)
+    fn sc(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let base = self.read_gp(base_reg);
+        let offset32 = sign_extend_16(offset);
+        let addr = base.wrapping_add(offset32);
+        if self.read_link() == Some(addr) {
+            let data = self.read_gp(tgt_reg);
+            if self.store_word(addr, data).is_some() {
+                self.write_gp(tgt_reg, 1);
+            }
+        } else {
+            self.write_gp(tgt_reg, 0);
+        }
+    }
+
     // Branch
     
     /// Branch if equal
@@ -443,7 +648,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let source = self.read_gp(src_reg);
         let target = self.read_gp(tgt_reg);
         if source == target {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -453,7 +658,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         let source = self.read_gp(src_reg);
         let target = self.read_gp(tgt_reg);
         if source != target {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -462,7 +667,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     fn bgtz(&mut self, src_reg: usize, offset: u16) {
         let source = self.read_gp(src_reg) as i32;
         if source > 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -471,7 +676,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     fn bgez(&mut self, src_reg: usize, offset: u16) {
         let source = self.read_gp(src_reg) as i32;
         if source >= 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -481,7 +686,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.link_register(31);
         let source = self.read_gp(src_reg) as i32;
         if source >= 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -490,7 +695,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     fn bltz(&mut self, src_reg: usize, offset: u16) {
         let source = self.read_gp(src_reg) as i32;
         if source < 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -499,7 +704,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     fn blez(&mut self, src_reg: usize, offset: u16) {
         let source = self.read_gp(src_reg) as i32;
         if source <= 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -509,7 +714,7 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.link_register(31);
         let source = self.read_gp(src_reg) as i32;
         if source < 0 {
-            let offset32 = sign_extend_16(offset) << 2;
+            let offset32 = branch_offset(offset);
             self.branch(offset32);
         }
     }
@@ -530,21 +735,27 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
     /// Jump register
     fn jr(&mut self, src_reg: usize) {
         let dest = self.read_gp(src_reg);
-        self.jump(dest);
+        self.jump_register(dest);
     }
 
     /// Jump and link register
+    /// 
+    /// Assemblers default the destination to `$ra`.
+    /// If the source and destination are the same register, the result is unpredictable:
+    /// here the register is linked, and the jump goes to its value from before the link.
     fn jalr(&mut self, src_reg: usize, dst_reg: usize) {
-        self.link_register(dst_reg);
         let dest = self.read_gp(src_reg);
-        self.jump(dest);
+        self.link_register(dst_reg);
+        self.jump_register(dest);
     }
 
     // Special
 
     /// System call
     fn syscall(&mut self) {
-        self.trigger_exception(ExceptionCode::Syscall);
+        if !self.host_syscall() {
+            self.trigger_exception(ExceptionCode::Syscall);
+        }
     }
 
     /// Break
@@ -552,10 +763,128 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
         self.trigger_exception(ExceptionCode::Breakpoint);
     }
 
+    /// Software debug breakpoint (MIPS32 EJTAG)
+    fn sdbbp(&mut self) {
+        self.trigger_exception(ExceptionCode::DebugBreak);
+    }
+
+    // Traps (MIPS II)
+
+    /// Trap if greater than or equal signed
+    fn tge(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg) as i32;
+        let target = self.read_gp(tgt_reg) as i32;
+        if source >= target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if greater than or equal unsigned
+    fn tgeu(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        if source >= target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if less than signed
+    fn tlt(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg) as i32;
+        let target = self.read_gp(tgt_reg) as i32;
+        if source < target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if less than unsigned
+    fn tltu(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        if source < target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if equal
+    fn teq(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        if source == target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if not equal
+    fn tne(&mut self, src_reg: usize, tgt_reg: usize) {
+        let source = self.read_gp(src_reg);
+        let target = self.read_gp(tgt_reg);
+        if source != target {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if greater than or equal immediate signed
+    fn tgei(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg) as i32;
+        let imm32 = sign_extend_16(imm) as i32;
+        if source >= imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if greater than or equal immediate unsigned
+    fn tgeiu(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg);
+        let imm32 = sign_extend_16(imm);
+        if source >= imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if less than immediate signed
+    fn tlti(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg) as i32;
+        let imm32 = sign_extend_16(imm) as i32;
+        if source < imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if less than immediate unsigned
+    fn tltiu(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg);
+        let imm32 = sign_extend_16(imm);
+        if source < imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if equal immediate
+    fn teqi(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg);
+        let imm32 = sign_extend_16(imm);
+        if source == imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
+    /// Trap if not equal immediate
+    fn tnei(&mut self, src_reg: usize, imm: u16) {
+        let source = self.read_gp(src_reg);
+        let imm32 = sign_extend_16(imm);
+        if source != imm32 {
+            self.trigger_exception(ExceptionCode::Trap);
+        }
+    }
+
     // Coprocessor
 
     /// Move register to coprocessor
     fn mtcz(&mut self, coproc: Coproc, tgt_reg: usize, cop_reg: usize) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         let val = self.read_gp(tgt_reg);
         match coproc {
             Coproc::_0 => self.coproc_0().move_to_reg(cop_reg, val),
@@ -567,6 +896,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Move register from coprocessor
     fn mfcz(&mut self, coproc: Coproc, tgt_reg: usize, cop_reg: usize) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         if let Some(val) = match coproc {
             Coproc::_0 => Some(self.coproc_0().move_from_reg(cop_reg)),
             Coproc::_1 => self.coproc_1().map(|cop| cop.move_from_reg(cop_reg)),
@@ -581,6 +913,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Move control to coprocessor
     fn ctcz(&mut self, coproc: Coproc, tgt_reg: usize, ctrl_reg: usize) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         let val = self.read_gp(tgt_reg);
         match coproc {
             Coproc::_0 => unreachable!(),
@@ -592,6 +927,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Move control from coprocessor
     fn cfcz(&mut self, coproc: Coproc, tgt_reg: usize, ctrl_reg: usize) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         if let Some(val) = match coproc {
             Coproc::_0 => unreachable!(),
             Coproc::_1 => self.coproc_1().map(|cop| cop.move_from_control(ctrl_reg)),
@@ -606,10 +944,13 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Load word into coprocessor
     fn lwcz(&mut self, coproc: Coproc, base_reg: usize, cop_reg: usize, offset: u16) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         let base = self.read_gp(base_reg);
         let offset32 = sign_extend_16(offset);
         let addr = base.wrapping_add(offset32);
-        let data = self.mem().read_word(addr.into());
+        let data = if let Some(data) = self.load_word(addr) { data } else { return };
         match coproc {
             Coproc::_0 => unreachable!(),
             Coproc::_1 => if let Some(cop) = self.coproc_1() {cop.move_to_reg(cop_reg, data)} else {self.trigger_exception(ExceptionCode::CoProcUnusable)},
@@ -620,6 +961,9 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
 
     /// Store word from coprocessor
     fn swcz(&mut self, coproc: Coproc, base_reg: usize, cop_reg: usize, offset: u16) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
         if let Some(data) = match coproc {
             Coproc::_0 => unreachable!(),
             Coproc::_1 => self.coproc_1().map(|cop| cop.move_from_reg(cop_reg)),
@@ -629,14 +973,149 @@ pub trait MIPSIInstructions<Mem>: MIPSICore<Mem = Mem>
             let base = self.read_gp(base_reg);
             let offset32 = sign_extend_16(offset);
             let addr = base.wrapping_add(offset32);
-            self.mem().write_word(addr.into(), data);
+            self.store_word(addr, data);
         } else {
             self.trigger_exception(ExceptionCode::CoProcUnusable);
         }
     }
 
+    /// Synchronise shared memory (MIPS II)
+    fn sync(&mut self) {
+        self.mem().on_sync();
+    }
+
+    /// Prefetch (MIPS32)
+    /// 
+    /// This has no architectural effect. If the address translates without a fault,
+    /// the memory is given it as a hint. A fault leaves coprocessor 0 untouched.
+    fn pref(&mut self, base_reg: usize, offset: u16) {
+        let base = self.read_gp(base_reg);
+        let offset32 = sign_extend_16(offset);
+        let addr = base.wrapping_add(offset32);
+        if let Ok(addr) = self.probe_translate(addr, false) {
+            self.mem().on_prefetch(addr.into());
+        }
+    }
+
+    /// Cache operation (MIPS32)
+    /// 
+    /// There is no cache to operate on. If the address translates without a fault,
+    /// the memory is given the operation. A fault leaves coprocessor 0 untouched.
+    /// 
+    /// This is privileged: it needs coprocessor 0 to be usable.
+    fn cache(&mut self, op: usize, base_reg: usize, offset: u16) {
+        if !self.check_coproc_usable(Coproc::_0) {
+            return;
+        }
+        let base = self.read_gp(base_reg);
+        let offset32 = sign_extend_16(offset);
+        let addr = base.wrapping_add(offset32);
+        if let Ok(addr) = self.probe_translate(addr, false) {
+            self.mem().on_cache(op as u32, addr.into());
+        }
+    }
+
+    /// Read indexed TLB entry
+    fn tlbr(&mut self) {
+        if !self.check_coproc_usable(Coproc::_0) {
+            return;
+        }
+        self.coproc_0().tlb_read();
+    }
+
+    /// Write indexed TLB entry
+    fn tlbwi(&mut self) {
+        if !self.check_coproc_usable(Coproc::_0) {
+            return;
+        }
+        self.coproc_0().tlb_write_indexed();
+    }
+
+    /// Write random TLB entry
+    fn tlbwr(&mut self) {
+        if !self.check_coproc_usable(Coproc::_0) {
+            return;
+        }
+        self.coproc_0().tlb_write_random();
+    }
+
+    /// Probe TLB for matching entry
+    fn tlbp(&mut self) {
+        if !self.check_coproc_usable(Coproc::_0) {
+            return;
+        }
+        self.coproc_0().tlb_probe();
+    }
+
+    /// Wait for interrupt (MIPS32)
+    fn wait(&mut self) {
+        self.wait_for_interrupt();
+    }
+
+    /// Branch on coprocessor true
+    fn bczt(&mut self, coproc: Coproc, offset: u16) {
+        if let Some(condition) = self.coproc_condition(coproc) {
+            if condition {
+                let offset32 = branch_offset(offset);
+                self.branch(offset32);
+            }
+        }
+    }
+
+    /// Branch on coprocessor false
+    fn bczf(&mut self, coproc: Coproc, offset: u16) {
+        if let Some(condition) = self.coproc_condition(coproc) {
+            if !condition {
+                let offset32 = branch_offset(offset);
+                self.branch(offset32);
+            }
+        }
+    }
+
+    /// Read the condition signal of a coprocessor.
+    /// 
+    /// Triggers an exception and returns `None` if the coprocessor is unusable.
+    fn coproc_condition(&mut self, coproc: Coproc) -> Option<bool> {
+        if !self.check_coproc_usable(coproc) {
+            return None;
+        }
+        let condition = match coproc {
+            Coproc::_0 => Some(self.coproc_0().condition_code()),
+            Coproc::_1 => self.coproc_1().map(|cop| cop.condition_code()),
+            Coproc::_2 => self.coproc_2().map(|cop| cop.condition_code()),
+            Coproc::_3 => self.coproc_3().map(|cop| cop.condition_code()),
+        };
+        if condition.is_none() {
+            self.trigger_exception(ExceptionCode::CoProcUnusable);
+        }
+        condition
+    }
+
+    /// Check that a coprocessor is usable, according to coprocessor 0.
+    /// 
+    /// If it isn't, this triggers `CoProcUnusable` and returns `false`.
+    fn check_coproc_usable(&mut self, coproc: Coproc) -> bool {
+        let usable = self.coproc_0().coproc_usable(coproc);
+        if !usable {
+            self.trigger_exception(ExceptionCode::CoProcUnusable);
+        }
+        usable
+    }
+
     /// Coprocessor operation
     fn copz(&mut self, coproc: Coproc, cofun: u32) {
+        if !self.check_coproc_usable(coproc) {
+            return;
+        }
+        let present = match coproc {
+            Coproc::_0 => true,
+            Coproc::_1 => self.coproc_1().is_some(),
+            Coproc::_2 => self.coproc_2().is_some(),
+            Coproc::_3 => self.coproc_3().is_some(),
+        };
+        if present {
+            self.observe_coproc_operation(coproc, cofun);
+        }
         match coproc {
             Coproc::_0 => self.coproc_0().operation(cofun),
             Coproc::_1 => if let Some(cop) = self.coproc_1() {cop.operation(cofun)} else {self.trigger_exception(ExceptionCode::CoProcUnusable)},
@@ -655,15 +1134,116 @@ impl<
 > MIPSCore for MIPSI<Mem, C0, C1, C2, C3>
     where <Mem as Memory>::Addr: From<u32>, MIPSI<Mem, C0, C1, C2, C3>: MIPSIInstructions<Mem> {
 
-    fn step(&mut self) {
-        let instr = self.mem.read_word(self.pc.into());
+    fn step(&mut self) -> StepResult {
+        if self.halted {
+            return StepResult::Halted;
+        }
+
+        if self.break_hit {
+            self.break_hit = false;
+        } else if self.breakpoints.contains(&self.pc) {
+            self.break_hit = true;
+            return StepResult::Breakpoint;
+        }
+
+        if let Some(line) = self.interrupts.select(self.coproc0.pending_interrupts()) {
+            // Any pending interrupt wakes the CPU, even if it isn't taken.
+            self.waiting = false;
+            if self.config.vector_exceptions && self.coproc0.exception_vector(ExceptionCode::Interrupt).is_some() {
+                self.current_pc = self.pc;
+                // A load in its delay slot still completes.
+                self.load_in_flight = self.load_pending.take();
+                self.retire_load();
+                self.trigger_exception(ExceptionCode::Interrupt);
+                self.mem.on_interrupt_acknowledged(line);
+                if self.config.check_invariants {
+                    self.check_invariants();
+                }
+                return StepResult::Executed;
+            }
+        }
+
+        if self.waiting {
+            // Time still passes, so the timer can wake the CPU.
+            self.coproc0.tick();
+            return StepResult::Waiting;
+        }
+
+        #[cfg(feature = "std")]
+        if self.profiling {
+            *self.profile.entry(self.pc).or_insert(0) += 1;
+        }
+
+        self.current_pc = self.pc;
+        self.last_exception = None;
+        let instr = if let Some(instr) = self.fetch_word(self.pc) {
+            instr
+        } else {
+            if self.config.check_invariants {
+                self.check_invariants();
+            }
+            return StepResult::Executed;
+        };
+        if self.config.fetch_guard && instr == 0xFFFF_FFFF {
+            return StepResult::GarbageFetch;
+        }
+        // The delay slot of a branch to itself, containing a NOP.
+        let stuck = self.delay_slot && (instr == 0) && (self.pc_next == self.pc.wrapping_sub(4));
+        self.execute(instr);
+        self.coproc0.tick();
+        if self.config.check_invariants {
+            self.check_invariants();
+        }
+
+        if self.last_exception.map(|e| e.code) == Some(ExceptionCode::DebugBreak) {
+            return StepResult::DebugBreak;
+        }
+
+        if let Some(limit) = self.config.stuck_limit {
+            if stuck {
+                self.stuck_count += 1;
+                if self.stuck_count >= limit {
+                    return StepResult::Stuck;
+                }
+            } else if !self.delay_slot {
+                self.stuck_count = 0;
+            }
+        }
+
+        StepResult::Executed
+    }
+
+    fn execute(&mut self, instr: u32) {
+        self.current_pc = self.pc;
+        self.last_exception = None;
         self.pc = self.pc_next;
         self.pc_next = self.pc_next.wrapping_add(4);
+        self.redirected = false;
+        self.load_in_flight = self.load_pending.take();
+
+        if self.config.strict_decode && !reserved_fields_clear(instr, self.config.generation) {
+            self.reserved_instruction(instr, None);
+            self.delay_slot = false;
+            self.retire_load();
+            return;
+        }
+
+        if required_isa_level(instr, self.config.generation) > self.config.isa_level {
+            let subop = match instr >> 26 {
+                SPECIAL | SPECIAL2 => Some(instr & 0x3F),
+                REGIMM => Some((instr >> 16) & 0x1F),
+                _ => None,
+            };
+            self.reserved_instruction(instr, subop);
+            self.delay_slot = false;
+            self.retire_load();
+            return;
+        }
 
-        let op = || -> u8 {
+        let op = || -> u32 {
             const MASK: u32 = 0xFC00_0000;
             const SHIFT: usize = 26;
-            ((instr & MASK) >> SHIFT) as u8
+            (instr & MASK) >> SHIFT
         };
         let source = || -> usize {
             const MASK: u32 = 0x03E0_0000;
@@ -685,9 +1265,9 @@ impl<
             const SHIFT: usize = 6;
             ((instr & MASK) >> SHIFT) as usize
         };
-        let special_op = || -> u8 {
+        let special_op = || -> u32 {
             const MASK: u32 = 0x0000_003F;
-            (instr & MASK) as u8
+            instr & MASK
         };
         let imm = || -> u16 {
             instr as u16
@@ -702,128 +1282,403 @@ impl<
         };
 
         match op() {
-            0 => match special_op() {
-                0x20 => self.add(source(), target(), dest()),
-                0x21 => self.addu(source(), target(), dest()),
-                0x22 => self.sub(source(), target(), dest()),
-                0x23 => self.subu(source(), target(), dest()),
-
-                0x18 => self.mult(source(), target()),
-                0x19 => self.multu(source(), target()),
-                0x1A => self.div(source(), target()),
-                0x1B => self.divu(source(), target()),
-
-                0x10 => self.mfhi(dest()),
-                0x12 => self.mflo(dest()),
-                0x11 => self.mthi(source()),
-                0x13 => self.mthi(source()),
-
-                0x24 => self.and(source(), target(), dest()),
-                0x25 => self.or(source(), target(), dest()),
-                0x26 => self.xor(source(), target(), dest()),
-                0x27 => self.nor(source(), target(), dest()),
-
-                0x00 => self.sll(target(), shift_amt(), dest()),
-                0x04 => self.sllv(source(), target(), dest()),
-                0x02 => self.srl(target(), shift_amt(), dest()),
-                0x06 => self.srlv(source(), target(), dest()),
-                0x03 => self.sra(target(), shift_amt(), dest()),
-                0x07 => self.srav(source(), target(), dest()),
-
-                0x2A => self.slt(source(), target(), dest()),
-                0x2B => self.sltu(source(), target(), dest()),
-
-                0x08 => self.jr(source()),
-                0x09 => self.jalr(source(), dest()),
-
-                0x0C => self.syscall(),
-                0x0D => self.brk(),
-
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            SPECIAL => match special_op() {
+                funct::ADD => self.add(source(), target(), dest()),
+                funct::ADDU => self.addu(source(), target(), dest()),
+                funct::SUB => self.sub(source(), target(), dest()),
+                funct::SUBU => self.subu(source(), target(), dest()),
+
+                funct::MULT => self.mult(source(), target()),
+                funct::MULTU => self.multu(source(), target()),
+                funct::DIV => self.div(source(), target()),
+                funct::DIVU => self.divu(source(), target()),
+
+                funct::MFHI => self.mfhi(dest()),
+                funct::MFLO => self.mflo(dest()),
+                funct::MTHI => self.mthi(source()),
+                funct::MTLO => self.mtlo(source()),
+
+                funct::AND => self.and(source(), target(), dest()),
+                funct::OR => self.or(source(), target(), dest()),
+                funct::XOR => self.xor(source(), target(), dest()),
+                funct::NOR => self.nor(source(), target(), dest()),
+
+                funct::SLL => self.sll(target(), shift_amt(), dest()),
+                funct::SLLV => self.sllv(source(), target(), dest()),
+                funct::SRL if self.generation() == Generation::MIPS32 && source() == 1 => self.rotr(target(), shift_amt(), dest()),
+                funct::SRL => self.srl(target(), shift_amt(), dest()),
+                funct::SRLV if self.generation() == Generation::MIPS32 && shift_amt() == 1 => self.rotrv(source(), target(), dest()),
+                funct::SRLV => self.srlv(source(), target(), dest()),
+                funct::SRA => self.sra(target(), shift_amt(), dest()),
+                funct::SRAV => self.srav(source(), target(), dest()),
+
+                funct::SLT => self.slt(source(), target(), dest()),
+                funct::SLTU => self.sltu(source(), target(), dest()),
+
+                funct::MOVZ => self.movz(source(), target(), dest()),
+                funct::MOVN => self.movn(source(), target(), dest()),
+
+                funct::JR => self.jr(source()),
+                funct::JALR => self.jalr(source(), dest()),
+
+                funct::SYSCALL => self.syscall(),
+                funct::BREAK => self.brk(),
+                funct::SYNC => self.sync(),
+
+                funct::TGE => self.tge(source(), target()),
+                funct::TGEU => self.tgeu(source(), target()),
+                funct::TLT => self.tlt(source(), target()),
+                funct::TLTU => self.tltu(source(), target()),
+                funct::TEQ => self.teq(source(), target()),
+                funct::TNE => self.tne(source(), target()),
+
+                _ => self.reserved_instruction(instr, Some(special_op())),
             },
             // Immediate instructions
-            0x08 => self.addi(source(), target(), imm()),
-            0x09 => self.addiu(source(), target(), imm()),
-
-            0x0C => self.andi(source(), target(), imm()),
-            0x0D => self.ori(source(), target(), imm()),
-            0x0E => self.xori(source(), target(), imm()),
-
-            0x0A => self.slti(source(), target(), imm()),
-            0x0B => self.sltiu(source(), target(), imm()),
-
-            0x04 => self.beq(source(), target(), imm()),
-            0x05 => self.bne(source(), target(), imm()),
-            0x06 => self.blez(source(), imm()),
-            0x07 => self.bgtz(source(), imm()),
-            0x01 => match target() {
-                0x00 => self.bltz(source(), imm()),
-                0x01 => self.bgez(source(), imm()),
-                0x10 => self.bltzal(source(), imm()),
-                0x11 => self.bgezal(source(), imm()),
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            ADDI => self.addi(source(), target(), imm()),
+            ADDIU => self.addiu(source(), target(), imm()),
+
+            ANDI => self.andi(source(), target(), imm()),
+            ORI => self.ori(source(), target(), imm()),
+            XORI => self.xori(source(), target(), imm()),
+
+            SLTI => self.slti(source(), target(), imm()),
+            SLTIU => self.sltiu(source(), target(), imm()),
+
+            BEQ => self.beq(source(), target(), imm()),
+            BNE => self.bne(source(), target(), imm()),
+            BLEZ => self.blez(source(), imm()),
+            BGTZ => self.bgtz(source(), imm()),
+            REGIMM => match target() as u32 {
+                regimm::BLTZ => self.bltz(source(), imm()),
+                regimm::BGEZ => self.bgez(source(), imm()),
+                regimm::BLTZAL => self.bltzal(source(), imm()),
+                regimm::BGEZAL => self.bgezal(source(), imm()),
+
+                regimm::TGEI => self.tgei(source(), imm()),
+                regimm::TGEIU => self.tgeiu(source(), imm()),
+                regimm::TLTI => self.tlti(source(), imm()),
+                regimm::TLTIU => self.tltiu(source(), imm()),
+                regimm::TEQI => self.teqi(source(), imm()),
+                regimm::TNEI => self.tnei(source(), imm()),
+                _ => self.reserved_instruction(instr, Some(target() as u32)),
             },
 
-            0x20 => self.lb(source(), target(), imm()),
-            0x24 => self.lbu(source(), target(), imm()),
-            0x21 => self.lh(source(), target(), imm()),
-            0x25 => self.lhu(source(), target(), imm()),
-            0x23 => self.lw(source(), target(), imm()),
-            0x22 => self.lwl(source(), target(), imm()),
-            0x26 => self.lwr(source(), target(), imm()),
+            LB => self.lb(source(), target(), imm()),
+            LBU => self.lbu(source(), target(), imm()),
+            LH => self.lh(source(), target(), imm()),
+            LHU => self.lhu(source(), target(), imm()),
+            LW => self.lw(source(), target(), imm()),
+            LWL => self.lwl(source(), target(), imm()),
+            LWR => self.lwr(source(), target(), imm()),
+
+            SB => self.sb(source(), target(), imm()),
+            SH => self.sh(source(), target(), imm()),
+            SW => self.sw(source(), target(), imm()),
+            SWL => self.swl(source(), target(), imm()),
+            SWR => self.swr(source(), target(), imm()),
 
-            0x28 => self.sb(source(), target(), imm()),
-            0x29 => self.sh(source(), target(), imm()),
-            0x2B => self.sw(source(), target(), imm()),
-            0x2A => self.swl(source(), target(), imm()),
-            0x2E => self.swr(source(), target(), imm()),
+            LL => self.ll(source(), target(), imm()),
+            SC => self.sc(source(), target(), imm()),
 
-            0x0F => self.lui(target(), imm()),
+            LUI => self.lui(target(), imm()),
 
             // Jump instructions
-            0x02 => self.j(jump_target()),
-            0x03 => self.jal(jump_target()),
+            J => self.j(jump_target()),
+            JAL => self.jal(jump_target()),
 
             // Coprocessor
-            0x10 => match source() {
-                0x00 => self.mfcz(Coproc::_0, target(), dest()),
-                0x04 => self.mtcz(Coproc::_0, target(), dest()),
-                x if (x & 0x10) == 0x10 => self.copz(Coproc::_0, cofun()),
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            COP0 => match source() as u32 {
+                cop::MF => self.mfcz(Coproc::_0, target(), dest()),
+                cop::MT => self.mtcz(Coproc::_0, target(), dest()),
+                cop::BC => match target() as u32 {
+                    cop::BCF => self.bczf(Coproc::_0, imm()),
+                    cop::BCT => self.bczt(Coproc::_0, imm()),
+                    _ => self.reserved_instruction(instr, Some(target() as u32)),
+                },
+                x if (x & cop::CO) == cop::CO => match special_op() {
+                    cop0::TLBR => self.tlbr(),
+                    cop0::TLBWI => self.tlbwi(),
+                    cop0::TLBWR => self.tlbwr(),
+                    cop0::TLBP => self.tlbp(),
+                    cop0::WAIT if self.generation() == Generation::MIPS32 => self.wait(),
+                    _ => self.copz(Coproc::_0, cofun()),
+                },
+                _ => self.reserved_instruction(instr, Some(source() as u32)),
+            },
+            COP1 => match source() as u32 {
+                cop::MF => self.mfcz(Coproc::_1, target(), dest()),
+                cop::CF => self.cfcz(Coproc::_1, target(), dest()),
+                cop::MT => self.mtcz(Coproc::_1, target(), dest()),
+                cop::CT => self.ctcz(Coproc::_1, target(), dest()),
+                cop::BC => match target() as u32 {
+                    cop::BCF => self.bczf(Coproc::_1, imm()),
+                    cop::BCT => self.bczt(Coproc::_1, imm()),
+                    _ => self.reserved_instruction(instr, Some(target() as u32)),
+                },
+                x if (x & cop::CO) == cop::CO => self.copz(Coproc::_1, cofun()),
+                _ => self.reserved_instruction(instr, Some(source() as u32)),
+            },
+            COP2 => match source() as u32 {
+                cop::MF => self.mfcz(Coproc::_2, target(), dest()),
+                cop::CF => self.cfcz(Coproc::_2, target(), dest()),
+                cop::MT => self.mtcz(Coproc::_2, target(), dest()),
+                cop::CT => self.ctcz(Coproc::_2, target(), dest()),
+                cop::BC => match target() as u32 {
+                    cop::BCF => self.bczf(Coproc::_2, imm()),
+                    cop::BCT => self.bczt(Coproc::_2, imm()),
+                    _ => self.reserved_instruction(instr, Some(target() as u32)),
+                },
+                x if (x & cop::CO) == cop::CO => self.copz(Coproc::_2, cofun()),
+                _ => self.reserved_instruction(instr, Some(source() as u32)),
             },
-            0x11 => match source() {
-                0x00 => self.mfcz(Coproc::_1, target(), dest()),
-                0x02 => self.cfcz(Coproc::_1, target(), dest()),
-                0x04 => self.mtcz(Coproc::_1, target(), dest()),
-                0x06 => self.ctcz(Coproc::_1, target(), dest()),
-                x if (x & 0x10) == 0x10 => self.copz(Coproc::_1, cofun()),
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            COP3 => match source() as u32 {
+                cop::MF => self.mfcz(Coproc::_3, target(), dest()),
+                cop::CF => self.cfcz(Coproc::_3, target(), dest()),
+                cop::MT => self.mtcz(Coproc::_3, target(), dest()),
+                cop::CT => self.ctcz(Coproc::_3, target(), dest()),
+                cop::BC => match target() as u32 {
+                    cop::BCF => self.bczf(Coproc::_3, imm()),
+                    cop::BCT => self.bczt(Coproc::_3, imm()),
+                    _ => self.reserved_instruction(instr, Some(target() as u32)),
+                },
+                x if (x & cop::CO) == cop::CO => self.copz(Coproc::_3, cofun()),
+                _ => self.reserved_instruction(instr, Some(source() as u32)),
             },
-            0x12 => match source() {
-                0x00 => self.mfcz(Coproc::_2, target(), dest()),
-                0x02 => self.cfcz(Coproc::_2, target(), dest()),
-                0x04 => self.mtcz(Coproc::_2, target(), dest()),
-                0x06 => self.ctcz(Coproc::_2, target(), dest()),
-                x if (x & 0x10) == 0x10 => self.copz(Coproc::_2, cofun()),
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            SPECIAL2 => match special_op() {
+                special2::MADD => self.madd(source(), target()),
+                special2::MADDU => self.maddu(source(), target()),
+                special2::MUL => self.mul(source(), target(), dest()),
+                special2::MSUB => self.msub(source(), target()),
+                special2::MSUBU => self.msubu(source(), target()),
+                special2::CLZ => self.clz(source(), dest()),
+                special2::CLO => self.clo(source(), dest()),
+                special2::SDBBP => self.sdbbp(),
+                _ => self.reserved_instruction(instr, Some(special_op())),
             },
-            0x13 => match source() {
-                0x00 => self.mfcz(Coproc::_3, target(), dest()),
-                0x02 => self.cfcz(Coproc::_3, target(), dest()),
-                0x04 => self.mtcz(Coproc::_3, target(), dest()),
-                0x06 => self.ctcz(Coproc::_3, target(), dest()),
-                x if (x & 0x10) == 0x10 => self.copz(Coproc::_3, cofun()),
-                _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            SPECIAL3 => match special_op() {
+                // The rd field holds the last bit of the field: relative to pos for EXT, absolute for INS.
+                special3::EXT => self.ext(source(), target(), shift_amt(), dest() + 1),
+                special3::INS => self.ins(source(), target(), shift_amt(), (dest() + 1).saturating_sub(shift_amt())),
+                special3::BSHFL => match shift_amt() as u32 {
+                    special3::WSBH => self.wsbh(target(), dest()),
+                    special3::SEB => self.seb(target(), dest()),
+                    special3::SEH => self.seh(target(), dest()),
+                    _ => self.reserved_instruction(instr, Some(special_op())),
+                },
+                _ => self.reserved_instruction(instr, Some(special_op())),
             },
-            0x31 => self.lwcz(Coproc::_1, source(), target(), imm()),
-            0x32 => self.lwcz(Coproc::_2, source(), target(), imm()),
-            0x33 => self.lwcz(Coproc::_3, source(), target(), imm()),
 
-            0x39 => self.swcz(Coproc::_1, source(), target(), imm()),
-            0x3A => self.swcz(Coproc::_2, source(), target(), imm()),
-            0x3B => self.swcz(Coproc::_3, source(), target(), imm()),
+            LWC1 => self.lwcz(Coproc::_1, source(), target(), imm()),
+            LWC2 => self.lwcz(Coproc::_2, source(), target(), imm()),
+            CACHE if self.generation() == Generation::MIPS32 => self.cache(target(), source(), imm()),
+            PREF if self.generation() == Generation::MIPS32 => self.pref(source(), imm()),
+            LWC3 => self.lwcz(Coproc::_3, source(), target(), imm()),
 
-            _ => self.trigger_exception(ExceptionCode::ReservedInstruction),
+            SWC1 => self.swcz(Coproc::_1, source(), target(), imm()),
+            SWC2 => self.swcz(Coproc::_2, source(), target(), imm()),
+            SWC3 => self.swcz(Coproc::_3, source(), target(), imm()),
+
+            _ => self.reserved_instruction(instr, None),
         }
+
+        // A branch that vectored to an exception handler leaves no delay slot.
+        self.delay_slot = !self.redirected && has_delay_slot(instr);
+        self.hilo_busy = if writes_hilo(instr) {
+            self.config.hilo_delay
+        } else {
+            self.hilo_busy.saturating_sub(1)
+        };
+        self.retire_load();
+    }
+
+    fn read_pc(&self) -> u32 {
+        self.pc
+    }
+
+    fn write_pc(&mut self, addr: u32) {
+        self.pc = addr;
+        self.pc_next = addr.wrapping_add(4);
+        self.delay_slot = false;
+        self.redirected = true;
+        self.break_hit = false;
+        self.waiting = false;
+        self.halted = false;
+        self.mark_pc_dirty();
     }
-}
\ No newline at end of file
+
+    fn last_exception(&self) -> Option<ExceptionInfo> {
+        self.last_exception
+    }
+
+    fn in_delay_slot(&self) -> bool {
+        self.delay_slot
+    }
+
+    fn pending_branch_target(&self) -> Option<u32> {
+        self.delay_slot.then_some(self.pc_next)
+    }
+
+    fn add_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.insert(addr);
+    }
+
+    fn remove_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.remove(&addr);
+    }
+
+    fn breakpoints(&self) -> Vec<u32> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    fn raise_interrupt(&mut self, line: usize) {
+        self.interrupts.set_line(line, true);
+        self.coproc0.set_interrupt_line(line, true);
+    }
+
+    fn clear_interrupt(&mut self, line: usize) {
+        self.interrupts.set_line(line, false);
+        self.coproc0.set_interrupt_line(line, false);
+    }
+
+    fn pending_interrupts(&self) -> u8 {
+        self.coproc0.pending_interrupts()
+    }
+}
+
+/// Find the category of an instruction.
+/// 
+/// This follows the same decoding as `execute`. `SYNC` is classed as `Alu`,
+/// and opcode `0x33` (`LWC3` or `PREF`) and `CACHE` as `Load`.
+pub fn instruction_class(instr: u32) -> InstrClass {
+    let rs = (instr >> 21) & 0x1F;
+    let rt = (instr >> 16) & 0x1F;
+    match instr >> 26 {
+        SPECIAL => match instr & 0x3F {
+            funct::JR | funct::JALR => InstrClass::Jump,
+            funct::SYSCALL | funct::BREAK => InstrClass::Trap,
+            funct::TGE..=funct::TEQ | funct::TNE => InstrClass::Trap,
+            funct::SLL | funct::SRL..=funct::SLLV | funct::SRLV..=funct::SRAV => InstrClass::Alu,
+            funct::MOVZ | funct::MOVN | funct::SYNC => InstrClass::Alu,
+            funct::MFHI..=funct::MTLO | funct::MULT..=funct::DIVU => InstrClass::Alu,
+            funct::ADD..=funct::NOR | funct::SLT | funct::SLTU => InstrClass::Alu,
+            _ => InstrClass::Reserved,
+        },
+        REGIMM => match rt {
+            regimm::BLTZ | regimm::BGEZ | regimm::BLTZAL | regimm::BGEZAL => InstrClass::Branch,
+            regimm::TGEI..=regimm::TEQI | regimm::TNEI => InstrClass::Trap,
+            _ => InstrClass::Reserved,
+        },
+        J | JAL => InstrClass::Jump,
+        BEQ..=BGTZ => InstrClass::Branch,
+        ADDI..=LUI => InstrClass::Alu,
+        COP0..=COP3 => match rs {
+            cop::BC if rt == cop::BCF || rt == cop::BCT => InstrClass::Branch,
+            cop::MF | cop::MT => InstrClass::Coprocessor,
+            cop::CF | cop::CT if (instr >> 26) != COP0 => InstrClass::Coprocessor,
+            x if (x & cop::CO) == cop::CO => InstrClass::Coprocessor,
+            _ => InstrClass::Reserved,
+        },
+        SPECIAL2 => match instr & 0x3F {
+            special2::SDBBP => InstrClass::Trap,
+            special2::MADD..=special2::MUL | special2::MSUB | special2::MSUBU => InstrClass::Alu,
+            special2::CLZ | special2::CLO => InstrClass::Alu,
+            _ => InstrClass::Reserved,
+        },
+        SPECIAL3 => match instr & 0x3F {
+            special3::EXT | special3::INS => InstrClass::Alu,
+            special3::BSHFL => match (instr >> 6) & 0x1F {
+                special3::WSBH | special3::SEB | special3::SEH => InstrClass::Alu,
+                _ => InstrClass::Reserved,
+            },
+            _ => InstrClass::Reserved,
+        },
+        LB..=LWR | CACHE | LL..=LWC3 => InstrClass::Load,
+        SB..=SW | SWR | SC..=SWC3 => InstrClass::Store,
+        _ => InstrClass::Reserved,
+    }
+}
+
+/// Get the instruction set revision that added an instruction.
+/// 
+/// The generation decides what opcode `0x33`, `WAIT` and the rotates decode as.
+/// Reserved instructions are `IsaLevel::MipsI`, as they trap at every level.
+/// `MOVZ`, `MOVN` and `PREF` were added in MIPS IV, and are counted as `IsaLevel::Mips32`.
+pub fn required_isa_level(instr: u32, generation: Generation) -> IsaLevel {
+    let mips32 = generation == Generation::MIPS32;
+    match instr >> 26 {
+        SPECIAL => match instr & 0x3F {
+            funct::SYNC | funct::TGE..=funct::TEQ | funct::TNE => IsaLevel::MipsII,
+            funct::MOVZ | funct::MOVN => IsaLevel::Mips32,
+            funct::SRL if mips32 && ((instr >> 21) & 0x1F) == 1 => IsaLevel::Mips32R2,
+            funct::SRLV if mips32 && ((instr >> 6) & 0x1F) == 1 => IsaLevel::Mips32R2,
+            _ => IsaLevel::MipsI,
+        },
+        REGIMM => match (instr >> 16) & 0x1F {
+            regimm::TGEI..=regimm::TEQI | regimm::TNEI => IsaLevel::MipsII,
+            _ => IsaLevel::MipsI,
+        },
+        COP0 if mips32 && ((instr >> 21) & cop::CO) == cop::CO && (instr & 0x3F) == cop0::WAIT => IsaLevel::Mips32,
+        LL | SC => IsaLevel::MipsII,
+        CACHE => IsaLevel::MipsIII,
+        PREF if mips32 => IsaLevel::Mips32,
+        SPECIAL2 => IsaLevel::Mips32,
+        SPECIAL3 if instruction_class(instr) != InstrClass::Reserved => IsaLevel::Mips32R2,
+        _ => IsaLevel::MipsI,
+    }
+}
+
+// Check if an instruction is a branch or jump, and therefore has a delay slot.
+fn has_delay_slot(instr: u32) -> bool {
+    matches!(instruction_class(instr), InstrClass::Branch | InstrClass::Jump)
+}
+
+// Check if an instruction is a multiply or divide that puts its result in HI and LO.
+fn writes_hilo(instr: u32) -> bool {
+    match instr >> 26 {
+        SPECIAL => matches!(instr & 0x3F, funct::MULT..=funct::DIVU),
+        SPECIAL2 => matches!(instr & 0x3F, special2::MADD | special2::MADDU | special2::MSUB | special2::MSUBU),
+        _ => false,
+    }
+}
+
+// Check that the fields an instruction doesn't use are zero.
+// In MIPS32, the lowest bit of the field selects a rotate instead of SRL or SRLV.
+fn reserved_fields_clear(instr: u32, generation: Generation) -> bool {
+    const RS: u32 = 0x1F << 21;
+    const RT: u32 = 0x1F << 16;
+    const RD: u32 = 0x1F << 11;
+    const SHAMT: u32 = 0x1F << 6;
+    let rotate = if generation == Generation::MIPS32 { (1 << 21) | (1 << 6) } else { 0 };
+
+    let reserved = match instr >> 26 {
+        SPECIAL => match instr & 0x3F {
+            funct::SLL | funct::SRA => RS,
+            funct::SRL => RS & !rotate,
+            funct::SLLV | funct::SRAV => SHAMT,
+            funct::SRLV => SHAMT & !rotate,
+            funct::JR => RT | RD | SHAMT,
+            funct::JALR => RT | SHAMT,
+            funct::MOVZ | funct::MOVN => SHAMT,
+            funct::SYNC => RS | RT | RD,
+            funct::MFHI | funct::MFLO => RS | RT | SHAMT,
+            funct::MTHI | funct::MTLO => RT | RD | SHAMT,
+            funct::MULT..=funct::DIVU => RD | SHAMT,
+            funct::ADD..=funct::SLTU => SHAMT,
+            _ => 0,
+        },
+        BLEZ | BGTZ => RT,
+        LUI => RS,
+        SPECIAL2 => match instr & 0x3F {
+            special2::MADD | special2::MADDU | special2::MSUB | special2::MSUBU => RD | SHAMT,
+            special2::MUL | special2::CLZ | special2::CLO => SHAMT,
+            _ => 0,
+        },
+        SPECIAL3 => match instr & 0x3F {
+            special3::BSHFL => RS,
+            _ => 0,
+        },
+        _ => 0,
+    };
+    (instr & reserved) == 0
+}
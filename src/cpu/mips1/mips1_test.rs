@@ -1,19 +1,44 @@
 use crate::{
     coproc::*,
+    common::*,
     mem::*,
     cpu::mips1::*,
     cpu::MIPSICore,
-    cpu::MIPSCore
+    cpu::MIPSCore,
+    cpu::StepResult,
+    cpu::ExceptionCode,
+    cpu::ExceptionInfo,
+    cpu::DecodeError,
+    cpu::TrapMode,
+    cpu::Generation,
+    cpu::IsaLevel,
+    cpu::InstrClass,
+    cpu::InterruptController,
+    cpu::RunOutcome,
+    cpu::SyscallHandler,
+    cpu::SyscallOutcome,
+    cpu::Translation
 };
 
 struct LittleMemTest {
-    bytes: Vec<u8>
+    bytes: Vec<u8>,
+    syncs: usize,
+    prefetches: Vec<u32>,
+    cache_ops: Vec<(u32, u32)>,
+    acknowledged: Vec<usize>,
+    // Addresses which take extra cycles to access.
+    slow: core::ops::Range<u32>,
 }
 
 impl LittleMemTest {
     fn new(size: usize) -> Self {
         Self {
-            bytes: vec![0; size]
+            bytes: vec![0; size],
+            syncs: 0,
+            prefetches: Vec::new(),
+            cache_ops: Vec::new(),
+            acknowledged: Vec::new(),
+            slow: 0..0,
         }
     }
 }
@@ -28,14 +53,189 @@ impl Memory for LittleMemTest {
     fn write_byte(&mut self, addr: Self::Addr, data: u8) {
         self.bytes[addr as usize] = data;
     }
+
+    fn access_cycles(&self, addr: Self::Addr, _is_write: bool) -> u32 {
+        if self.slow.contains(&addr) { 4 } else { 1 }
+    }
+
+    fn on_sync(&mut self) {
+        self.syncs += 1;
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        self.prefetches.push(addr);
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        self.cache_ops.push((op, addr));
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        self.acknowledged.push(line);
+    }
 }
 
 impl_mem_32_little!{ LittleMemTest }
 
+// Memory backed by a fixed array, with no heap storage of its own.
+struct ArrayMemTest {
+    bytes: [u8; 0x100]
+}
+
+impl Memory for ArrayMemTest {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.bytes[addr as usize]
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.bytes[addr as usize] = data;
+    }
+}
+
+impl_mem_32_little!{ ArrayMemTest }
+
+// Memory that only responds to addresses below 0x1000.
+struct UnmappedMemTest {
+    bytes: Vec<u8>
+}
+
+impl UnmappedMemTest {
+    fn map(&mut self, addr: u32) -> Result<usize, MemError> {
+        if (addr as usize) < self.bytes.len() {
+            Ok(addr as usize)
+        } else {
+            Err(MemError::BusError)
+        }
+    }
+}
+
+impl Memory for UnmappedMemTest {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.try_read_byte(addr).unwrap()
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.try_write_byte(addr, data).unwrap()
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        let i = self.map(addr)?;
+        Ok(self.bytes[i])
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        let i = self.map(addr)?;
+        self.bytes[i] = data;
+        Ok(())
+    }
+}
+
+impl Mem16 for UnmappedMemTest {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.try_read_halfword(addr).unwrap()
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.try_write_halfword(addr, data).unwrap()
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        Ok(make16(self.try_read_byte(addr)?, self.try_read_byte(addr + 1)?))
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        self.try_write_byte(addr, lo16(data))?;
+        self.try_write_byte(addr + 1, hi16(data))
+    }
+
+    fn little_endian(&self) -> bool {
+        true
+    }
+}
+
+impl Mem32 for UnmappedMemTest {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.try_read_word(addr).unwrap()
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.try_write_word(addr, data).unwrap()
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        let lo = self.try_read_halfword(addr)?;
+        let hi = self.try_read_halfword(addr + 2)?;
+        Ok(((hi as u32) << 16) | (lo as u32))
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        self.try_write_halfword(addr, data as u16)?;
+        self.try_write_halfword(addr + 2, (data >> 16) as u16)
+    }
+}
+
+// Memory that counts instruction fetches and data reads.
+struct CountingMemTest {
+    mem:        LittleMemTest,
+    fetches:    usize,
+    reads:      usize,
+}
+
+impl Memory for CountingMemTest {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.reads += 1;
+        self.mem.read_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.mem.write_byte(addr, data);
+    }
+}
+
+impl Mem16 for CountingMemTest {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.reads += 1;
+        self.mem.read_halfword(addr)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.mem.write_halfword(addr, data);
+    }
+
+    fn little_endian(&self) -> bool {
+        true
+    }
+}
+
+impl Mem32 for CountingMemTest {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.reads += 1;
+        self.mem.read_word(addr)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.mem.write_word(addr, data);
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.fetches += 1;
+        Ok(self.mem.read_word(addr))
+    }
+}
+
 #[derive(Default)]
 struct TestCoproc {
     control_reg:    [u32; 32],
     data_reg:       [u32; 32],
+    condition:      bool,
+    flags:          u32,
+    resets:         usize,
 }
 
 impl Coprocessor for TestCoproc {
@@ -63,13 +263,56 @@ impl Coprocessor for TestCoproc {
     // For testing purposes:
     // op "1" adds data reg 1 & 2 together, and stores result in 3
     // op "2" multiplies data reg 4 & 5 together, and stores result in 6
+    // op "3" compares data reg 1 & 2, setting the condition if equal and flag bit 0 if not
     fn operation(&mut self, op: u32) {
         match op {
             1 => self.data_reg[3] = self.data_reg[1] + self.data_reg[2],
             2 => self.data_reg[6] = self.data_reg[4] * self.data_reg[5],
+            3 => {
+                self.condition = self.data_reg[1] == self.data_reg[2];
+                self.flags = if self.condition { 0 } else { 1 };
+            },
             _ => {}
         }
     }
+
+    fn condition_code(&self) -> bool {
+        self.condition
+    }
+
+    fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    fn reset(&mut self) {
+        *self = Self { resets: self.resets + 1, ..Self::default() };
+    }
+}
+
+#[derive(Default)]
+struct TestCoproc0 {
+    data_reg:   [u32; 32],
+    exceptions: Vec<ExceptionCode>,
+    resets:     usize,
+}
+
+impl Coprocessor0 for TestCoproc0 {
+    fn move_from_reg(&mut self, reg: usize) -> u32 {
+        self.data_reg[reg]
+    }
+    fn move_to_reg(&mut self, reg: usize, val: u32) {
+        self.data_reg[reg] = val;
+    }
+
+    fn operation(&mut self, _: u32) {}
+
+    fn handle_exception(&mut self, exception: ExceptionCode, _epc: u32, _branch_delay: bool) {
+        self.exceptions.push(exception);
+    }
+
+    fn reset(&mut self) {
+        self.resets += 1;
+    }
 }
 
 impl MIPSI<LittleMemTest, EmptyCoproc0, TestCoproc, EmptyCoproc, EmptyCoproc> {
@@ -80,10 +323,76 @@ impl MIPSI<LittleMemTest, EmptyCoproc0, TestCoproc, EmptyCoproc, EmptyCoproc> {
     }
 }
 
+impl MIPSI<LittleMemTest, TestCoproc0, TestCoproc, EmptyCoproc, EmptyCoproc> {
+    // A CPU that records the exceptions it triggers.
+    fn with_test_coproc0() -> Self {
+        Self::with_memory(Box::new(LittleMemTest::new(0x1000)))
+            .add_coproc0(TestCoproc0::default())
+            .add_coproc1(TestCoproc::default())
+            .build()
+    }
+}
+
 fn make_i_instr(instr: u32, src: u32, tgt: u32, imm: u32) -> u32 {
     (instr << 26) | (src << 21) | (tgt << 16) | imm
 }
 
+#[test]
+fn opcode_constants() {
+    use opcodes::*;
+
+    // addiu $1, $0, 16; lui $8, 0x8000; lw $31, 16($29); sw $31, 16($29)
+    assert_eq!(make_i_instr(ADDIU, 0, 1, 0x10), 0x2401_0010);
+    assert_eq!(make_i_instr(LUI, 0, 8, 0x8000), 0x3C08_8000);
+    assert_eq!(make_i_instr(LW, 29, 31, 0x10), 0x8FBF_0010);
+    assert_eq!(make_i_instr(SW, 29, 31, 0x10), 0xAFBF_0010);
+    // add $3, $1, $2; mtlo $1; bgezal $1, 4
+    assert_eq!(make_i_instr(SPECIAL, 1, 2, (3 << 11) | funct::ADD), 0x0022_1820);
+    assert_eq!(make_i_instr(SPECIAL, 1, 0, funct::MTLO), 0x0020_0013);
+    assert_eq!(make_i_instr(REGIMM, 1, regimm::BGEZAL, 4), 0x0431_0004);
+
+    // Each of mthi and mtlo decode to the right instruction.
+    let mut cpu = MIPSI::default();
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(2, 0x5678);
+    cpu.execute(make_i_instr(SPECIAL, 1, 0, funct::MTHI));
+    cpu.execute(make_i_instr(SPECIAL, 2, 0, funct::MTLO));
+    assert_eq!(cpu.read_hi(), 0x1234);
+    assert_eq!(cpu.read_lo(), 0x5678);
+}
+
+#[test]
+fn instr_class() {
+    use opcodes::*;
+
+    // beq $1, $2, 4; bc1t 4
+    assert_eq!(instruction_class(make_i_instr(BEQ, 1, 2, 4)), InstrClass::Branch);
+    assert_eq!(instruction_class(make_i_instr(COP1, cop::BC, cop::BCT, 4)), InstrClass::Branch);
+    // j 0x100; jr $31
+    assert_eq!(instruction_class((J << 26) | 0x40), InstrClass::Jump);
+    assert_eq!(instruction_class(make_i_instr(SPECIAL, 31, 0, funct::JR)), InstrClass::Jump);
+    // lw $31, 16($29); lwc2 $1, 0($2); sw $31, 16($29); sc $1, 0($2)
+    assert_eq!(instruction_class(make_i_instr(LW, 29, 31, 0x10)), InstrClass::Load);
+    assert_eq!(instruction_class(make_i_instr(LWC2, 2, 1, 0)), InstrClass::Load);
+    assert_eq!(instruction_class(make_i_instr(SW, 29, 31, 0x10)), InstrClass::Store);
+    assert_eq!(instruction_class(make_i_instr(SC, 2, 1, 0)), InstrClass::Store);
+    // add $3, $1, $2; lui $8, 0x8000; clz $3, $1
+    assert_eq!(instruction_class(make_i_instr(SPECIAL, 1, 2, (3 << 11) | funct::ADD)), InstrClass::Alu);
+    assert_eq!(instruction_class(make_i_instr(LUI, 0, 8, 0x8000)), InstrClass::Alu);
+    assert_eq!(instruction_class(make_i_instr(SPECIAL2, 1, 0, (3 << 11) | special2::CLZ)), InstrClass::Alu);
+    // mfc0 $1, $12; rfe
+    assert_eq!(instruction_class(make_i_instr(COP0, cop::MF, 1, 12 << 11)), InstrClass::Coprocessor);
+    assert_eq!(instruction_class(make_i_instr(COP0, cop::CO, 0, cop0::RFE)), InstrClass::Coprocessor);
+    // syscall; teqi $1, 0; sdbbp
+    assert_eq!(instruction_class(make_i_instr(SPECIAL, 0, 0, funct::SYSCALL)), InstrClass::Trap);
+    assert_eq!(instruction_class(make_i_instr(REGIMM, 1, regimm::TEQI, 0)), InstrClass::Trap);
+    assert_eq!(instruction_class(make_i_instr(SPECIAL2, 0, 0, special2::SDBBP)), InstrClass::Trap);
+    // Unused opcode, SPECIAL function and cfc0.
+    assert_eq!(instruction_class(0x14 << 26), InstrClass::Reserved);
+    assert_eq!(instruction_class(make_i_instr(SPECIAL, 0, 0, 0x01)), InstrClass::Reserved);
+    assert_eq!(instruction_class(make_i_instr(COP0, cop::CF, 1, 0)), InstrClass::Reserved);
+}
+
 // TODO: make this a benchmark.
 #[test]
 fn add_speed() {
@@ -107,6 +416,24 @@ fn add_speed() {
     //assert_eq!(cpu.read_gp(1), 1_000_000);
 }
 
+#[test]
+fn fetch_fast_path() {
+    let mut slow = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    let mut fast = MIPSI::<RamMemory<0x1000>>::with_memory(Box::new(RamMemory::new())).build();
+    assert!(slow.mem().as_bytes().is_none());
+    assert!(fast.mem().as_bytes().is_some());
+
+    for addr in (0..0x100).step_by(4) {
+        let word = addr * 0x0101_0101 + 0x1234;
+        slow.mem().write_word(addr, word);
+        fast.mem().write_word(addr, word);
+    }
+    for addr in (0..0x100).step_by(4) {
+        assert_eq!(slow.fetch_word(addr), fast.fetch_word(addr));
+    }
+    assert_eq!(fast.fetch_word(0x10), Some(0x10 * 0x0101_0101 + 0x1234));
+}
+
 #[test]
 fn add() {
     let mut cpu = MIPSI::default();
@@ -119,10 +446,105 @@ fn add() {
     let mut cpu = MIPSI::default();
 
     // Test overflow.
+    cpu.write_gp(1, 0x7FFFFFFF);
+    cpu.write_gp(2, 0x5);
+    cpu.add(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 0);
+
+    let mut cpu = MIPSI::default();
+
+    // Negative operands don't overflow.
     cpu.write_gp(1, 0xFFFFFFFF);
     cpu.write_gp(2, 0x5);
     cpu.add(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 4);
+}
+
+#[test]
+fn try_add() {
+    let mut cpu = MIPSI::default();
+
+    // The largest sum that doesn't overflow.
+    cpu.write_gp(1, 0x7FFF_FFFF);
+    cpu.write_gp(2, 0);
+    assert_eq!(cpu.try_add(1, 2, 3), Ok(()));
+    assert_eq!(cpu.read_gp(3), 0x7FFF_FFFF);
+    assert_eq!(cpu.try_addi(1, 4, 0), Ok(()));
+    assert_eq!(cpu.read_gp(4), 0x7FFF_FFFF);
+
+    cpu.write_gp(2, 1);
+    assert_eq!(cpu.try_add(1, 2, 5), Err(ExceptionCode::ArithmeticOverflow));
+    assert_eq!(cpu.read_gp(5), 0);
+    assert_eq!(cpu.try_addi(1, 5, 1), Err(ExceptionCode::ArithmeticOverflow));
+    assert_eq!(cpu.read_gp(5), 0);
+
+    cpu.write_gp(1, 0x8000_0000);
+    assert_eq!(cpu.try_sub(1, 0, 6), Ok(()));
+    assert_eq!(cpu.read_gp(6), 0x8000_0000);
+    assert_eq!(cpu.try_sub(1, 2, 7), Err(ExceptionCode::ArithmeticOverflow));
+    assert_eq!(cpu.read_gp(7), 0);
+
+    // Lenient mode wraps instead of trapping.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_trap_mode(TrapMode::Lenient)
+        .build();
+    cpu.write_gp(1, 0x7FFF_FFFF);
+    cpu.write_gp(2, 1);
+    assert_eq!(cpu.try_add(1, 2, 3), Ok(()));
+    assert_eq!(cpu.read_gp(3), 0x8000_0000);
+}
+
+#[test]
+fn strict_decode() {
+    // add $3, $1, $2 with a shift amount of 1
+    let instr = (1 << 21) | (2 << 16) | (3 << 11) | (1 << 6) | 0x20;
+
+    let mut cpu = MIPSI::with_test_coproc0();
+    cpu.write_gp(1, 1);
+    cpu.write_gp(2, 2);
+    cpu.execute(instr);
+    assert_eq!(cpu.read_gp(3), 3);
+    assert!(cpu.coproc_0().exceptions.is_empty());
+
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(TestCoproc0::default())
+        .with_strict_decode()
+        .build();
+    cpu.write_gp(1, 1);
+    cpu.write_gp(2, 2);
+    cpu.execute(instr);
+    assert_eq!(cpu.read_gp(3), 0);
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::ReservedInstruction]);
+
+    // jr $1 with rd set
+    cpu.execute((1 << 21) | (4 << 11) | 0x08);
+    assert!(!cpu.in_delay_slot());
+    assert_eq!(cpu.coproc_0().exceptions.len(), 2);
+
+    cpu.execute(instr & !(1 << 6));
+    assert_eq!(cpu.read_gp(3), 3);
+    assert_eq!(cpu.coproc_0().exceptions.len(), 2);
+}
+
+#[test]
+fn add_trap_mode() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_trap_mode(TrapMode::Strict)
+        .build();
+
+    cpu.write_gp(1, 0x7FFFFFFF);
+    cpu.write_gp(2, 0x1);
+    cpu.add(1, 2, 3);
     assert_eq!(cpu.read_gp(3), 0);
+
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_trap_mode(TrapMode::Lenient)
+        .build();
+
+    cpu.write_gp(1, 0x7FFFFFFF);
+    cpu.write_gp(2, 0x1);
+    cpu.add(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 0x80000000);
 }
 
 #[test]
@@ -136,7 +558,7 @@ fn addi() {
     let mut cpu = MIPSI::default();
 
     // Test overflow.
-    cpu.write_gp(1, 0x10000);
+    cpu.write_gp(1, 0x80000000);
     cpu.addi(1, 2, 0x8000);
     assert_eq!(cpu.read_gp(2), 0);
 }
@@ -187,8 +609,8 @@ fn sub() {
     let mut cpu = MIPSI::default();
 
     // Test overflow.
-    cpu.write_gp(1, 0xFFFFFFFE);
-    cpu.write_gp(2, 0xFFFFFFFF);
+    cpu.write_gp(1, 0x80000000);
+    cpu.write_gp(2, 0x1);
     cpu.sub(1, 2, 3);
     assert_eq!(cpu.read_gp(3), 0);
 }
@@ -212,6 +634,36 @@ fn subu() {
     assert_eq!(cpu.read_gp(3), -1i32 as u32);
 }
 
+#[test]
+fn immediate_extension() {
+    let mut cpu = MIPSI::default();
+    cpu.write_gp(1, 0x1234_0010);
+
+    // Arithmetic and comparisons sign-extend the immediate.
+    cpu.addiu(1, 2, 0xFFFF);
+    assert_eq!(cpu.read_gp(2), 0x1234_000F);
+    cpu.addi(1, 2, 0x8000);
+    assert_eq!(cpu.read_gp(2), 0x1233_8010);
+    cpu.slti(1, 2, 0x8000);
+    assert_eq!(cpu.read_gp(2), 0);
+    cpu.sltiu(1, 2, 0x8000);
+    assert_eq!(cpu.read_gp(2), 1);
+
+    // Logic zero-extends it.
+    cpu.ori(1, 2, 0xFFFF);
+    assert_eq!(cpu.read_gp(2), 0x1234_FFFF);
+    cpu.andi(1, 2, 0x8010);
+    assert_eq!(cpu.read_gp(2), 0x0000_0010);
+    cpu.xori(1, 2, 0xFFFF);
+    assert_eq!(cpu.read_gp(2), 0x1234_FFEF);
+
+    // The same through the decoder: addiu $2, $1, -1; ori $3, $1, 0xFFFF
+    cpu.execute(make_i_instr(0x09, 1, 2, 0xFFFF));
+    cpu.execute(make_i_instr(0x0D, 1, 3, 0xFFFF));
+    assert_eq!(cpu.read_gp(2), 0x1234_000F);
+    assert_eq!(cpu.read_gp(3), 0x1234_FFFF);
+}
+
 #[test]
 fn mult() {
     let mut cpu = MIPSI::default();
@@ -231,6 +683,172 @@ fn mult() {
     assert_eq!(cpu.read_hi(), 0xFFFF_FFFF);
 }
 
+// Syscall 1 succeeds and returns 0 in $v0, syscall 10 exits with the code in $a0.
+struct ExitHandler {
+    exit_code:  std::rc::Rc<std::cell::Cell<Option<u32>>>,
+}
+
+impl<Mem: Mem32> SyscallHandler<Mem> for ExitHandler {
+    fn syscall(&mut self, gp_reg: &mut [u32; 32], _mem: &mut Mem) -> SyscallOutcome {
+        match gp_reg[2] {
+            1 => {
+                gp_reg[2] = 0;
+                SyscallOutcome::Handled
+            },
+            10 => {
+                self.exit_code.set(Some(gp_reg[4]));
+                SyscallOutcome::Halt
+            },
+            _ => SyscallOutcome::Unhandled,
+        }
+    }
+}
+
+#[test]
+fn syscall_handler() {
+    let exit_code = std::rc::Rc::new(std::cell::Cell::new(None));
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_syscall_handler(ExitHandler { exit_code: exit_code.clone() })
+        .build();
+
+    let syscall = 0x0000_000C;
+    cpu.mem().write_word(0x0, make_i_instr(0x09, 0, 2, 1));    // addiu $v0, $zero, 1
+    cpu.mem().write_word(0x4, syscall);
+    cpu.mem().write_word(0x8, make_i_instr(0x09, 0, 2, 99));   // addiu $v0, $zero, 99
+    cpu.mem().write_word(0xC, syscall);
+    cpu.mem().write_word(0x10, make_i_instr(0x09, 0, 2, 10));  // addiu $v0, $zero, 10
+    cpu.mem().write_word(0x14, make_i_instr(0x09, 0, 4, 42));  // addiu $a0, $zero, 42
+    cpu.mem().write_word(0x18, syscall);
+
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0);
+
+    // Unhandled syscalls trigger the exception.
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::Syscall));
+
+    assert_eq!(cpu.run(), StepResult::Halted);
+    assert_eq!(exit_code.get(), Some(42));
+    assert_eq!(cpu.read_pc(), 0x1C);
+    assert_eq!(cpu.step(), StepResult::Halted);
+}
+
+#[test]
+fn coproc_observer() {
+    let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut cpu = MIPSI::default();
+    let observer_seen = seen.clone();
+    cpu.set_coproc_observer(Box::new(move |coproc, cofun| observer_seen.borrow_mut().push((coproc, cofun))));
+
+    // cop1 1: adds data regs 1 and 2 into 3.
+    cpu.coproc_1().unwrap().data_reg[1] = 2;
+    cpu.coproc_1().unwrap().data_reg[2] = 3;
+    cpu.execute((0x11 << 26) | (1 << 25) | 1);
+    assert_eq!(cpu.coproc_1().unwrap().data_reg[3], 5);
+    cpu.execute((0x11 << 26) | (1 << 25) | 0x1AB_CDEF);
+    // Coprocessor 2 is missing, so nothing is dispatched.
+    cpu.execute((0x12 << 26) | (1 << 25) | 1);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::CoProcUnusable));
+    assert_eq!(*seen.borrow(), vec![(1, 1), (1, 0x1AB_CDEF)]);
+
+    cpu.clear_coproc_observer();
+    cpu.execute((0x11 << 26) | (1 << 25) | 1);
+    assert_eq!(seen.borrow().len(), 2);
+}
+
+#[test]
+fn exception_handler() {
+    let counts = std::rc::Rc::new(std::cell::RefCell::new([0usize; 32]));
+    let mut cpu = MIPSI::<LittleMemTest, StandardCoproc0>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_translation(Translation::Flat)
+        .with_exception_vectoring()
+        .build();
+    let handler_counts = counts.clone();
+    cpu.set_exception_handler(Box::new(move |cpu, code| {
+        handler_counts.borrow_mut()[code as usize] += 1;
+        // Return a value from syscalls, as a user-mode emulator would.
+        if code == ExceptionCode::Syscall {
+            cpu.write_gp(2, 0x55);
+        }
+        assert_eq!(cpu.last_exception().map(|e| e.code), Some(code));
+    }));
+
+    cpu.mem().write_word(0x0, 0x0000_000C);                    // syscall
+    cpu.mem().write_word(0x4, 0x0000_000D);                    // break
+    cpu.mem().write_word(0x8, 0x0022_1820);                    // add $3, $1, $2
+    cpu.mem().write_word(0xC, 0x0000_000C);                    // syscall
+    cpu.mem().write_word(0x10, make_i_instr(0x23, 0, 4, 0x2)); // lw $4, 2($zero)
+    cpu.write_gp(1, 0x7FFF_FFFF);
+    cpu.write_gp(2, 1);
+
+    for _ in 0..5 {
+        cpu.step();
+    }
+    let counts = counts.borrow();
+    assert_eq!(counts[ExceptionCode::Syscall as usize], 2);
+    assert_eq!(counts[ExceptionCode::Breakpoint as usize], 1);
+    assert_eq!(counts[ExceptionCode::ArithmeticOverflow as usize], 1);
+    assert_eq!(counts[ExceptionCode::AddrErrorLoad as usize], 1);
+    assert_eq!(cpu.last_exception().and_then(|e| e.bad_addr), Some(0x2));
+
+    // The handler took precedence: coprocessor 0 wasn't told, and the CPU didn't vector.
+    assert_eq!(cpu.read_pc(), 0x14);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::EPC), 0);
+    assert_eq!(cpu.read_gp(2), 0x55);
+    assert_eq!(cpu.read_gp(3), 0);
+
+    // Without a handler, exceptions go to coprocessor 0 again.
+    cpu.clear_exception_handler();
+    cpu.mem().write_word(0x14, 0x0000_000C);
+    cpu.step();
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::EPC), 0x14);
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+}
+
+#[test]
+fn hilo_hazards() {
+    let mult = make_i_instr(0, 1, 2, 0x18);
+    let mfhi = (3 << 11) | 0x10;
+    let mflo = (4 << 11) | 0x12;
+
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_hilo_hazards(2)
+        .build();
+    cpu.write_gp(1, 0xFFFF_FFFF);
+    cpu.write_gp(2, 0x3);
+
+    // Reading within two instructions gives the undefined marker.
+    cpu.execute(mult);
+    cpu.execute(mfhi);
+    cpu.execute(mflo);
+    assert_eq!(cpu.read_gp(3), HILO_UNDEFINED);
+    assert_eq!(cpu.read_gp(4), HILO_UNDEFINED);
+    cpu.execute(mflo);
+    assert_eq!(cpu.read_gp(4), 0xFFFF_FFFD);
+
+    // A new multiply restarts the count.
+    cpu.execute(mult);
+    cpu.execute(0);
+    cpu.execute(mult);
+    cpu.execute(0);
+    cpu.execute(mfhi);
+    assert_eq!(cpu.read_gp(3), HILO_UNDEFINED);
+    cpu.execute(mfhi);
+    assert_eq!(cpu.read_gp(3), 0xFFFF_FFFF);
+
+    // By default results are ready immediately.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100))).build();
+    cpu.write_gp(1, 0xFFFF_FFFF);
+    cpu.write_gp(2, 0x3);
+    cpu.execute(mult);
+    cpu.execute(mflo);
+    assert_eq!(cpu.read_gp(4), 0xFFFF_FFFD);
+}
+
 #[test]
 fn multu() {
     let mut cpu = MIPSI::default();
@@ -267,6 +885,26 @@ fn div() {
     cpu.div(1, 2);
     assert_eq!(cpu.read_lo(), 0);
     assert_eq!(cpu.read_hi(), 0xFFFF_FFFF);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.write_gp(2, 0xFFFF_FFFF);
+    cpu.div(1, 2);
+    assert_eq!(cpu.read_lo(), 0x8000_0000);
+    assert_eq!(cpu.read_hi(), 0);
+
+    // Divide by zero.
+    cpu.write_gp(1, 5);
+    cpu.write_gp(2, 0);
+    cpu.div(1, 2);
+    assert_eq!(cpu.read_lo(), 0xFFFF_FFFF);
+    assert_eq!(cpu.read_hi(), 5);
+
+    cpu.write_gp(1, 0xFFFF_FFFB);
+    cpu.div(1, 2);
+    assert_eq!(cpu.read_lo(), 1);
+    assert_eq!(cpu.read_hi(), 0xFFFF_FFFB);
 }
 
 #[test]
@@ -286,6 +924,12 @@ fn divu() {
     cpu.divu(1, 2);
     assert_eq!(cpu.read_lo(), 1);
     assert_eq!(cpu.read_hi(), 1);
+
+    // Divide by zero.
+    cpu.write_gp(2, 0);
+    cpu.divu(1, 2);
+    assert_eq!(cpu.read_lo(), 0xFFFF_FFFF);
+    assert_eq!(cpu.read_hi(), 0xFFFF_FFFF);
 }
 
 #[test]
@@ -356,31 +1000,201 @@ fn nor() {
 }
 
 #[test]
-fn sll() {
-    let mut cpu = MIPSI::default();
-
-    cpu.write_gp(1, 0x3);
-    cpu.sll(1, 8, 2);
-    assert_eq!(cpu.read_gp(2), 768);
-
+fn save_state() {
     let mut cpu = MIPSI::default();
 
-    cpu.write_gp(1, 0x1234_5678);
-    cpu.sll(1, 16, 2);
-    assert_eq!(cpu.read_gp(2), 0x5678_0000);
+    cpu.mem().write_word(0, make_i_instr(0x04, 0, 0, 0x40));
+    cpu.write_gp(1, 0x1234);
+    cpu.write_hi(5);
+    cpu.step();
+    let state = cpu.save_state();
+    assert_eq!(state.gp_reg[1], 0x1234);
+    assert_eq!(state.pc_next, 0x104);
+    assert!(state.delay_slot);
+
+    let mut other = MIPSI::default();
+    other.load_state(&state);
+    assert_eq!(other.read_gp(1), 0x1234);
+    assert_eq!(other.read_hi(), 5);
+    other.step();
+    assert_eq!(other.read_pc(), 0x104);
 }
 
 #[test]
-fn srl() {
-    let mut cpu = MIPSI::default();
+fn save_state_coprocessors() {
+    let make = || MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    let mut cpu = make();
+    cpu.coproc_0().move_to_reg(cp0_reg::STATUS, STATUS_IEC);
+    cpu.coproc_0().move_to_reg(cp0_reg::EPC, 0x8000_0040);
+    let state = cpu.save_state();
+    assert!(!state.coprocs[0].is_empty());
+    assert!(state.coprocs[1].is_empty());
+
+    let mut restored = make();
+    restored.load_state(&state);
+    assert_eq!(restored.coproc_0().move_from_reg(cp0_reg::STATUS), STATUS_IEC);
+    assert_eq!(restored.coproc_0().move_from_reg(cp0_reg::EPC), 0x8000_0040);
+}
 
-    cpu.write_gp(1, 0xFEDC_BA98);
-    cpu.srl(1, 8, 2);
-    assert_eq!(cpu.read_gp(2), 0xFE_DCBA);
+#[test]
+fn save_state_mid_branch() {
+    let program = [
+        make_i_instr(0x09, 0, 1, 0x1),  // addiu $1, $0, 1
+        make_i_instr(0x04, 0, 0, 0x3),  // beq $0, $0, 3
+        make_i_instr(0x09, 0, 2, 0x2),  // addiu $2, $0, 2
+        make_i_instr(0x09, 0, 3, 0x3),  // addiu $3, $0, 3
+        0,
+        make_i_instr(0x09, 0, 4, 0x4),  // addiu $4, $0, 4
+    ];
+    let load = |cpu: &mut MIPSI<LittleMemTest, EmptyCoproc0, TestCoproc, EmptyCoproc, EmptyCoproc>| {
+        for (i, instr) in program.iter().enumerate() {
+            cpu.mem().write_word((i * 4) as u32, *instr);
+        }
+    };
 
     let mut cpu = MIPSI::default();
-
-    cpu.write_gp(1, 0x1234_5678);
+    load(&mut cpu);
+    cpu.step();
+    assert_eq!(cpu.pending_branch_target(), None);
+    cpu.step();
+    assert!(cpu.in_delay_slot());
+    assert_eq!(cpu.pending_branch_target(), Some(0x14));
+
+    let state = cpu.save_state();
+    assert_eq!(state.pending_branch_target(), Some(0x14));
+    let mut restored = MIPSI::default();
+    load(&mut restored);
+    restored.load_state(&state);
+    assert_eq!(restored.pending_branch_target(), Some(0x14));
+
+    for _ in 0..2 {
+        cpu.step();
+        restored.step();
+        assert_eq!(restored.save_state(), cpu.save_state());
+    }
+    assert_eq!(restored.read_gp(2), 2);
+    assert_eq!(restored.read_gp(3), 0);
+    assert_eq!(restored.read_gp(4), 4);
+    assert_eq!(restored.pending_branch_target(), None);
+}
+
+#[test]
+fn save_state_waiting() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_generation(Generation::MIPS32)
+        .with_hilo_hazards(4)
+        .build();
+    // mult $1, $2; wait
+    cpu.mem().write_word(0, 0x0022_0018);
+    cpu.mem().write_word(4, 0x4200_0020);
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.step(), StepResult::Waiting);
+
+    let state = cpu.save_state();
+    assert!(state.waiting);
+    assert_ne!(state.hilo_busy, 0);
+    let mut restored = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_generation(Generation::MIPS32)
+        .with_hilo_hazards(4)
+        .build();
+    restored.load_state(&state);
+    assert_eq!(restored.step(), StepResult::Waiting);
+    assert!(!restored.hilo_ready());
+    assert_eq!(restored.cycles(), cpu.cycles());
+}
+
+#[test]
+fn zero_register() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(0, 0x1234);
+    assert_eq!(cpu.read_gp(0), 0);
+
+    // Even a restored state with a non-zero $0 reads as zero.
+    let mut state = cpu.save_state();
+    state.gp_reg[0] = 0xDEAD_BEEF;
+    cpu.load_state(&state);
+    assert_eq!(cpu.read_gp(0), 0);
+    cpu.addiu(0, 1, 1);
+    assert_eq!(cpu.read_gp(1), 1);
+}
+
+// A register that counts reads, so the side effect of a load can be seen.
+#[derive(Default)]
+struct CountingRegister {
+    reads: usize,
+}
+
+impl Registers for CountingRegister {
+    fn read_register(&mut self, _offset: u32) -> u32 {
+        self.reads += 1;
+        0x1234_5678
+    }
+
+    fn write_register(&mut self, _offset: u32, _data: u32) {}
+}
+
+#[test]
+fn load_to_zero_register() {
+    let mut cpu = MIPSI::<MmioDevice<CountingRegister>>::with_memory(Box::new(MmioDevice::new(CountingRegister::default()))).build();
+
+    // lw $0, 0($1): the read still happens, but $0 stays zero.
+    cpu.write_gp(1, 0x10);
+    cpu.execute(make_i_instr(0x23, 1, 0, 0));
+    assert_eq!(cpu.mem().device().reads, 1);
+    assert_eq!(cpu.read_gp(0), 0);
+    assert_eq!(cpu.last_exception(), None);
+
+    // lw $2, 4($1)
+    cpu.execute(make_i_instr(0x23, 1, 2, 4));
+    assert_eq!(cpu.mem().device().reads, 2);
+    assert_eq!(cpu.read_gp(2), 0x1234_5678);
+
+    cpu.lw(1, 0, 8);
+    assert_eq!(cpu.mem().device().reads, 3);
+    assert_eq!(cpu.read_gp(0), 0);
+}
+
+#[test]
+fn sll() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x3);
+    cpu.sll(1, 8, 2);
+    assert_eq!(cpu.read_gp(2), 768);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x1234_5678);
+    cpu.sll(1, 16, 2);
+    assert_eq!(cpu.read_gp(2), 0x5678_0000);
+
+    // Only the bottom 5 bits of the shift amount are used.
+    cpu.write_gp(1, 0x3);
+    cpu.sll(1, 40, 2);
+    assert_eq!(cpu.read_gp(2), 768);
+    cpu.srl(2, 40, 3);
+    assert_eq!(cpu.read_gp(3), 0x3);
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.sra(1, 36, 2);
+    assert_eq!(cpu.read_gp(2), 0xF800_0000);
+}
+
+#[test]
+fn srl() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0xFEDC_BA98);
+    cpu.srl(1, 8, 2);
+    assert_eq!(cpu.read_gp(2), 0xFE_DCBA);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x1234_5678);
     cpu.srl(1, 16, 2);
     assert_eq!(cpu.read_gp(2), 0x1234);
 }
@@ -465,6 +1279,230 @@ fn srav() {
     assert_eq!(cpu.read_gp(3), 0xFFFF_8765);
 }
 
+#[test]
+fn rotr() {
+    let mut cpu = MIPSI::default();
+    let val = 0x1234_5678;
+    // Rotate with shifts, to check against.
+    #[allow(clippy::manual_rotate)]
+    let manual = |x: u32, amount: u32| (x >> amount) | (x << (32 - amount));
+    cpu.write_gp(1, val);
+
+    cpu.rotr(1, 8, 2);
+    assert_eq!(cpu.read_gp(2), manual(val, 8));
+    assert_eq!(cpu.read_gp(2), 0x7812_3456);
+    cpu.rotr(1, 0, 2);
+    assert_eq!(cpu.read_gp(2), val);
+    // The amount is masked to 5 bits.
+    cpu.rotr(1, 40, 2);
+    assert_eq!(cpu.read_gp(2), 0x7812_3456);
+
+    cpu.write_gp(3, 8);
+    cpu.rotrv(3, 1, 4);
+    assert_eq!(cpu.read_gp(4), manual(val, 8));
+    cpu.write_gp(3, 0x24);
+    cpu.rotrv(3, 1, 4);
+    assert_eq!(cpu.read_gp(4), manual(val, 4));
+}
+
+#[test]
+fn rotr_decode() {
+    // rotr $2, $1, 8 and rotrv $4, $1, $3
+    let rotr = (1 << 21) | (1 << 16) | (2 << 11) | (8 << 6) | 0x02;
+    let rotrv = (3 << 21) | (1 << 16) | (4 << 11) | (1 << 6) | 0x06;
+    let build = |generation, isa_level| MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_generation(generation)
+        .with_isa_level(isa_level)
+        .with_strict_decode()
+        .build();
+
+    let mut cpu = build(Generation::MIPS32, IsaLevel::Mips32R2);
+    cpu.write_gp(1, 0x1234_5678);
+    cpu.write_gp(3, 4);
+    cpu.execute(rotr);
+    cpu.execute(rotrv);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0x7812_3456);
+    assert_eq!(cpu.read_gp(4), 0x8123_4567);
+
+    // Below MIPS32r2 the rotates are reserved.
+    let mut cpu = build(Generation::MIPS32, IsaLevel::Mips32);
+    cpu.write_gp(1, 0x1234_5678);
+    cpu.execute(rotr);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+    assert_eq!(cpu.read_gp(2), 0);
+
+    // In MIPS I the field is reserved.
+    let mut cpu = build(Generation::MIPSI, IsaLevel::Mips32R2);
+    cpu.execute(rotr);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+
+    assert_eq!(required_isa_level(rotr, Generation::MIPS32), IsaLevel::Mips32R2);
+    assert_eq!(required_isa_level(rotrv, Generation::MIPS32), IsaLevel::Mips32R2);
+    assert_eq!(required_isa_level(rotr, Generation::MIPSI), IsaLevel::MipsI);
+}
+
+#[test]
+fn ext() {
+    let mut cpu = MIPSI::default();
+    cpu.write_gp(1, 0x1234_5678);
+
+    // A middle field: bits 8-19.
+    cpu.ext(1, 2, 8, 12);
+    assert_eq!(cpu.read_gp(2), 0x456);
+    // The whole register.
+    cpu.ext(1, 3, 0, 32);
+    assert_eq!(cpu.read_gp(3), 0x1234_5678);
+    // The top bit alone.
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.ext(1, 4, 31, 1);
+    assert_eq!(cpu.read_gp(4), 1);
+    // A field past bit 31 reads 0 for the missing bits.
+    cpu.write_gp(1, 0xF000_0000);
+    cpu.ext(1, 5, 28, 8);
+    assert_eq!(cpu.read_gp(5), 0xF);
+
+    // ext $2, $1, 4, 8: rd holds size - 1, sa holds pos.
+    cpu.write_gp(1, 0xABCD);
+    cpu.execute((0x1F << 26) | (1 << 21) | (2 << 16) | (7 << 11) | (4 << 6));
+    assert_eq!(cpu.read_gp(2), 0xBC);
+    // ext $2, $1, 0, 32
+    cpu.execute((0x1F << 26) | (1 << 21) | (2 << 16) | (31 << 11));
+    assert_eq!(cpu.read_gp(2), 0xABCD);
+}
+
+#[test]
+fn ins() {
+    let mut cpu = MIPSI::default();
+
+    // Insert into a middle field: bits 8-15.
+    cpu.write_gp(1, 0xFFFF_FFAB);
+    cpu.write_gp(2, 0x1234_5678);
+    cpu.ins(1, 2, 8, 8);
+    assert_eq!(cpu.read_gp(2), 0x1234_AB78);
+    // The whole register.
+    cpu.ins(1, 2, 0, 32);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FFAB);
+    // The top bit alone.
+    cpu.write_gp(2, 0);
+    cpu.ins(1, 2, 31, 1);
+    assert_eq!(cpu.read_gp(2), 0x8000_0000);
+    // A field of size 0 changes nothing.
+    cpu.ins(1, 2, 4, 0);
+    assert_eq!(cpu.read_gp(2), 0x8000_0000);
+
+    // ins $2, $1, 4, 8: rd holds pos + size - 1, sa holds pos.
+    cpu.write_gp(1, 0xCD);
+    cpu.write_gp(2, 0xFFFF_FFFF);
+    cpu.execute((0x1F << 26) | (1 << 21) | (2 << 16) | (11 << 11) | (4 << 6) | 0x04);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FCDF);
+    // With msb below lsb, nothing is inserted.
+    cpu.execute((0x1F << 26) | (1 << 21) | (2 << 16) | (2 << 11) | (4 << 6) | 0x04);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FCDF);
+}
+
+#[test]
+fn seb_seh() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x80);
+    cpu.seb(1, 2);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FF80);
+    cpu.write_gp(1, 0x8000);
+    cpu.seh(1, 2);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_8000);
+
+    // Only the low bits are used.
+    cpu.write_gp(1, 0x1234_567F);
+    cpu.seb(1, 2);
+    assert_eq!(cpu.read_gp(2), 0x7F);
+    cpu.seh(1, 2);
+    assert_eq!(cpu.read_gp(2), 0x567F);
+
+    // seb $2, $1 and seh $3, $1
+    let seb = (0x1F << 26) | (1 << 16) | (2 << 11) | (0x10 << 6) | 0x20;
+    let seh = (0x1F << 26) | (1 << 16) | (3 << 11) | (0x18 << 6) | 0x20;
+    cpu.write_gp(1, 0xFF);
+    cpu.execute(seb);
+    cpu.execute(seh);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FFFF);
+    assert_eq!(cpu.read_gp(3), 0xFF);
+    assert_eq!(instruction_class(seb), InstrClass::Alu);
+    assert_eq!(required_isa_level(seh, Generation::MIPSI), IsaLevel::Mips32R2);
+
+    // Below MIPS32r2 they are reserved.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_isa_level(IsaLevel::Mips32)
+        .build();
+    cpu.execute(seb);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+}
+
+#[test]
+fn wsbh() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x1122_3344);
+    cpu.wsbh(1, 2);
+    assert_eq!(cpu.read_gp(2), 0x2211_4433);
+
+    // wsbh $2, $1 then rotating by 16 swaps the whole word.
+    let wsbh = (0x1F << 26) | (1 << 16) | (2 << 11) | (0x02 << 6) | 0x20;
+    cpu.execute(wsbh);
+    cpu.rotr(2, 16, 2);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0x4433_2211);
+    assert_eq!(instruction_class(wsbh), InstrClass::Alu);
+    assert_eq!(required_isa_level(wsbh, Generation::MIPSI), IsaLevel::Mips32R2);
+
+    // Below MIPS32r2 it is reserved.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_isa_level(IsaLevel::Mips32)
+        .build();
+    cpu.execute(wsbh);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+}
+
+#[test]
+fn shift_by_zero() {
+    let mut cpu = MIPSI::default();
+    let val = 0x8765_4321;
+    cpu.write_gp(1, val);
+
+    cpu.sll(1, 0, 2);
+    assert_eq!(cpu.read_gp(2), val);
+    cpu.srl(1, 0, 2);
+    assert_eq!(cpu.read_gp(2), val);
+    cpu.sra(1, 0, 2);
+    assert_eq!(cpu.read_gp(2), val);
+    // A shift amount of 32 is masked to 0.
+    cpu.sll(1, 32, 2);
+    assert_eq!(cpu.read_gp(2), val);
+
+    // Shift amounts of 0 and 32 (masked to 0) in a register.
+    for amount in [0, 32] {
+        cpu.write_gp(3, amount);
+        cpu.sllv(3, 1, 2);
+        assert_eq!(cpu.read_gp(2), val);
+        cpu.srlv(3, 1, 2);
+        assert_eq!(cpu.read_gp(2), val);
+        cpu.srav(3, 1, 2);
+        assert_eq!(cpu.read_gp(2), val);
+    }
+
+    // sll $0, $0, 0 is a NOP, and decodes as one.
+    let before = cpu.save_state();
+    cpu.step();
+    let mut after = cpu.save_state();
+    assert_eq!(after.pc, before.pc + 4);
+    after.pc = before.pc;
+    after.pc_next = before.pc_next;
+    after.cycles = before.cycles;
+    assert_eq!(after, before);
+}
+
 #[test]
 fn slt() {
     let mut cpu = MIPSI::default();
@@ -631,6 +1669,116 @@ fn lhu() {
     assert_eq!(cpu.read_gp(2), 0xFEDC);
 }
 
+#[test]
+fn load_delay() {
+    let program = [
+        make_i_instr(0x23, 0, 1, 0x100),    // lw $1, 0x100($0)
+        0x0020_1021,                        // addu $2, $1, $0
+        0x0020_1821,                        // addu $3, $1, $0
+    ];
+    let load = |cpu: &mut MIPSI<LittleMemTest>| {
+        for (i, instr) in program.iter().enumerate() {
+            cpu.mem().write_word((i * 4) as u32, *instr);
+        }
+        cpu.mem().write_word(0x100, 0x1234);
+        cpu.write_gp(1, 7);
+    };
+
+    // By default the loaded value is ready immediately.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    load(&mut cpu);
+    for _ in 0..3 {
+        cpu.step();
+    }
+    assert_eq!(cpu.read_gp(2), 0x1234);
+    assert_eq!(cpu.read_gp(3), 0x1234);
+
+    // The instruction in the load delay slot reads the old value.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_load_delay()
+        .build();
+    load(&mut cpu);
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 7);
+    assert_eq!(cpu.save_state().load_delay, Some((1, 0x1234)));
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x1234);
+    cpu.step();
+    assert_eq!(cpu.read_gp(2), 7);
+    assert_eq!(cpu.read_gp(3), 0x1234);
+}
+
+#[test]
+fn load_delay_overwritten() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_load_delay()
+        .build();
+    cpu.mem().write_word(0x100, 0x1122_3344);
+    cpu.mem().write_word(0x104, 0x5566_7788);
+
+    // A write in the delay slot to the loaded register wins.
+    cpu.mem().write_word(0, make_i_instr(0x23, 0, 1, 0x100));  // lw $1, 0x100($0)
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 1, 5));      // addiu $1, $0, 5
+    cpu.step();
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 5);
+
+    // An unaligned load with LWR then LWL merges with the load in progress.
+    cpu.mem().write_word(0x0C, make_i_instr(0x26, 0, 2, 0x101));  // lwr $2, 0x101($0)
+    cpu.mem().write_word(0x10, make_i_instr(0x22, 0, 2, 0x104));  // lwl $2, 0x104($0)
+    cpu.step();
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_gp(2), 0x8811_2233);
+}
+
+#[test]
+fn load_sign_extension() {
+    let mut cpu = MIPSI::default();
+
+    // Halfwords 0x8000 and 0xFFFF, then 0x7FFF and 0x0080.
+    cpu.mem().write_word(0x100, 0xFFFF_8000);
+    cpu.mem().write_word(0x104, 0x0080_7FFF);
+    cpu.write_gp(1, 0x100);
+
+    cpu.lh(1, 2, 0);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_8000);
+    cpu.lh(1, 2, 2);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FFFF);
+    cpu.lh(1, 2, 4);
+    assert_eq!(cpu.read_gp(2), 0x0000_7FFF);
+    cpu.lh(1, 2, 6);
+    assert_eq!(cpu.read_gp(2), 0x0000_0080);
+
+    cpu.lhu(1, 2, 0);
+    assert_eq!(cpu.read_gp(2), 0x0000_8000);
+    cpu.lhu(1, 2, 2);
+    assert_eq!(cpu.read_gp(2), 0x0000_FFFF);
+
+    // Bytes 0x00, 0x80, 0xFF, 0xFF, then 0xFF, 0x7F, 0x80, 0x00.
+    cpu.lb(1, 2, 1);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FF80);
+    cpu.lb(1, 2, 3);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FFFF);
+    cpu.lb(1, 2, 5);
+    assert_eq!(cpu.read_gp(2), 0x0000_007F);
+    cpu.lb(1, 2, 6);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FF80);
+    cpu.lb(1, 2, 0);
+    assert_eq!(cpu.read_gp(2), 0);
+
+    cpu.lbu(1, 2, 1);
+    assert_eq!(cpu.read_gp(2), 0x0000_0080);
+    cpu.lbu(1, 2, 3);
+    assert_eq!(cpu.read_gp(2), 0x0000_00FF);
+
+    // A negative offset.
+    cpu.write_gp(1, 0x108);
+    cpu.lh(1, 2, 0xFFFA);
+    assert_eq!(cpu.read_gp(2), 0xFFFF_FFFF);
+}
+
 #[test]
 fn lw() {
     let mut cpu = MIPSI::default();
@@ -801,6 +1949,47 @@ fn swr() {
 // - ADDI
 // - Step
 
+#[test]
+fn ll_sc() {
+    let mut cpu = MIPSI::default();
+
+    // ll $1, 0x100($0); addiu $1, $1, 1; sc $1, 0x100($0)
+    cpu.mem().write_word(0, make_i_instr(0x30, 0, 1, 0x100));
+    cpu.mem().write_word(4, make_i_instr(0x09, 1, 1, 1));
+    cpu.mem().write_word(8, make_i_instr(0x38, 0, 1, 0x100));
+    cpu.mem().write_word(0x100, 0x41);
+
+    for _ in 0..3 {
+        cpu.step();
+    }
+    assert_eq!(cpu.read_gp(1), 1);
+    assert_eq!(cpu.mem().read_word(0x100), 0x42);
+
+    // The link is used up.
+    cpu.write_gp(1, 0x43);
+    cpu.sc(0, 1, 0x100);
+    assert_eq!(cpu.read_gp(1), 0);
+    assert_eq!(cpu.mem().read_word(0x100), 0x42);
+}
+
+#[test]
+fn sc_invalidated() {
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0x100, 0x41);
+    cpu.ll(0, 1, 0x100);
+    assert_eq!(cpu.read_gp(1), 0x41);
+
+    // Another store to the linked word.
+    cpu.write_gp(2, 0x55);
+    cpu.sb(0, 2, 0x102);
+
+    cpu.write_gp(1, 0x42);
+    cpu.sc(0, 1, 0x100);
+    assert_eq!(cpu.read_gp(1), 0);
+    assert_eq!(cpu.mem().read_word(0x100), 0x0055_0041);
+}
+
 #[test]
 fn beq() {
     let mut cpu = MIPSI::default();
@@ -834,6 +2023,76 @@ fn beq() {
     assert_eq!(cpu.read_gp(4), 0x123);
 }
 
+#[test]
+fn branch_target() {
+    // A forward branch from 0x20: the target is the delay slot (0x24) + (0x10 << 2).
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x20);
+    cpu.mem().write_word(0x20, make_i_instr(0x04, 0, 0, 0x10));
+
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x24);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x64);
+
+    // A backward branch from 0x80: the target is the delay slot (0x84) - (3 << 2).
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x80);
+    cpu.mem().write_word(0x80, make_i_instr(0x04, 0, 0, 0xFFFD));
+
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x78);
+}
+
+#[test]
+fn backward_branch_loop() {
+    // beq $0, $0, -1 branches back to itself: the target is the delay slot (0x44) - 4.
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x40);
+    cpu.mem().write_word(0x40, make_i_instr(0x04, 0, 0, 0xFFFF));
+    for _ in 0..3 {
+        cpu.step();
+        assert_eq!(cpu.read_pc(), 0x44);
+        cpu.step();
+        assert_eq!(cpu.read_pc(), 0x40);
+    }
+
+    // A countdown loop: addiu $1, $1, -1; bne $1, $0, -2; nop
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x100);
+    cpu.mem().write_word(0x100, make_i_instr(0x09, 1, 1, 0xFFFF));
+    cpu.mem().write_word(0x104, make_i_instr(0x05, 1, 0, 0xFFFE));
+    cpu.write_gp(1, 3);
+    for _ in 0..9 {
+        cpu.step();
+    }
+    assert_eq!(cpu.read_gp(1), 0);
+    assert_eq!(cpu.read_pc(), 0x10C);
+
+    // The most negative offset.
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x3_0000);
+    cpu.execute(make_i_instr(0x04, 0, 0, 0x8000));
+    assert_eq!(cpu.pending_branch_target(), Some(0x3_0004 - 0x2_0000));
+
+    // A backward branch below address 0 wraps around.
+    let mut cpu = MIPSI::default();
+    cpu.mem().write_word(0, make_i_instr(0x04, 0, 0, 0xFFFD));
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0xFFFF_FFF8);
+
+    // bgezal with a negative offset links past the delay slot.
+    let mut cpu = MIPSI::default();
+    cpu.write_pc(0x80);
+    cpu.mem().write_word(0x80, make_i_instr(0x01, 0, 0x11, 0xFFF0));
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x44);
+    assert_eq!(cpu.read_gp(31), 0x88);
+}
+
 #[test]
 fn bgtz() {
     let mut cpu = MIPSI::default();
@@ -898,38 +2157,1634 @@ fn bgezal() {
     assert_eq!(cpu.read_gp(4), 0x123);
 }
 
-// TODO: test jumps
+#[test]
+fn j() {
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, (0x02 << 26) | (0x100 >> 2));
+    cpu.mem().write_word(4, make_i_instr(0x08, 3, 3, 0x123));
+    cpu.mem().write_word(0x100, make_i_instr(0x08, 4, 4, 0x456));
+
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_gp(3), 0x123);
+    assert_eq!(cpu.read_pc(), 0x100);
+    cpu.step();
+    assert_eq!(cpu.read_gp(4), 0x456);
+}
 
 #[test]
-fn mtc1() {
+fn jal() {
     let mut cpu = MIPSI::default();
 
-    cpu.write_gp(10, 0x100);
-    cpu.mtcz(Coproc::_1, 10, 1);
-    
-    assert_eq!(cpu.coproc_1().unwrap().data_reg[1], 0x100);
+    cpu.mem().write_word(0, (0x03 << 26) | (0x100 >> 2));
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(31), 8);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x100);
 }
 
 #[test]
-fn mfc1() {
+fn jr() {
     let mut cpu = MIPSI::default();
 
-    cpu.coproc_1().unwrap().data_reg[1] = 0xFF;
-    cpu.mfcz(Coproc::_1, 10, 1);
-    
-    assert_eq!(cpu.read_gp(10), 0xFF);
+    cpu.mem().write_word(0, (1 << 21) | 0x08);
+    cpu.write_gp(1, 0x200);
+
+    cpu.step();
+    assert!(cpu.in_delay_slot());
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x200);
+
+    // The whole target address is used, even in another segment.
+    cpu.mem().write_word(0x200, (1 << 21) | 0x08);
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x8000_0000);
 }
 
 #[test]
-fn cop1() {
+fn jalr() {
     let mut cpu = MIPSI::default();
 
-    cpu.coproc_1().unwrap().data_reg[1] = 0xFF;
-    cpu.coproc_1().unwrap().data_reg[2] = 0x2;
+    cpu.mem().write_word(0, (1 << 21) | (5 << 11) | 0x09);
+    cpu.write_gp(1, 0x200);
 
-    cpu.mem().write_word(0, (0x11 << 26) | (0x1 << 25) | 0x1);
+    cpu.step();
+    assert_eq!(cpu.read_gp(5), 8);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x200);
+}
+
+#[test]
+fn jalr_default_link() {
+    let mut cpu = MIPSI::default();
+
+    // jalr $ra, $t9 from 0x100: the return address is after the delay slot.
+    cpu.mem().write_word(0x100, (25 << 21) | (31 << 11) | 0x09);
+    cpu.write_gp(25, 0x400);
+    cpu.write_pc(0x100);
 
     cpu.step();
-    
-    assert_eq!(cpu.coproc_1().unwrap().data_reg[3], 0x101);
+    assert_eq!(cpu.read_gp(31), 0x108);
+    assert_eq!(cpu.pending_branch_target(), Some(0x400));
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x400);
+}
+
+#[test]
+fn jalr_same_register() {
+    let mut cpu = MIPSI::default();
+
+    // jalr $5, $5 links $5, and jumps to its value from before.
+    cpu.mem().write_word(0, (5 << 21) | (5 << 11) | 0x09);
+    cpu.write_gp(5, 0x200);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(5), 8);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x200);
+}
+
+#[test]
+fn jump_register_segment() {
+    let mut cpu = MIPSI::default();
+
+    // Unlike j and jal, jr and jalr can leave the 256MB segment.
+    cpu.write_gp(1, 0x8000_0200);
+    cpu.execute((1 << 21) | 0x08);
+    assert_eq!(cpu.pending_branch_target(), Some(0x8000_0200));
+
+    cpu.write_pc(0xBFC0_0000);
+    cpu.execute((1 << 21) | (31 << 11) | 0x09);
+    assert_eq!(cpu.read_gp(31), 0xBFC0_0008);
+    assert_eq!(cpu.pending_branch_target(), Some(0x8000_0200));
+}
+
+#[test]
+fn successors() {
+    let mut cpu = MIPSI::default();
+
+    // beq $1, $2, -2 at 0x100
+    cpu.mem().write_word(0x100, make_i_instr(0x04, 1, 2, 0xFFFE));
+    cpu.write_pc(0x100);
+    let succ = cpu.successors();
+    assert_eq!(succ.fall_through, Some(0x108));
+    assert_eq!(succ.target, Some(0xFC));
+    assert!(succ.contains(0xFC) && succ.contains(0x108));
+    assert_eq!(succ.iter().collect::<Vec<_>>(), vec![0x108, 0xFC]);
+    // Nothing was executed.
+    assert_eq!(cpu.read_pc(), 0x100);
+
+    // In the delay slot, only the resolved target. $1 == $2 so the branch is taken.
+    cpu.step();
+    assert_eq!(cpu.successors(), SuccessorSet { fall_through: None, target: Some(0xFC) });
+
+    // jr $3
+    cpu.mem().write_word(0x200, (3 << 21) | 0x08);
+    cpu.write_gp(3, 0x8000_0040);
+    cpu.write_pc(0x200);
+    assert_eq!(cpu.successors(), SuccessorSet { fall_through: None, target: Some(0x8000_0040) });
+
+    // j 0x40
+    cpu.mem().write_word(0x200, (0x02 << 26) | 0x10);
+    assert_eq!(cpu.successors(), SuccessorSet { fall_through: None, target: Some(0x40) });
+
+    // addiu $1, $1, 1
+    cpu.mem().write_word(0x200, make_i_instr(0x09, 1, 1, 1));
+    assert_eq!(cpu.successors(), SuccessorSet { fall_through: Some(0x204), target: None });
+
+    // A reserved instruction.
+    cpu.mem().write_word(0x200, 0xFC00_0000);
+    assert_eq!(cpu.successors(), SuccessorSet::default());
+}
+
+#[test]
+fn invariant_checks() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_invariant_checks()
+        .build();
+
+    cpu.mem().write_word(0x00, make_i_instr(0x09, 0, 1, 3));       // addiu $1, $0, 3
+    cpu.mem().write_word(0x04, make_i_instr(0x09, 1, 1, 0xFFFF));  // addiu $1, $1, -1
+    cpu.mem().write_word(0x08, make_i_instr(0x05, 1, 0, 0xFFFE));  // bne $1, $0, -2
+    cpu.mem().write_word(0x10, (0x03 << 26) | (0x40 >> 2));        // jal 0x40
+    cpu.mem().write_word(0x18, (2 << 21) | 0x08);                  // jr $2
+    cpu.mem().write_word(0x40, (31 << 21) | 0x08);                 // jr $ra
+    cpu.write_gp(2, 0x102);
+
+    for _ in 0..20 {
+        cpu.step();
+    }
+    assert_eq!(cpu.read_gp(1), 0);
+    // Fetching from an unaligned address is allowed, as long as it raises an address error.
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::AddrErrorLoad));
+}
+
+#[test]
+#[should_panic(expected = "$zero is not 0")]
+fn invariant_checks_zero() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_invariant_checks()
+        .build();
+
+    cpu.gp_reg[0] = 1;
+    cpu.step();
+}
+
+#[test]
+#[should_panic(expected = "outside of a delay slot")]
+fn invariant_checks_next_pc() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_invariant_checks()
+        .build();
+    // A host handler that moves the next pc, without going through write_pc.
+    cpu.set_exception_handler(Box::new(|cpu, _| cpu.pc_next = 0x500));
+
+    cpu.mem().write_word(0, 0x0000_000C);    // syscall
+    cpu.step();
+}
+
+#[test]
+fn step_branch_aware() {
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, make_i_instr(0x04, 1, 2, 0x40));
+    cpu.mem().write_word(4, make_i_instr(0x08, 3, 3, 0x123));
+    cpu.mem().write_word(0x104, make_i_instr(0x8, 4, 4, 0x456));
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(2, 0x1234);
+
+    assert_eq!(cpu.step_branch_aware(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(3), 0x123);
+    assert_eq!(cpu.read_pc(), 0x104);
+    assert!(!cpu.in_delay_slot());
+
+    // Non-branches are a single step.
+    assert_eq!(cpu.step_branch_aware(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(4), 0x456);
+    assert_eq!(cpu.read_pc(), 0x108);
+}
+
+#[test]
+fn stuck() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_stuck_limit(10)
+        .build();
+
+    cpu.mem().write_word(0, make_i_instr(0x09, 1, 1, 0x1));
+    // b . (beq $0, $0, -1)
+    cpu.mem().write_word(4, make_i_instr(0x04, 0, 0, 0xFFFF));
+
+    assert_eq!(cpu.run(), StepResult::Stuck);
+    assert_eq!(cpu.read_gp(1), 0x1);
+    assert_eq!(cpu.read_pc(), 4);
+
+    // A tight loop that changes state is not stuck.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_stuck_limit(10)
+        .build();
+
+    // bne $1, $0, -1
+    cpu.mem().write_word(0, make_i_instr(0x05, 1, 0, 0xFFFF));
+    cpu.mem().write_word(4, make_i_instr(0x09, 1, 1, 0xFFFF));
+    cpu.mem().write_word(8, make_i_instr(0x04, 0, 0, 0xFFFF));
+    cpu.write_gp(1, 20);
+
+    assert_eq!(cpu.run(), StepResult::Stuck);
+    assert_eq!(cpu.read_gp(1), 0xFFFF_FFFF);
+    assert_eq!(cpu.read_pc(), 8);
+}
+
+#[test]
+fn teq() {
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(2, 0x1234);
+    cpu.teq(1, 2);
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::Trap]);
+
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(2, 0x1235);
+    cpu.teq(1, 2);
+    assert!(cpu.coproc_0().exceptions.is_empty());
+}
+
+#[test]
+fn trap_decode() {
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    // tlt $1, $2
+    cpu.mem().write_word(0, (1 << 21) | (2 << 16) | 0x32);
+    // tgei $1, -1
+    cpu.mem().write_word(4, make_i_instr(0x01, 1, 0x08, 0xFFFF));
+    cpu.write_gp(1, 0xFFFF_FFFF);
+    cpu.write_gp(2, 0);
+
+    cpu.step();
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::Trap]);
+    cpu.step();
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::Trap, ExceptionCode::Trap]);
+}
+
+#[test]
+fn sync() {
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    cpu.mem().write_word(0, 0x0000_000F);
+    cpu.step();
+    assert!(cpu.coproc_0().exceptions.is_empty());
+    assert_eq!(cpu.mem().syncs, 1);
+}
+
+#[test]
+fn hilo() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_hilo(0x1234_5678_9ABC_DEF0);
+    assert_eq!(cpu.read_hi(), 0x1234_5678);
+    assert_eq!(cpu.read_lo(), 0x9ABC_DEF0);
+
+    cpu.write_lo(0x1111_1111);
+    assert_eq!(cpu.read_hilo(), 0x1234_5678_1111_1111);
+}
+
+#[test]
+fn movz() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(3, 0x5678);
+    cpu.movz(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 0x1234);
+
+    cpu.write_gp(2, 1);
+    cpu.write_gp(3, 0x5678);
+    cpu.movz(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 0x5678);
+}
+
+#[test]
+fn movn() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x1234);
+    cpu.write_gp(3, 0x5678);
+    cpu.movn(1, 2, 3);
+    assert_eq!(cpu.read_gp(3), 0x5678);
+
+    // movn $3, $1, $2
+    cpu.write_gp(2, 1);
+    cpu.mem().write_word(0, (1 << 21) | (2 << 16) | (3 << 11) | 0x0B);
+    cpu.step();
+    assert_eq!(cpu.read_gp(3), 0x1234);
+}
+
+#[test]
+fn mul() {
+    let mut cpu = MIPSI::default();
+
+    for (a, b) in [(3, 5), (0xFFFF_FFFF, 7), (0x8000_0000, 0x8000_0000), (0x1234_5678, 0x9ABC_DEF0)].iter() {
+        cpu.write_gp(1, *a);
+        cpu.write_gp(2, *b);
+        cpu.mult(1, 2);
+        cpu.mul(1, 2, 3);
+        assert_eq!(cpu.read_gp(3), cpu.read_lo());
+    }
+
+    // mul $4, $1, $2
+    cpu.write_hi(0x55);
+    cpu.write_lo(0x55);
+    cpu.mem().write_word(0, (0x1C << 26) | (1 << 21) | (2 << 16) | (4 << 11) | 0x02);
+    cpu.step();
+    assert_eq!(cpu.read_gp(4), 0x1234_5678u32.wrapping_mul(0x9ABC_DEF0));
+    assert_eq!(cpu.read_lo(), 0x55);
+}
+
+#[test]
+fn madd() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.write_gp(2, 4);
+    cpu.write_gp(3, 0xFFFF_FFFF);
+    cpu.madd(1, 2);
+    assert_eq!(cpu.read_hi(), 0xFFFF_FFFE);
+    assert_eq!(cpu.read_lo(), 0);
+    cpu.madd(3, 3);
+    assert_eq!(cpu.read_hi(), 0xFFFF_FFFE);
+    assert_eq!(cpu.read_lo(), 1);
+
+    // maddu $1, $2
+    cpu.mem().write_word(0, (0x1C << 26) | (1 << 21) | (2 << 16) | 0x01);
+    cpu.step();
+    assert_eq!(cpu.read_hi(), 0);
+    assert_eq!(cpu.read_lo(), 1);
+}
+
+#[test]
+fn msub() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0xFFFF_FFFF);
+    cpu.write_gp(2, 3);
+    cpu.msub(1, 2);
+    assert_eq!(cpu.read_hi(), 0);
+    assert_eq!(cpu.read_lo(), 3);
+
+    cpu.msubu(1, 2);
+    assert_eq!(cpu.read_hi(), 0xFFFF_FFFD);
+    assert_eq!(cpu.read_lo(), 6);
+}
+
+#[test]
+fn clz() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0);
+    cpu.clz(1, 2);
+    assert_eq!(cpu.read_gp(2), 32);
+
+    cpu.write_gp(1, 0x0001_FFFF);
+    cpu.clz(1, 2);
+    assert_eq!(cpu.read_gp(2), 15);
+
+    cpu.write_gp(1, 0x8000_0000);
+    cpu.clz(1, 2);
+    assert_eq!(cpu.read_gp(2), 0);
+}
+
+#[test]
+fn clo() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(1, 0xFFFF_FFFF);
+    cpu.clo(1, 2);
+    assert_eq!(cpu.read_gp(2), 32);
+
+    cpu.write_gp(1, 0xF000_0000);
+    cpu.clo(1, 2);
+    assert_eq!(cpu.read_gp(2), 4);
+
+    // clo $3, $1
+    cpu.mem().write_word(0, (0x1C << 26) | (1 << 21) | (3 << 16) | (3 << 11) | 0x21);
+    cpu.step();
+    assert_eq!(cpu.read_gp(3), 4);
+}
+
+#[test]
+fn mtc1() {
+    let mut cpu = MIPSI::default();
+
+    cpu.write_gp(10, 0x100);
+    cpu.mtcz(Coproc::_1, 10, 1);
+    
+    assert_eq!(cpu.coproc_1().unwrap().data_reg[1], 0x100);
+}
+
+#[test]
+fn mfc1() {
+    let mut cpu = MIPSI::default();
+
+    cpu.coproc_1().unwrap().data_reg[1] = 0xFF;
+    cpu.mfcz(Coproc::_1, 10, 1);
+    
+    assert_eq!(cpu.read_gp(10), 0xFF);
+}
+
+#[test]
+fn cop1() {
+    let mut cpu = MIPSI::default();
+
+    cpu.coproc_1().unwrap().data_reg[1] = 0xFF;
+    cpu.coproc_1().unwrap().data_reg[2] = 0x2;
+
+    cpu.mem().write_word(0, (0x11 << 26) | (0x1 << 25) | 0x1);
+
+    cpu.step();
+    
+    assert_eq!(cpu.coproc_1().unwrap().data_reg[3], 0x101);
+}
+
+#[test]
+fn bczt() {
+    let mut cpu = MIPSI::default();
+
+    // bc1t 0x40; addiu $1, $0, 1
+    cpu.mem().write_word(0, (0x11 << 26) | (0x08 << 21) | (0x01 << 16) | 0x10);
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 1, 1));
+    cpu.mem().write_word(8, make_i_instr(0x09, 0, 2, 1));
+    cpu.coproc_1().unwrap().condition = true;
+
+    cpu.step();
+    assert!(cpu.in_delay_slot());
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 1);
+    assert_eq!(cpu.read_pc(), 0x44);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, (0x11 << 26) | (0x08 << 21) | (0x01 << 16) | 0x10);
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x8);
+}
+
+#[test]
+fn coproc_condition_and_flags() {
+    let mut cpu = MIPSI::default();
+
+    // mtc1 $1, $1; mtc1 $2, $2; cop1 3; bc1t 0x10
+    cpu.mem().write_word(0x0, (0x11 << 26) | (0x04 << 21) | (1 << 16) | (1 << 11));
+    cpu.mem().write_word(0x4, (0x11 << 26) | (0x04 << 21) | (2 << 16) | (2 << 11));
+    cpu.mem().write_word(0x8, (0x11 << 26) | (0x10 << 21) | 3);
+    cpu.mem().write_word(0xC, (0x11 << 26) | (0x08 << 21) | (0x01 << 16) | 0x10);
+    cpu.write_gp(1, 5);
+    cpu.write_gp(2, 5);
+
+    for _ in 0..5 {
+        cpu.step();
+    }
+    assert!(cpu.coproc_1().unwrap().condition_code());
+    assert_eq!(cpu.coproc_1().unwrap().flags(), 0);
+    assert_eq!(cpu.read_pc(), 0x50);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0x0, (0x11 << 26) | (0x04 << 21) | (1 << 16) | (1 << 11));
+    cpu.mem().write_word(0x4, (0x11 << 26) | (0x10 << 21) | 3);
+    cpu.mem().write_word(0x8, (0x11 << 26) | (0x08 << 21) | (0x01 << 16) | 0x10);
+    cpu.write_gp(1, 5);
+
+    for _ in 0..4 {
+        cpu.step();
+    }
+    assert!(!cpu.coproc_1().unwrap().condition_code());
+    assert_eq!(cpu.coproc_1().unwrap().flags(), 1);
+    assert_eq!(cpu.read_pc(), 0x10);
+}
+
+#[test]
+fn bczf() {
+    let mut cpu = MIPSI::default();
+
+    cpu.bczf(Coproc::_1, 0x10);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x40);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.coproc_1().unwrap().condition = true;
+    cpu.bczf(Coproc::_1, 0x10);
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x4);
+
+    // Coprocessor 2 isn't installed.
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    cpu.bczf(Coproc::_2, 0x10);
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::CoProcUnusable]);
+}
+
+#[test]
+fn flat_binary() {
+    // addiu $1, $0, 0x42; addiu $2, $0, 7 (partial)
+    let program = [0x42, 0x00, 0x01, 0x24, 0x07, 0x00];
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_translation(Translation::Flat)
+        .with_flat_binary(0x200, &program)
+        .build();
+
+    assert_eq!(cpu.read_pc(), 0x200);
+    assert_eq!(cpu.mem().read_halfword(0x204), 0x0007);
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x42);
+    assert_eq!(cpu.read_pc(), 0x204);
+}
+
+#[test]
+fn cache() {
+    // cache 0x15, 0x10($1)
+    let cache = make_i_instr(0x2F, 1, 0x15, 0x10);
+
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_generation(Generation::MIPS32)
+        .build();
+    cpu.write_gp(1, 0xF0);
+    cpu.execute(cache);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.mem().cache_ops, vec![(0x15, 0x100)]);
+    assert_eq!(instruction_class(cache), InstrClass::Load);
+
+    // MIPS I has no CACHE instruction.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    cpu.execute(cache);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+    assert!(cpu.mem().cache_ops.is_empty());
+}
+
+#[test]
+fn cache_privileged() {
+    // cache 0x15, 0x10($1)
+    let cache = make_i_instr(0x2F, 1, 0x15, 0x10);
+    let mut cpu = MIPSI::<LittleMemTest, StandardCoproc0>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_generation(Generation::MIPS32)
+        .build();
+
+    // A TLB miss in kernel mode is dropped without a trace.
+    cpu.write_gp(1, 0x1234);
+    cpu.execute(cache);
+    assert_eq!(cpu.last_exception(), None);
+    assert!(cpu.mem().cache_ops.is_empty());
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::BAD_VADDR), 0);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::ENTRY_HI), 0);
+
+    cpu.write_gp(1, KSEG0 + 0xF0);
+    cpu.execute(cache);
+    assert_eq!(cpu.mem().cache_ops, vec![(0x15, 0x100)]);
+
+    // User mode can't operate on the cache.
+    cpu.set_status(STATUS_KUC);
+    cpu.execute(cache);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::CoProcUnusable));
+    assert_eq!(cpu.mem().cache_ops.len(), 1);
+}
+
+#[test]
+fn pref() {
+    let build = |generation| MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc3(TestCoproc::default())
+        .with_generation(generation)
+        .build();
+
+    // MIPS32: a hint to memory, with no effect on the coprocessor.
+    let mut cpu = build(Generation::MIPS32);
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.write_gp(1, 0xF0);
+    cpu.execute(make_i_instr(0x33, 1, 2, 0x10));
+    assert_eq!(cpu.coproc_3().unwrap().move_from_reg(2), 0);
+    assert_eq!(cpu.mem().prefetches, vec![0x100]);
+    assert_eq!(cpu.last_exception(), None);
+
+    // MIPS I: a load into coprocessor 3.
+    let mut cpu = build(Generation::MIPSI);
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.write_gp(1, 0xF0);
+    cpu.execute(make_i_instr(0x33, 1, 2, 0x10));
+    assert_eq!(cpu.coproc_3().unwrap().move_from_reg(2), 0x1234_5678);
+    assert!(cpu.mem().prefetches.is_empty());
+}
+
+#[test]
+fn pref_tlb_miss() {
+    let mut cpu = MIPSI::<LittleMemTest, StandardCoproc0>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_generation(Generation::MIPS32)
+        .build();
+
+    // pref 0, 0x1234($zero): a TLB miss, which is dropped without a trace.
+    cpu.execute(make_i_instr(0x33, 0, 0, 0x1234));
+    assert_eq!(cpu.last_exception(), None);
+    assert!(cpu.mem().prefetches.is_empty());
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::BAD_VADDR), 0);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::ENTRY_HI), 0);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::CONTEXT), 0);
+}
+
+#[test]
+fn breakpoint() {
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, make_i_instr(0x09, 0, 1, 0x1));
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 2, 0x2));
+    cpu.mem().write_word(8, make_i_instr(0x09, 0, 3, 0x3));
+    cpu.add_breakpoint(8);
+
+    assert_eq!(cpu.run(), StepResult::Breakpoint);
+    assert_eq!(cpu.read_gp(1), 0x1);
+    assert_eq!(cpu.read_gp(2), 0x2);
+    assert_eq!(cpu.read_gp(3), 0);
+
+    // Resuming executes the instruction at the breakpoint.
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(3), 0x3);
+
+    cpu.remove_breakpoint(8);
+}
+
+#[test]
+fn breakpoint_after_write_pc() {
+    let mut cpu = MIPSI::default();
+    cpu.add_breakpoint(0);
+    cpu.add_breakpoint(8);
+
+    // Moving the pc after a breakpoint is reported doesn't skip the breakpoint at the new pc.
+    assert_eq!(cpu.step(), StepResult::Breakpoint);
+    cpu.write_pc(8);
+    assert_eq!(cpu.step(), StepResult::Breakpoint);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_pc(), 0xC);
+}
+
+#[test]
+fn breakpoint_delay_slot() {
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, make_i_instr(0x04, 0, 0, 0x40));
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 3, 0x123));
+    cpu.mem().write_word(0x104, make_i_instr(0x09, 0, 4, 0x456));
+    cpu.add_breakpoint(0x104);
+
+    // The delay slot executes before the branch target is reached.
+    assert_eq!(cpu.run(), StepResult::Breakpoint);
+    assert_eq!(cpu.read_gp(3), 0x123);
+    assert_eq!(cpu.read_gp(4), 0);
+
+    let mut cpu = MIPSI::default();
+
+    cpu.mem().write_word(0, make_i_instr(0x04, 0, 0, 0x40));
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 3, 0x123));
+    cpu.add_breakpoint(4);
+
+    // A breakpoint on the delay slot itself is honoured.
+    assert_eq!(cpu.run(), StepResult::Breakpoint);
+    assert_eq!(cpu.read_gp(3), 0);
+    cpu.step();
+    assert_eq!(cpu.read_gp(3), 0x123);
+}
+
+#[test]
+fn sdbbp() {
+    let mut cpu = MIPSI::with_test_coproc0();
+
+    cpu.mem().write_word(0, make_i_instr(0x09, 0, 1, 0x1));
+    cpu.mem().write_word(4, 0x7000_003F);
+    cpu.mem().write_word(8, make_i_instr(0x09, 0, 2, 0x2));
+
+    assert_eq!(cpu.run(), StepResult::DebugBreak);
+    assert_eq!(cpu.read_gp(1), 0x1);
+    assert_eq!(cpu.last_exception().map(|e| (e.code, e.pc)), Some((ExceptionCode::DebugBreak, 4)));
+    assert!(cpu.coproc_0().exceptions.is_empty());
+
+    // Execution continues after the breakpoint.
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(2), 0x2);
+}
+
+#[test]
+fn array_memory() {
+    let mem = ArrayMemTest { bytes: [0; 0x100] };
+    let mut cpu = MIPSI::<ArrayMemTest>::with_memory(Box::new(mem)).build();
+
+    cpu.mem().write_word(0, make_i_instr(0x09, 0, 1, 0x10));
+    cpu.mem().write_word(4, make_i_instr(0x2B, 0, 1, 0x80));
+    cpu.mem().write_word(8, make_i_instr(0x23, 0, 2, 0x80));
+
+    cpu.step();
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_gp(2), 0x10);
+    assert_eq!(cpu.mem().read_word(0x80), 0x10);
+}
+
+#[test]
+fn cp0_timer() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    cpu.write_pc(KSEG0);
+    cpu.coproc_0().move_to_reg(cp0_reg::COMPARE, 4);
+
+    for _ in 0..3 {
+        cpu.step();
+        assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::CAUSE) & CAUSE_TIMER_INTERRUPT, 0);
+    }
+    cpu.step();
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::COUNT), 4);
+    assert_ne!(cpu.coproc_0().move_from_reg(cp0_reg::CAUSE) & CAUSE_TIMER_INTERRUPT, 0);
+}
+
+#[test]
+fn cp0_accessors() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    let mtc0 = |rt: u32, rd: usize| (0x10 << 26) | (0x04 << 21) | (rt << 16) | ((rd as u32) << 11);
+    let mfc0 = |rt: u32, rd: usize| (0x10 << 26) | (rt << 16) | ((rd as u32) << 11);
+
+    cpu.write_gp(1, 0x1040_FF01);
+    cpu.write_gp(2, 0x8000_1234);
+    cpu.execute(mtc0(1, cp0_reg::STATUS));
+    cpu.execute(mtc0(2, cp0_reg::EPC));
+    cpu.execute(mtc0(1, cp0_reg::CAUSE));
+    assert_eq!(cpu.status(), 0x1040_FF01);
+    assert_eq!(cpu.epc(), 0x8000_1234);
+    assert_eq!(cpu.cause(), 0x0000_0300);
+
+    cpu.set_status(0x0040_0000);
+    cpu.set_epc(0x8000_0080);
+    cpu.set_cause(0xFFFF_FFFF);
+    cpu.execute(mfc0(3, cp0_reg::STATUS));
+    cpu.execute(mfc0(4, cp0_reg::EPC));
+    cpu.execute(mfc0(5, cp0_reg::CAUSE));
+    assert_eq!(cpu.read_gp(3), 0x0040_0000);
+    assert_eq!(cpu.read_gp(4), 0x8000_0080);
+    assert_eq!(cpu.read_gp(5), 0x0000_0300);
+}
+
+#[test]
+fn interrupt_acknowledged() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .build();
+
+    cpu.write_pc(KSEG0 + 0x200);
+    cpu.mem().write_word(0x80, make_i_instr(0x09, 0, 1, 0x1));
+    cpu.mem().write_word(0x200, make_i_instr(0x09, 0, 2, 0x2));
+    cpu.set_status(STATUS_IEC | bit(8 + 4));
+
+    // A masked line is ignored.
+    cpu.raise_interrupt(3);
+    cpu.step();
+    assert_eq!(cpu.read_gp(2), 0x2);
+    assert!(cpu.mem().acknowledged.is_empty());
+    cpu.clear_interrupt(3);
+
+    cpu.raise_interrupt(4);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+    assert_eq!(cpu.epc(), KSEG0 + 0x204);
+    assert_eq!(cpu.cause() & CAUSE_EXC_CODE, 0);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::Interrupt));
+    assert_eq!(cpu.mem().acknowledged, vec![4]);
+
+    // Interrupts are disabled in the handler.
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x1);
+    assert_eq!(cpu.mem().acknowledged, vec![4]);
+}
+
+#[test]
+fn run_with_budget() {
+    let mut cpu = MIPSI::default();
+    cpu.mem().write_word(0x0, make_i_instr(0x09, 1, 1, 1)); // addiu $1, $1, 1
+    cpu.mem().write_word(0x4, 0x0800_0000);                 // j 0
+
+    // Each loop is three instructions, including the delay slot.
+    assert_eq!(cpu.run_with_budget(30), RunOutcome::BudgetExhausted);
+    assert_eq!(cpu.read_gp(1), 10);
+    assert_eq!(cpu.read_pc(), 0x0);
+    assert_eq!(cpu.run_with_budget(1), RunOutcome::BudgetExhausted);
+    assert_eq!(cpu.read_gp(1), 11);
+
+    // Other stops are still reported.
+    cpu.add_breakpoint(0x8);
+    assert_eq!(cpu.run_with_budget(100), RunOutcome::Stopped(StepResult::Breakpoint));
+    assert_eq!(cpu.run_with_budget(0), RunOutcome::BudgetExhausted);
+    assert_eq!(cpu.read_pc(), 0x8);
+}
+
+#[test]
+fn fetch_guard() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100)))
+        .with_fetch_guard()
+        .build();
+    cpu.mem().write_word(0x8, 0xFFFF_FFFF);
+
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.step(), StepResult::GarbageFetch);
+    assert_eq!(cpu.read_pc(), 0x8);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.step(), StepResult::GarbageFetch);
+
+    // Without the guard, the word is executed.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x100))).build();
+    cpu.mem().write_word(0x0, 0xFFFF_FFFF);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+}
+
+#[test]
+fn wait() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_generation(Generation::MIPS32)
+        .with_entry(KSEG0 + 0x200)
+        .build();
+    // wait; addiu $2, $0, 2
+    cpu.mem().write_word(0x200, 0x4200_0020);
+    cpu.mem().write_word(0x204, make_i_instr(0x09, 0, 2, 0x2));
+    cpu.mem().write_word(0x80, make_i_instr(0x09, 0, 1, 0x1));
+    cpu.set_status(STATUS_IEC | bit(8 + 2));
+
+    assert_eq!(cpu.step(), StepResult::Executed);
+    for _ in 0..10 {
+        assert_eq!(cpu.step(), StepResult::Waiting);
+    }
+    assert_eq!(cpu.read_pc(), KSEG0 + 0x204);
+    assert_eq!(cpu.read_gp(2), 0);
+
+    // A masked line doesn't wake the CPU.
+    cpu.raise_interrupt(3);
+    assert_eq!(cpu.step(), StepResult::Waiting);
+
+    cpu.raise_interrupt(2);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+    assert_eq!(cpu.epc(), KSEG0 + 0x204);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(1), 0x1);
+    assert_eq!(cpu.read_gp(2), 0);
+
+    // In MIPS I, the operation goes to coprocessor 0 instead.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    cpu.execute(0x4200_0020);
+    assert_eq!(cpu.step(), StepResult::Executed);
+}
+
+#[test]
+fn wait_for_timer() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_generation(Generation::MIPS32)
+        .with_entry(KSEG0 + 0x200)
+        .build();
+    cpu.mem().write_word(0x200, 0x4200_0020);
+    cpu.coproc_0().move_to_reg(cp0_reg::COMPARE, 5);
+    cpu.set_status(STATUS_IEC | bit(8 + 7));
+
+    // Count keeps going while the CPU waits.
+    assert_eq!(cpu.step(), StepResult::Executed);
+    for _ in 0..4 {
+        assert_eq!(cpu.step(), StepResult::Waiting);
+    }
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::COUNT), 5);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+    assert_eq!(cpu.epc(), KSEG0 + 0x204);
+}
+
+#[test]
+fn wait_without_vectoring() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_generation(Generation::MIPS32)
+        .with_entry(KSEG0 + 0x200)
+        .build();
+    // wait; addiu $2, $0, 2
+    cpu.mem().write_word(0x200, 0x4200_0020);
+    cpu.mem().write_word(0x204, make_i_instr(0x09, 0, 2, 0x2));
+    cpu.set_status(STATUS_IEC | bit(8 + 2));
+
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.step(), StepResult::Waiting);
+
+    // The interrupt isn't taken, but it still wakes the CPU.
+    cpu.raise_interrupt(2);
+    assert_eq!(cpu.step(), StepResult::Executed);
+    assert_eq!(cpu.read_gp(2), 0x2);
+    assert_eq!(cpu.read_pc(), KSEG0 + 0x208);
+}
+
+#[test]
+fn interrupt_priority() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_entry(KSEG0 + 0x200)
+        .build();
+    cpu.set_status(STATUS_IEC | STATUS_IM);
+
+    cpu.raise_interrupt(2);
+    cpu.raise_interrupt(6);
+    assert_eq!(cpu.interrupts().asserted(), 0b0100_0100);
+    assert_eq!(cpu.pending_interrupts(), 0b0100_0100);
+
+    // The higher line goes first, then the other once it is cleared and interrupts are re-enabled.
+    cpu.step();
+    assert_eq!(cpu.mem().acknowledged, vec![6]);
+    assert_eq!(cpu.pending_interrupts(), 0);
+    cpu.clear_interrupt(6);
+    cpu.set_status(STATUS_IEC | STATUS_IM);
+    cpu.step();
+    assert_eq!(cpu.mem().acknowledged, vec![6, 2]);
+
+    // With a different order, the lower line goes first.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_interrupt_controller(InterruptController::with_priority([2, 6, 7, 5, 4, 3, 1, 0]))
+        .build();
+    cpu.set_status(STATUS_IEC | STATUS_IM);
+    cpu.raise_interrupt(2);
+    cpu.raise_interrupt(6);
+    cpu.step();
+    assert_eq!(cpu.mem().acknowledged, vec![2]);
+}
+
+#[test]
+fn coproc_usable() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .add_coproc1(TestCoproc::default())
+        .build();
+    cpu.write_gp(1, 0x1234);
+    // mtc1 $1, $2
+    let mtc1 = make_i_instr(0x11, 0x04, 1, 2 << 11);
+
+    // An attached coprocessor 1 still traps while CU1 is clear.
+    cpu.execute(mtc1);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::CoProcUnusable));
+    assert_eq!(cpu.coproc_1().unwrap().move_from_reg(2), 0);
+
+    cpu.set_status(STATUS_CU1);
+    cpu.execute(mtc1);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.coproc_1().unwrap().move_from_reg(2), 0x1234);
+
+    // Coprocessor 0 is usable in kernel mode, but not in user mode without CU0.
+    // mfc0 $3, $12
+    let mfc0 = make_i_instr(0x10, 0x00, 3, 12 << 11);
+    cpu.execute(mfc0);
+    assert_eq!(cpu.read_gp(3), STATUS_CU1);
+    cpu.set_status(STATUS_KUC);
+    cpu.execute(mfc0);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::CoProcUnusable));
+    cpu.set_status(STATUS_KUC | STATUS_CU0);
+    cpu.execute(mfc0);
+    assert_eq!(cpu.read_gp(3), STATUS_KUC | STATUS_CU0);
+}
+
+#[test]
+fn tlb() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    let cop0 = |funct: u32| (0x10 << 26) | (1 << 25) | funct;
+    let tlbr = cop0(0x01);
+    let tlbwi = cop0(0x02);
+    let tlbp = cop0(0x08);
+
+    // mtc0 $1, Index; mtc0 $2, EntryHi; mtc0 $3, EntryLo; tlbwi
+    cpu.mem().write_word(0x0, (0x10 << 26) | (0x04 << 21) | (1 << 16) | ((cp0_reg::INDEX as u32) << 11));
+    cpu.mem().write_word(0x4, (0x10 << 26) | (0x04 << 21) | (2 << 16) | ((cp0_reg::ENTRY_HI as u32) << 11));
+    cpu.mem().write_word(0x8, (0x10 << 26) | (0x04 << 21) | (3 << 16) | ((cp0_reg::ENTRY_LO as u32) << 11));
+    cpu.mem().write_word(0xC, tlbwi);
+    // mtc0 $0, Index; mtc0 $0, EntryLo; tlbp; tlbr
+    cpu.mem().write_word(0x10, (0x10 << 26) | (0x04 << 21) | ((cp0_reg::INDEX as u32) << 11));
+    cpu.mem().write_word(0x14, (0x10 << 26) | (0x04 << 21) | ((cp0_reg::ENTRY_LO as u32) << 11));
+    cpu.mem().write_word(0x18, tlbp);
+    cpu.mem().write_word(0x1C, tlbr);
+    cpu.write_gp(1, 12 << 8);
+    cpu.write_gp(2, 0x0040_0000);
+    cpu.write_gp(3, 0x0010_0000 | ENTRY_LO_V | ENTRY_LO_D);
+    cpu.write_pc(KSEG0);
+
+    for _ in 0..4 {
+        cpu.step();
+    }
+    assert_eq!(cpu.coproc_0().tlb_entry(12), TLBEntry { entry_hi: 0x0040_0000, entry_lo: 0x0010_0600 });
+
+    for _ in 0..3 {
+        cpu.step();
+    }
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::INDEX), 12 << 8);
+
+    cpu.step();
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::ENTRY_LO), 0x0010_0600);
+}
+
+#[test]
+fn tlb_translate() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    // Map virtual page 0x0040_0000 to physical page 0.
+    cpu.coproc_0().move_to_reg(cp0_reg::ENTRY_HI, 0x0040_0000);
+    cpu.coproc_0().move_to_reg(cp0_reg::ENTRY_LO, ENTRY_LO_V | ENTRY_LO_D);
+    cpu.coproc_0().tlb_write_indexed();
+
+    // lw $1, 0x100($2); sw $1, 0x200($0)
+    cpu.mem().write_word(0x0, make_i_instr(0x23, 2, 1, 0x100));
+    cpu.mem().write_word(0x4, make_i_instr(0x2B, 0, 1, 0x200));
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.write_gp(2, 0x0040_0000);
+    cpu.write_pc(KSEG0);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x1234_5678);
+
+    // Page 0 is not mapped.
+    cpu.step();
+    assert_eq!(cpu.mem().read_word(0x200), 0);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::BAD_VADDR), 0x200);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::CAUSE) & CAUSE_EXC_CODE, (ExceptionCode::TLBStore as u32) << 2);
+}
+
+#[test]
+fn execute() {
+    let mut cpu = MIPSI::default();
+
+    // Memory at the PC holds a NOP.
+    cpu.write_gp(1, 0x10);
+    cpu.execute(make_i_instr(0x09, 1, 2, 0x20));
+    assert_eq!(cpu.read_gp(2), 0x30);
+    assert_eq!(cpu.read_pc(), 4);
+
+    // beq $0, $0, 0x10 (from 0x4)
+    cpu.execute(make_i_instr(0x04, 0, 0, 0x4));
+    assert!(cpu.in_delay_slot());
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 0x18);
+}
+
+#[test]
+fn bus_error() {
+    let mem = UnmappedMemTest { bytes: vec![0; 0x1000] };
+    let mut cpu = MIPSI::<UnmappedMemTest>::with_memory(Box::new(mem))
+        .add_coproc0(TestCoproc0::default())
+        .build();
+
+    // lw $1, 0x2000($0); sw $1, 0x2000($0)
+    cpu.mem().write_word(0, make_i_instr(0x23, 0, 1, 0x2000));
+    cpu.mem().write_word(4, make_i_instr(0x2B, 0, 1, 0x2000));
+    cpu.write_gp(1, 0x1234);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x1234);
+    cpu.step();
+    assert_eq!(cpu.coproc_0().exceptions, vec![ExceptionCode::DataBusError, ExceptionCode::DataBusError]);
+
+    cpu.write_pc(0x2000);
+    cpu.step();
+    assert_eq!(cpu.coproc_0().exceptions.last(), Some(&ExceptionCode::InstructionBusError));
+}
+
+#[test]
+fn entry() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_entry(0x800)
+        .build();
+
+    cpu.mem().write_word(0x800, make_i_instr(0x09, 0, 1, 0x10));
+    assert_eq!(cpu.read_pc(), 0x800);
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x10);
+    assert_eq!(cpu.read_pc(), 0x804);
+
+    let cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_boot_vector()
+        .build();
+    assert_eq!(cpu.read_pc(), BOOT_VECTOR);
+}
+
+#[test]
+fn exception_vector() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_entry(KSEG0 + 0x100)
+        .build();
+
+    // beq $0, $0, 0x10; syscall
+    cpu.mem().write_word(0x100, make_i_instr(0x04, 0, 0, 0x4));
+    cpu.mem().write_word(0x104, 0x0000_000C);
+
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+    assert!(!cpu.in_delay_slot());
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::EPC), KSEG0 + 0x100);
+    let cause = cpu.coproc_0().move_from_reg(cp0_reg::CAUSE);
+    assert_eq!(cause & CAUSE_EXC_CODE, (ExceptionCode::Syscall as u32) << 2);
+    assert_ne!(cause & CAUSE_BD, 0);
+}
+
+#[test]
+fn exception_vector_from_branch() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_exception_vectoring()
+        .with_entry(KSEG0)
+        .build();
+
+    // bc1t 0x10, with coprocessor 1 unusable; break in the handler.
+    cpu.mem().write_word(0x0, (0x11 << 26) | (0x08 << 21) | (1 << 16) | 0x4);
+    cpu.mem().write_word(0x80, 0x0000_000D);
+
+    cpu.step();
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::CoProcUnusable));
+    assert_eq!(cpu.read_pc(), VECTOR_GENERAL);
+    // The handler doesn't start in a delay slot.
+    assert!(!cpu.in_delay_slot());
+    assert_eq!(cpu.successors().fall_through, Some(VECTOR_GENERAL + 4));
+
+    cpu.step();
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::Breakpoint));
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::EPC), VECTOR_GENERAL);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::CAUSE) & CAUSE_BD, 0);
+}
+
+#[test]
+fn exception_vectoring_off() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_entry(KSEG0 + 0x100)
+        .build();
+    cpu.set_status(STATUS_IEC | STATUS_IM);
+
+    // syscall; addiu $1, $1, 1
+    cpu.mem().write_word(0x100, 0x0000_000C);
+    cpu.mem().write_word(0x104, make_i_instr(0x09, 1, 1, 1));
+
+    // The exception is recorded and passed to coprocessor 0, but execution carries on.
+    cpu.step();
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::Syscall));
+    assert_eq!(cpu.read_pc(), KSEG0 + 0x104);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::EPC), KSEG0 + 0x100);
+    assert_eq!(cpu.status(), STATUS_IEC | STATUS_IM);
+
+    // Interrupts aren't taken.
+    cpu.raise_interrupt(2);
+    assert_ne!(cpu.pending_interrupts(), 0);
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 1);
+    assert!(cpu.mem().acknowledged.is_empty());
+}
+
+#[test]
+fn fetch() {
+    let mem = CountingMemTest { mem: LittleMemTest::new(0x1000), fetches: 0, reads: 0 };
+    let mut cpu = MIPSI::<CountingMemTest>::with_memory(Box::new(mem)).build();
+
+    // lw $1, 0x100($0); addiu $1, $1, 1; lb $2, 0x100($0)
+    cpu.mem().write_word(0, make_i_instr(0x23, 0, 1, 0x100));
+    cpu.mem().write_word(4, make_i_instr(0x09, 1, 1, 1));
+    cpu.mem().write_word(8, make_i_instr(0x20, 0, 2, 0x100));
+
+    for _ in 0..3 {
+        cpu.step();
+    }
+    assert_eq!(cpu.mem().fetches, 3);
+    assert_eq!(cpu.mem().reads, 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn profile() {
+    let mut cpu = MIPSI::default();
+
+    // addiu $1, $0, 3; loop: addiu $1, $1, -1; bne $1, $0, loop; nop
+    cpu.mem().write_word(0x0, make_i_instr(0x09, 0, 1, 3));
+    cpu.mem().write_word(0x4, make_i_instr(0x09, 1, 1, 0xFFFF));
+    cpu.mem().write_word(0x8, make_i_instr(0x05, 1, 0, 0xFFFE));
+    cpu.set_profiling(true);
+
+    for _ in 0..10 {
+        cpu.step();
+    }
+    assert_eq!(cpu.read_pc(), 0x10);
+    assert_eq!(cpu.profile().get(&0x0), Some(&1));
+    assert_eq!(cpu.profile().get(&0x4), Some(&3));
+    assert_eq!(cpu.profile().get(&0x8), Some(&3));
+    assert_eq!(cpu.profile().get(&0xC), Some(&3));
+
+    cpu.clear_profile();
+    cpu.set_profiling(false);
+    cpu.step();
+    assert!(cpu.profile().is_empty());
+}
+
+#[test]
+fn last_exception() {
+    let mut cpu = MIPSI::default();
+
+    // lw $1, 0x102($0); nop
+    cpu.mem().write_word(0, make_i_instr(0x23, 0, 1, 0x102));
+    cpu.write_gp(1, 0x55);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 0x55);
+    assert_eq!(cpu.last_exception(), Some(ExceptionInfo {
+        code:       ExceptionCode::AddrErrorLoad,
+        pc:         0,
+        bad_addr:   Some(0x102),
+        decode:     None,
+    }));
+
+    cpu.step();
+    assert_eq!(cpu.last_exception(), None);
+
+    cpu.write_gp(2, 0x8000_0000);
+    cpu.execute(0x0042_1820);   // add $3, $2, $2
+    assert_eq!(cpu.last_exception(), Some(ExceptionInfo {
+        code:       ExceptionCode::ArithmeticOverflow,
+        pc:         8,
+        bad_addr:   None,
+        decode:     None,
+    }));
+}
+
+#[test]
+fn decode_error() {
+    let mut cpu = MIPSI::default();
+
+    // dsra32 $1, $2, 4 (MIPS III)
+    cpu.execute(0x0002_093F);
+    assert_eq!(cpu.last_exception().and_then(|e| e.decode), Some(DecodeError {
+        instr:      0x0002_093F,
+        opcode:     0x00,
+        subop:      Some(0x3F),
+    }));
+
+    // dmfc1 $1, $2 (MIPS III)
+    cpu.execute(0x4421_1000);
+    let info = cpu.last_exception().unwrap();
+    assert_eq!(info.code, ExceptionCode::ReservedInstruction);
+    assert_eq!(info.pc, 4);
+    assert_eq!(info.decode, Some(DecodeError {
+        instr:      0x4421_1000,
+        opcode:     0x11,
+        subop:      Some(0x01),
+    }));
+
+    // ld $1, 0($2) (MIPS III)
+    cpu.execute(0xDC41_0000);
+    assert_eq!(cpu.last_exception().and_then(|e| e.decode).map(|d| (d.opcode, d.subop)), Some((0x37, None)));
+
+    // Exceptions from executing an instruction have no decode details.
+    cpu.execute(0x0000_000C);   // syscall
+    assert_eq!(cpu.last_exception().map(|e| (e.code, e.decode)), Some((ExceptionCode::Syscall, None)));
+}
+
+#[test]
+fn decode_error_in_handler() {
+    let mut cpu = MIPSI::default();
+    cpu.set_exception_handler(Box::new(|cpu, _| {
+        let instr = cpu.last_exception().and_then(|e| e.decode).map_or(0, |d| d.instr);
+        cpu.write_gp(1, instr);
+    }));
+
+    // The handler can see what couldn't be decoded.
+    cpu.execute(0x0002_093F);
+    assert_eq!(cpu.read_gp(1), 0x0002_093F);
+}
+
+#[test]
+fn coproc_operation_unimplemented() {
+    let mut cpu = MIPSI::default();
+
+    // cop1 with funct 0x3F, which the coprocessor doesn't implement.
+    // The operation is passed on, and it is up to the coprocessor to reject it, so nothing is recorded.
+    cpu.execute(0x4600_003F);
+    assert_eq!(cpu.last_exception(), None);
+
+    // Without coprocessor 1, it is unusable rather than reserved.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    cpu.execute(0x4600_003F);
+    assert_eq!(cpu.last_exception().map(|e| (e.code, e.decode)), Some((ExceptionCode::CoProcUnusable, None)));
+}
+
+#[test]
+fn translation() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_translation(Translation::Kseg)
+        .build();
+
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.write_gp(1, KSEG0);
+    cpu.write_gp(2, KSEG1);
+    cpu.lw(1, 3, 0x100);
+    cpu.lw(2, 4, 0x100);
+    cpu.lw(0, 5, 0x100);
+    assert_eq!(cpu.read_gp(3), 0x1234_5678);
+    assert_eq!(cpu.read_gp(4), 0x1234_5678);
+    assert_eq!(cpu.read_gp(5), 0x1234_5678);
+
+    // The TLB would fault on kuseg.
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .build();
+
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.lw(0, 5, 0x100);
+    assert_eq!(cpu.read_gp(5), 0);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::TLBLoad));
+
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_translation(Translation::Flat)
+        .build();
+
+    cpu.mem().write_word(0x100, 0x1234_5678);
+    cpu.lw(0, 5, 0x100);
+    assert_eq!(cpu.read_gp(5), 0x1234_5678);
+}
+
+#[test]
+fn display() {
+    let mut cpu = MIPSI::default();
+
+    // jal 0x100; addiu $1, $0, 0x12
+    cpu.mem().write_word(0, (0x03 << 26) | (0x100 >> 2));
+    cpu.mem().write_word(4, make_i_instr(0x09, 0, 1, 0x12));
+    cpu.step();
+    cpu.step();
+
+    let state = format!("{}", cpu);
+    assert!(state.contains("pc=0x00000100"));
+    assert!(state.contains("ra=0x00000008"));
+    assert!(state.contains("at=0x00000012"));
+    assert!(format!("{:?}", cpu).contains("pc: 256"));
+}
+
+// Run a compiled program end-to-end. See testdata/program.ll for the source.
+#[test]
+fn compiled_program() {
+    let mut cpu = MIPSI::<RamMemory<0x1000>>::with_memory(Box::new(RamMemory::new()))
+        .with_generation(Generation::MIPS32)
+        .with_flat_binary(0, include_bytes!("testdata/program.bin"))
+        .build();
+
+    let mut steps = 0;
+    while cpu.last_exception().map(|e| e.code) != Some(ExceptionCode::Syscall) {
+        assert_eq!(cpu.step(), StepResult::Executed);
+        assert_eq!(cpu.last_exception().filter(|e| e.code != ExceptionCode::Syscall), None);
+        steps += 1;
+        assert!(steps < 100_000, "program didn't finish");
+    }
+
+    assert_eq!(cpu.last_exception().map(|e| e.pc), Some(0x30));
+    assert_eq!(cpu.read_gp(16), 610);
+    assert_eq!(cpu.read_gp(17), 0x7806_812C);
+    assert_eq!(cpu.read_gp(18), 21);
+    assert_eq!(cpu.read_gp(29), 0x1000);
+}
+
+#[test]
+fn replace_memory() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    // addiu $1, $1, 1
+    cpu.mem().write_word(0, make_i_instr(0x09, 1, 1, 1));
+    cpu.mem().write_word(4, make_i_instr(0x09, 1, 1, 1));
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 1);
+
+    // addiu $1, $1, 0x10
+    let mut mem = LittleMemTest::new(0x1000);
+    mem.write_word(4, make_i_instr(0x09, 1, 1, 0x10));
+    let mut old = cpu.replace_memory(Box::new(mem));
+    assert_eq!(old.read_word(4), make_i_instr(0x09, 1, 1, 1));
+
+    cpu.step();
+    assert_eq!(cpu.read_pc(), 8);
+    assert_eq!(cpu.read_gp(1), 0x11);
+}
+
+#[test]
+fn preload() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000))).build();
+    cpu.preload(&[
+        (0x000, make_i_instr(0x09, 1, 1, 1)),
+        (0x120, 0x1234_5678),
+        (0xFFC, 0xDEAD_BEEF),
+        (0x800, 0x8000_0000),
+    ]);
+
+    assert_eq!(cpu.mem().read_word(0x000), make_i_instr(0x09, 1, 1, 1));
+    assert_eq!(cpu.mem().read_word(0x120), 0x1234_5678);
+    assert_eq!(cpu.mem().read_word(0xFFC), 0xDEAD_BEEF);
+    assert_eq!(cpu.mem().read_word(0x800), 0x8000_0000);
+    assert_eq!(cpu.mem().read_word(0x004), 0);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 1);
+}
+
+#[test]
+fn dirty_registers() {
+    let mut cpu = MIPSI::default();
+    cpu.write_gp(1, 5);
+    cpu.write_gp(2, 7);
+    // Nothing is tracked until enabled.
+    assert_eq!(cpu.dirty_registers(), 0);
+
+    cpu.set_dirty_tracking(true);
+    // add $3, $1, $2
+    cpu.execute(0x0022_1820);
+    assert_eq!(cpu.read_gp(3), 12);
+    assert_eq!(cpu.dirty_registers(), 1 << 3);
+    assert_eq!(cpu.dirty_special(), DirtySpecial::default());
+
+    cpu.clear_dirty();
+    // mult $1, $2
+    cpu.execute(0x0022_0018);
+    assert_eq!(cpu.dirty_registers(), 0);
+    assert_eq!(cpu.dirty_special(), DirtySpecial { hi: true, lo: true, pc: false });
+
+    cpu.clear_dirty();
+    // jal 0x100
+    cpu.execute(0x0C00_0040);
+    assert_eq!(cpu.dirty_registers(), 1 << 31);
+    assert!(cpu.dirty_special().pc);
+}
+
+#[test]
+fn isa_level() {
+    let build = |isa_level| MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .with_isa_level(isa_level)
+        .build();
+    // ll $2, 0x100($0)
+    let ll = make_i_instr(0x30, 0, 2, 0x100);
+
+    let mut cpu = build(IsaLevel::MipsI);
+    cpu.mem().write_word(0x100, 0x1234);
+    cpu.execute(ll);
+    assert_eq!(cpu.last_exception().map(|e| e.code), Some(ExceptionCode::ReservedInstruction));
+    assert_eq!(cpu.read_gp(2), 0);
+    // MIPS I instructions still execute.
+    cpu.execute(make_i_instr(0x23, 0, 2, 0x100));
+    assert_eq!(cpu.read_gp(2), 0x1234);
+
+    let mut cpu = build(IsaLevel::MipsII);
+    cpu.mem().write_word(0x100, 0x1234);
+    cpu.execute(ll);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.read_gp(2), 0x1234);
+    // clz $3, $2 is MIPS32.
+    cpu.execute(0x7043_1820);
+    assert_eq!(cpu.last_exception().and_then(|e| e.decode).and_then(|d| d.subop), Some(0x20));
+
+    assert_eq!(required_isa_level(0x7043_1820, Generation::MIPSI), IsaLevel::Mips32);
+    assert_eq!(required_isa_level(make_i_instr(0x2F, 1, 0, 0), Generation::MIPS32), IsaLevel::MipsIII);
+    assert_eq!(required_isa_level(make_i_instr(0x33, 1, 0, 0), Generation::MIPSI), IsaLevel::MipsI);
+    assert_eq!(required_isa_level(make_i_instr(0x33, 1, 0, 0), Generation::MIPS32), IsaLevel::Mips32);
+}
+
+#[test]
+fn peek_instruction() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(StandardCoproc0::new())
+        .with_entry(KSEG0 + 0x200)
+        .build();
+    // addiu $1, $0, 5; addiu $1, $1, 1
+    cpu.mem().write_word(0x200, make_i_instr(0x09, 0, 1, 5));
+    cpu.mem().write_word(0x204, make_i_instr(0x09, 1, 1, 1));
+
+    assert_eq!(cpu.peek_instruction(), Some(make_i_instr(0x09, 0, 1, 5)));
+    assert_eq!(cpu.current_disassembly().as_deref(), Some("addiu $at, $zero, 5"));
+    assert_eq!(cpu.read_pc(), KSEG0 + 0x200);
+
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 5);
+    assert_eq!(cpu.peek_instruction(), Some(make_i_instr(0x09, 1, 1, 1)));
+    cpu.step();
+    assert_eq!(cpu.read_gp(1), 6);
+
+    // Unmapped addresses can't be read, and coprocessor 0 isn't set up for a TLB miss.
+    cpu.write_pc(0x0040_0000);
+    assert_eq!(cpu.peek_instruction(), None);
+    assert_eq!(cpu.last_exception(), None);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::BAD_VADDR), 0);
+    assert_eq!(cpu.coproc_0().move_from_reg(cp0_reg::ENTRY_HI), 0);
+}
+
+#[test]
+fn peek_instruction_memory() {
+    // Without as_bytes, the memory sees a fetch.
+    let mem = CountingMemTest { mem: LittleMemTest::new(0x1000), fetches: 0, reads: 0 };
+    let mut cpu = MIPSI::<CountingMemTest>::with_memory(Box::new(mem)).build();
+    cpu.mem().write_word(0, make_i_instr(0x09, 0, 1, 5));
+    assert_eq!(cpu.peek_instruction(), Some(make_i_instr(0x09, 0, 1, 5)));
+    assert_eq!(cpu.mem().fetches, 1);
+    assert_eq!(cpu.cycles(), 0);
+
+    // With as_bytes, the bytes are read directly, in the memory's byte order.
+    let mut cpu = MIPSI::<RamMemory<0x1000>>::with_memory(Box::new(RamMemory::new())).build();
+    cpu.mem().write_word(0, make_i_instr(0x09, 0, 1, 5));
+    assert_eq!(cpu.peek_instruction(), Some(make_i_instr(0x09, 0, 1, 5)));
+    assert_eq!(cpu.successors().fall_through, Some(4));
+    assert_eq!(cpu.cycles(), 0);
+}
+
+#[test]
+fn access_cycles() {
+    let mut mem = LittleMemTest::new(0x1000);
+    mem.slow = 0x800..0x900;
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(mem)).build();
+    // lw $1, 0x800($0); sw $1, 0x100($0); nop
+    cpu.mem().write_word(0, make_i_instr(0x23, 0, 1, 0x800));
+    cpu.mem().write_word(4, make_i_instr(0x2B, 0, 1, 0x100));
+    assert_eq!(cpu.cycles(), 0);
+
+    // A fetch and a slow load.
+    cpu.step();
+    assert_eq!(cpu.cycles(), 5);
+    // A fetch and a fast store.
+    cpu.step();
+    assert_eq!(cpu.cycles(), 7);
+    cpu.step();
+    assert_eq!(cpu.cycles(), 8);
+
+    // Fetches from slow memory.
+    cpu.write_pc(0x800);
+    cpu.step();
+    assert_eq!(cpu.cycles(), 12);
+}
+
+#[test]
+fn reset_coprocessors() {
+    let mut cpu = MIPSI::<LittleMemTest>::with_memory(Box::new(LittleMemTest::new(0x1000)))
+        .add_coproc0(TestCoproc0::default())
+        .add_coproc1(TestCoproc::default())
+        .add_coproc3(TestCoproc::default())
+        .build();
+    cpu.coproc_1().unwrap().move_to_reg(1, 0x1234);
+
+    let mut attached = Vec::new();
+    cpu.for_each_coproc(|n, coproc| attached.push((n, coproc.move_from_reg(1))));
+    assert_eq!(attached, vec![(1, 0x1234), (3, 0)]);
+
+    cpu.reset_coprocessors();
+    assert_eq!(cpu.coproc_0().resets, 1);
+    assert_eq!(cpu.coproc_1().unwrap().resets, 1);
+    assert_eq!(cpu.coproc_1().unwrap().move_from_reg(1), 0);
+    assert_eq!(cpu.coproc_3().unwrap().resets, 1);
+}
+
+#[test]
+fn jump_segment() {
+    let mut cpu = MIPSI::default();
+
+    // j 0x100, with the delay slot in the next 256MB segment.
+    cpu.write_pc(0x0FFF_FFFC);
+    cpu.execute(0x0800_0040);
+    assert_eq!(cpu.read_pc(), 0x1000_0000);
+    assert_eq!(cpu.pending_branch_target(), Some(0x1000_0100));
+
+    // j 0x100, with the delay slot at the end of the segment.
+    cpu.write_pc(0x0FFF_FFF8);
+    cpu.execute(0x0800_0040);
+    assert_eq!(cpu.pending_branch_target(), Some(0x0000_0100));
+
+    // jal 0x100
+    cpu.write_pc(0x1FFF_FFFC);
+    cpu.execute(0x0C00_0040);
+    assert_eq!(cpu.pending_branch_target(), Some(0x2000_0100));
+    assert_eq!(cpu.read_gp(31), 0x2000_0004);
 }
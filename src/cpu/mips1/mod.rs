@@ -1,18 +1,49 @@
 mod instructions;
+/// Disassembler.
+mod disasm;
+/// Instruction encodings: primary opcodes, and the function and field codes within each group.
+pub mod opcodes;
 #[cfg(test)]
 mod mips1_test;
 
+use alloc::{
+    boxed::Box,
+    format,
+    string::String,
+    vec::Vec
+};
+use alloc::collections::BTreeSet;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
 use super::*;
 
 use crate::common::*;
 use crate::coproc::*;
 use crate::mem::{
     Memory,
-    Mem16,
-    Mem32
+    Mem32,
+    load_flat,
+    read_word_fast
 };
 
 pub use instructions::*;
+pub use disasm::*;
+
+/// The address a MIPS processor starts executing from after reset.
+pub const BOOT_VECTOR: u32 = 0xBFC0_0000;
+
+/// The value `MFHI` and `MFLO` read when HI and LO aren't ready yet.
+/// 
+/// This is only used when HI/LO hazards are enabled.
+pub const HILO_UNDEFINED: u32 = 0xDEAD_BEEF;
+
+/// A host callback for exceptions, set with `MIPSI::set_exception_handler`.
+pub type ExceptionHandler<Mem, C0, C1, C2, C3> = Box<dyn FnMut(&mut MIPSI<Mem, C0, C1, C2, C3>, ExceptionCode)>;
+
+/// A host callback for coprocessor operations, set with `MIPSI::set_coproc_observer`.
+pub type CoprocObserver = Box<dyn FnMut(usize, u32)>;
 
 /// Mips I processor.
 pub struct MIPSI<
@@ -25,16 +56,61 @@ pub struct MIPSI<
     gp_reg:     [u32; 32],
     hi:         u32,
     lo:         u32,
+    link:       Option<u32>,
 
     pc:         u32,
     pc_next:    u32,
+    delay_slot: bool,
+    // Set when the pc is moved while an instruction executes, by an exception or the host.
+    // The instruction's own branch is then discarded.
+    redirected: bool,
+    // Address of the instruction being executed.
+    current_pc: u32,
+    last_exception: Option<ExceptionInfo>,
 
     mem:        Box<Mem>,
 
     coproc0:    C0,
     coproc1:    Option<C1>,
     coproc2:    Option<C2>,
-    coproc3:    Option<C3>
+    coproc3:    Option<C3>,
+
+    syscall_handler:    Option<Box<dyn SyscallHandler<Mem>>>,
+    exception_handler:  Option<ExceptionHandler<Mem, C0, C1, C2, C3>>,
+    coproc_observer:    Option<CoprocObserver>,
+
+    config:         Config,
+    // Number of consecutive branch-to-self loops executed.
+    stuck_count:    usize,
+
+    interrupts:     InterruptController,
+
+    breakpoints:    BTreeSet<u32>,
+    // Set when a breakpoint was just reported, so the next step executes the instruction.
+    break_hit:      bool,
+    // Set by WAIT, until an interrupt is pending.
+    waiting:        bool,
+    // Number of instructions until the result of a multiply or divide is ready.
+    hilo_busy:      usize,
+    // A load waiting for the next instruction to execute before it is written back.
+    load_pending:   Option<(usize, u32)>,
+    // A load being written back once the current instruction has executed.
+    load_in_flight: Option<(usize, u32)>,
+    // Set when the syscall handler halts the CPU.
+    halted:         bool,
+    // Cycles taken by memory accesses.
+    cycles:         u64,
+
+    dirty_tracking: bool,
+    // GPRs written since the dirty flags were cleared, one bit per register.
+    dirty:          u32,
+    dirty_special:  DirtySpecial,
+
+    #[cfg(feature = "std")]
+    profiling:      bool,
+    // Number of times each PC has been executed.
+    #[cfg(feature = "std")]
+    profile:        HashMap<u32, u64>,
 }
 
 impl<
@@ -45,21 +121,53 @@ impl<
     C3: Coprocessor
 > MIPSI<Mem, C0, C1, C2, C3> {
     /// Make a new MIPS I processor.
-    fn new(mem: Box<Mem>, coproc0: C0, coproc1: Option<C1>, coproc2: Option<C2>, coproc3: Option<C3>) -> Self {
+    fn new(mem: Box<Mem>, coproc0: C0, coproc1: Option<C1>, coproc2: Option<C2>, coproc3: Option<C3>, syscall_handler: Option<Box<dyn SyscallHandler<Mem>>>, config: Config) -> Self {
         Self {
             gp_reg:     [0; 32],
             hi:         0,
             lo:         0,
+            link:       None,
+
+            pc:         config.entry,
+            pc_next:    config.entry.wrapping_add(4),
+            delay_slot: false,
+            redirected: false,
+            current_pc: config.entry,
+            last_exception: None,
+
+            mem,
 
-            pc:         0,
-            pc_next:    4,
+            coproc0,
+            coproc1,
+            coproc2,
+            coproc3,
 
-            mem:        mem,
+            syscall_handler,
+            exception_handler:  None,
+            coproc_observer:    None,
 
-            coproc0:    coproc0,
-            coproc1:    coproc1,
-            coproc2:    coproc2,
-            coproc3:    coproc3,
+            config,
+            stuck_count:    0,
+
+            interrupts:     config.interrupts,
+
+            breakpoints:    BTreeSet::new(),
+            break_hit:      false,
+            waiting:        false,
+            hilo_busy:      0,
+            load_pending:   None,
+            load_in_flight: None,
+            halted:         false,
+            cycles:         0,
+
+            dirty_tracking: false,
+            dirty:          0,
+            dirty_special:  DirtySpecial::default(),
+
+            #[cfg(feature = "std")]
+            profiling:      false,
+            #[cfg(feature = "std")]
+            profile:        HashMap::new(),
         }
     }
 
@@ -70,6 +178,314 @@ impl<
     pub fn with_memory(mem: Box<Mem>) -> MIPSIBuilder<Mem> {
         MIPSIBuilder::<Mem>::new(mem)
     }
+
+    /// Capture the register state of the processor and its coprocessors.
+    /// 
+    /// Memory is not included.
+    pub fn save_state(&self) -> MIPSIState {
+        MIPSIState {
+            gp_reg:     self.gp_reg,
+            hi:         self.hi,
+            lo:         self.lo,
+            link:       self.link,
+            pc:         self.pc,
+            pc_next:    self.pc_next,
+            delay_slot: self.delay_slot,
+            load_delay: self.load_pending,
+            waiting:    self.waiting,
+            halted:     self.halted,
+            hilo_busy:  self.hilo_busy,
+            cycles:     self.cycles,
+            coprocs:    [
+                self.coproc0.save_state(),
+                self.coproc1.as_ref().map_or_else(Vec::new, |c| c.save_state()),
+                self.coproc2.as_ref().map_or_else(Vec::new, |c| c.save_state()),
+                self.coproc3.as_ref().map_or_else(Vec::new, |c| c.save_state()),
+            ],
+        }
+    }
+
+    /// Restore register state captured by `save_state`.
+    /// 
+    /// The last exception, and a breakpoint that was just reported, aren't part of the state, so they are cleared.
+    pub fn load_state(&mut self, state: &MIPSIState) {
+        self.gp_reg = state.gp_reg;
+        self.gp_reg[0] = 0;
+        self.hi = state.hi;
+        self.lo = state.lo;
+        self.link = state.link;
+        self.pc = state.pc;
+        self.pc_next = state.pc_next;
+        self.delay_slot = state.delay_slot;
+        self.load_pending = state.load_delay;
+        self.load_in_flight = None;
+        self.waiting = state.waiting;
+        self.halted = state.halted;
+        self.hilo_busy = state.hilo_busy;
+        self.cycles = state.cycles;
+        self.coproc0.load_state(&state.coprocs[0]);
+        self.for_each_coproc(|num, coproc| coproc.load_state(&state.coprocs[num]));
+        self.last_exception = None;
+        self.break_hit = false;
+    }
+
+    /// Call `f` on each attached coprocessor other than coprocessor 0, with its number.
+    /// 
+    /// Coprocessor 0 has its own trait, and is always attached. Use `coproc_0` to reach it.
+    pub fn for_each_coproc(&mut self, mut f: impl FnMut(usize, &mut dyn Coprocessor)) {
+        if let Some(coproc) = self.coproc1.as_mut() {
+            f(1, coproc);
+        }
+        if let Some(coproc) = self.coproc2.as_mut() {
+            f(2, coproc);
+        }
+        if let Some(coproc) = self.coproc3.as_mut() {
+            f(3, coproc);
+        }
+    }
+
+    /// Reset coprocessor 0 and each attached coprocessor to its power-on state.
+    pub fn reset_coprocessors(&mut self) {
+        self.coproc0.reset();
+        self.for_each_coproc(|_, coproc| coproc.reset());
+    }
+
+    /// Get the number of cycles taken so far.
+    /// 
+    /// Each instruction fetch, load and store takes the number of cycles given by
+    /// the memory's `access_cycles`.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Get the interrupt controller, to see which lines are asserted.
+    pub fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    /// Replace the memory, returning the old memory.
+    /// 
+    /// All other state is kept, so execution continues from the same pc in the new memory.
+    pub fn replace_memory(&mut self, mem: Box<Mem>) -> Box<Mem> {
+        core::mem::replace(&mut self.mem, mem)
+    }
+
+    /// Write words to scattered physical addresses, given as `(address, word)` pairs.
+    /// 
+    /// This is useful to set up sparse memory. For contiguous data, use `MIPSIBuilder::with_flat_binary`.
+    pub fn preload(&mut self, entries: &[(u32, u32)])
+        where Mem: Mem32<Addr = u32> {
+        for &(addr, word) in entries {
+            self.mem.write_word(addr, word);
+        }
+    }
+
+    /// Handle every exception in host code, instead of with coprocessor 0.
+    /// 
+    /// `f` is called with the CPU and the exception code each time an exception is triggered,
+    /// once `last_exception` has been recorded. Coprocessor 0 isn't told about the exception,
+    /// and the CPU doesn't jump to a vector, so execution continues after the instruction unless `f` moves the pc.
+    /// An exception triggered from inside `f` goes to coprocessor 0.
+    pub fn set_exception_handler(&mut self, f: ExceptionHandler<Mem, C0, C1, C2, C3>) {
+        self.exception_handler = Some(f);
+    }
+
+    /// Remove the exception handler, so exceptions go to coprocessor 0 again.
+    pub fn clear_exception_handler(&mut self) {
+        self.exception_handler = None;
+    }
+
+    /// Watch the operations passed to coprocessors.
+    /// 
+    /// `f` is called with the coprocessor number and the operation (the low 25 bits of the `COPz` instruction)
+    /// before each operation is passed to the coprocessor. It isn't called if the coprocessor is unusable.
+    pub fn set_coproc_observer(&mut self, f: CoprocObserver) {
+        self.coproc_observer = Some(f);
+    }
+
+    /// Stop watching coprocessor operations.
+    pub fn clear_coproc_observer(&mut self) {
+        self.coproc_observer = None;
+    }
+
+    /// Enable or disable tracking which registers are written.
+    /// 
+    /// Disabled by default.
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty_tracking = enabled;
+    }
+
+    /// Get the GPRs written since `clear_dirty` was called, one bit per register.
+    /// 
+    /// Writes are only tracked while dirty tracking is enabled.
+    pub fn dirty_registers(&self) -> u32 {
+        self.dirty
+    }
+
+    /// Get which of hi, lo and pc were written since `clear_dirty` was called.
+    pub fn dirty_special(&self) -> DirtySpecial {
+        self.dirty_special
+    }
+
+    /// Clear the dirty flags for all registers.
+    pub fn clear_dirty(&mut self) {
+        self.dirty = 0;
+        self.dirty_special = DirtySpecial::default();
+    }
+
+    fn mark_pc_dirty(&mut self) {
+        if self.dirty_tracking {
+            self.dirty_special.pc = true;
+        }
+    }
+
+    // Record an exception, and pass it to the exception handler or coprocessor 0.
+    fn take_exception(&mut self, exception: ExceptionCode, bad_addr: Option<u32>, decode: Option<DecodeError>) {
+        self.last_exception = Some(ExceptionInfo {
+            code:       exception,
+            pc:         self.current_pc,
+            bad_addr,
+            decode,
+        });
+        if let Some(mut handler) = self.exception_handler.take() {
+            handler(self, exception);
+            if self.exception_handler.is_none() {
+                self.exception_handler = Some(handler);
+            }
+            return;
+        }
+        if exception == ExceptionCode::DebugBreak {
+            return;
+        }
+        let epc = if self.delay_slot { self.current_pc.wrapping_sub(4) } else { self.current_pc };
+        if !self.config.vector_exceptions {
+            // Coprocessor 0 records the exception, but the CPU stays in the same mode,
+            // so interrupts aren't masked by a handler that never runs.
+            let status = self.coproc0.move_from_reg(cp0_reg::STATUS);
+            self.coproc0.handle_exception(exception, epc, self.delay_slot);
+            self.coproc0.move_to_reg(cp0_reg::STATUS, status);
+            return;
+        }
+        self.coproc0.handle_exception(exception, epc, self.delay_slot);
+        if let Some(vector) = self.coproc0.exception_vector(exception) {
+            self.pc = vector;
+            self.pc_next = vector.wrapping_add(4);
+            self.delay_slot = false;
+            self.redirected = true;
+            self.mark_pc_dirty();
+        }
+    }
+
+    // Write back a load once the instruction in its delay slot has executed.
+    fn retire_load(&mut self) {
+        if let Some((reg, val)) = self.load_in_flight.take() {
+            self.gp_reg[reg] = val;
+            if self.dirty_tracking {
+                self.dirty |= 1 << reg;
+            }
+        }
+    }
+
+    // Panic if the state after a step is inconsistent.
+    fn check_invariants(&self) {
+        assert_eq!(self.gp_reg[0], 0, "$zero is not 0 after executing 0x{:08X}", self.current_pc);
+        let address_error = matches!(
+            self.last_exception.map(|e| e.code),
+            Some(ExceptionCode::AddrErrorLoad) | Some(ExceptionCode::AddrErrorStore)
+        );
+        assert!(
+            (self.current_pc & 3) == 0 || address_error,
+            "executed from unaligned address 0x{:08X} without an address error", self.current_pc
+        );
+        assert!(
+            self.delay_slot || self.pc_next == self.pc.wrapping_add(4),
+            "after executing 0x{:08X}, the next pc is 0x{:08X} outside of a delay slot (pc 0x{:08X})",
+            self.current_pc, self.pc_next, self.pc
+        );
+    }
+
+    /// Enable or disable counting how many times each instruction address is executed.
+    /// 
+    /// Disabled by default.
+    #[cfg(feature = "std")]
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+
+    /// Get the number of times each instruction address has been executed while profiling.
+    #[cfg(feature = "std")]
+    pub fn profile(&self) -> &HashMap<u32, u64> {
+        &self.profile
+    }
+
+    /// Clear the profile counts.
+    #[cfg(feature = "std")]
+    pub fn clear_profile(&mut self) {
+        self.profile.clear();
+    }
+}
+
+/// Registers other than the GPRs which have been written, while dirty tracking is enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirtySpecial {
+    pub hi: bool,
+    pub lo: bool,
+    /// Set when the pc leaves sequential order: branches, jumps and exceptions.
+    pub pc: bool,
+}
+
+/// The register state of a MIPS I processor, for save states.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MIPSIState {
+    /// The general-purpose registers. Register 0 always reads as 0, whatever is stored here.
+    pub gp_reg:     [u32; 32],
+    pub hi:         u32,
+    pub lo:         u32,
+    /// The address linked by `LL`.
+    pub link:       Option<u32>,
+    /// The address of the next instruction to be executed.
+    pub pc:         u32,
+    /// The address of the instruction after that, or a pending branch target.
+    pub pc_next:    u32,
+    /// Set if the next instruction is in a delay slot.
+    pub delay_slot: bool,
+    /// A load waiting to be written back after the next instruction, as (register, value).
+    pub load_delay: Option<(usize, u32)>,
+    /// Set if the CPU is idle after a `WAIT` instruction.
+    pub waiting:    bool,
+    /// Set if the syscall handler halted the CPU.
+    pub halted:     bool,
+    /// The number of instructions until the result of a multiply or divide is ready.
+    pub hilo_busy:  usize,
+    /// The number of cycles taken so far.
+    pub cycles:     u64,
+    /// The state of each coprocessor, by number. Empty for missing coprocessors.
+    pub coprocs:    [Vec<u8>; 4],
+}
+
+impl MIPSIState {
+    /// If the next instruction is in a delay slot, get the address to go to after it.
+    pub fn pending_branch_target(&self) -> Option<u32> {
+        self.delay_slot.then_some(self.pc_next)
+    }
+}
+
+// Options set by the builder.
+#[derive(Clone, Copy, Default)]
+struct Config {
+    entry:          u32,
+    translation:    Translation,
+    trap_mode:      TrapMode,
+    generation:     Generation,
+    isa_level:      IsaLevel,
+    stuck_limit:    Option<usize>,
+    strict_decode:  bool,
+    fetch_guard:    bool,
+    vector_exceptions:  bool,
+    hilo_delay:     usize,
+    load_delay:     bool,
+    check_invariants:   bool,
+    interrupts:     InterruptController,
 }
 
 //
@@ -86,6 +502,10 @@ pub struct MIPSIBuilder<
     coproc1:    Option<C1>,
     coproc2:    Option<C2>,
     coproc3:    Option<C3>,
+
+    syscall_handler:    Option<Box<dyn SyscallHandler<Mem>>>,
+
+    config:     Config,
 }
 
 impl<
@@ -97,11 +517,15 @@ impl<
 > MIPSIBuilder<Mem, C0, C1, C2, C3> {
     fn new(mem: Box<Mem>) -> MIPSIBuilder<Mem, EmptyCoproc0, EmptyCoproc, EmptyCoproc, EmptyCoproc> {
         MIPSIBuilder {
-            mem:        mem,
+            mem,
             coproc0:    EmptyCoproc0{},
             coproc1:    None,
             coproc2:    None,
             coproc3:    None,
+
+            syscall_handler:    None,
+
+            config:     Config::default(),
         }
     }
 
@@ -109,10 +533,14 @@ impl<
     pub fn add_coproc0<NewC0: Coprocessor0>(self, coproc0: NewC0) -> MIPSIBuilder<Mem, NewC0, C1, C2, C3> {
         MIPSIBuilder {
             mem:        self.mem,
-            coproc0:    coproc0,
+            coproc0,
             coproc1:    self.coproc1,
             coproc2:    self.coproc2,
             coproc3:    self.coproc3,
+
+            syscall_handler:    self.syscall_handler,
+
+            config:     self.config,
         }
     }
 
@@ -124,6 +552,10 @@ impl<
             coproc1:    Some(coproc1),
             coproc2:    self.coproc2,
             coproc3:    self.coproc3,
+
+            syscall_handler:    self.syscall_handler,
+
+            config:     self.config,
         }
     }
 
@@ -135,6 +567,10 @@ impl<
             coproc1:    self.coproc1,
             coproc2:    Some(coproc2),
             coproc3:    self.coproc3,
+
+            syscall_handler:    self.syscall_handler,
+
+            config:     self.config,
         }
     }
 
@@ -146,12 +582,246 @@ impl<
             coproc1:    self.coproc1,
             coproc2:    self.coproc2,
             coproc3:    Some(coproc3),
+
+            syscall_handler:    self.syscall_handler,
+
+            config:     self.config,
         }
     }
 
+    /// Set the address of the first instruction to execute.
+    /// 
+    /// Defaults to 0.
+    pub fn with_entry(mut self, addr: u32) -> Self {
+        self.config.entry = addr;
+        self
+    }
+
+    /// Start executing from the reset vector, as real hardware does.
+    pub fn with_boot_vector(self) -> Self {
+        self.with_entry(BOOT_VECTOR)
+    }
+
+    /// Load a flat binary image into memory at `addr`, and start executing from its first byte.
+    /// 
+    /// The image is loaded at the physical address `addr`, and the entry point is the same virtual address.
+    pub fn with_flat_binary(mut self, addr: u32, bytes: &[u8]) -> Self
+        where Mem: Mem32<Addr = u32> {
+        load_flat(self.mem.as_mut(), addr, bytes);
+        self.with_entry(addr)
+    }
+
+    /// Set how virtual addresses are translated before accessing memory.
+    /// 
+    /// Defaults to `Translation::Tlb`.
+    pub fn with_translation(mut self, translation: Translation) -> Self {
+        self.config.translation = translation;
+        self
+    }
+
+    /// Set how the trapping arithmetic instructions treat overflow.
+    /// 
+    /// Defaults to `TrapMode::Strict`.
+    pub fn with_trap_mode(mut self, trap_mode: TrapMode) -> Self {
+        self.config.trap_mode = trap_mode;
+        self
+    }
+
+    /// Set the architecture generation to decode instructions for.
+    /// 
+    /// Defaults to `Generation::MIPSI`.
+    pub fn with_generation(mut self, generation: Generation) -> Self {
+        self.config.generation = generation;
+        self
+    }
+
+    /// Set the instruction set revision to allow.
+    /// Instructions from any later revision trigger a `ReservedInstruction` exception.
+    /// 
+    /// Defaults to `IsaLevel::Mips32R2`, so that every implemented instruction is available.
+    /// For strict emulation of MIPS I hardware, use `IsaLevel::MipsI`.
+    pub fn with_isa_level(mut self, isa_level: IsaLevel) -> Self {
+        self.config.isa_level = isa_level;
+        self
+    }
+
+    /// Trigger a `ReservedInstruction` exception for instructions with unused fields that aren't zero.
+    /// 
+    /// By default these fields are ignored.
+    pub fn with_strict_decode(mut self) -> Self {
+        self.config.strict_decode = true;
+        self
+    }
+
+    /// Jump to the exception handler when an exception is triggered, and take interrupts.
+    /// 
+    /// Coprocessor 0 provides the address of the handler.
+    /// By default exceptions are recorded in `last_exception` and passed to coprocessor 0,
+    /// but execution continues with the next instruction, and interrupts are not taken.
+    /// The Status register is left as it was, so the CPU doesn't enter kernel mode or mask interrupts.
+    /// This suits testing instructions without a handler installed.
+    pub fn with_exception_vectoring(mut self) -> Self {
+        self.config.vector_exceptions = true;
+        self
+    }
+
+    /// Stop with `StepResult::GarbageFetch` instead of executing a fetched word of `0xFFFF_FFFF`.
+    /// 
+    /// This helps to catch a PC that has run off into unprogrammed memory.
+    /// By default the word is executed, and is a reserved instruction.
+    pub fn with_fetch_guard(mut self) -> Self {
+        self.config.fetch_guard = true;
+        self
+    }
+
+    /// Handle `SYSCALL` instructions on the host.
+    /// 
+    /// By default syscalls trigger the `Syscall` exception.
+    pub fn with_syscall_handler<H: SyscallHandler<Mem> + 'static>(mut self, handler: H) -> Self {
+        self.syscall_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Model the latency of multiply and divide results.
+    /// 
+    /// An `MFHI` or `MFLO` within `cycles` instructions of a `MULT`, `MULTU`, `DIV` or `DIVU`
+    /// (or a MIPS32 multiply-accumulate) reads `HILO_UNDEFINED` instead of the result.
+    /// By default results are ready immediately.
+    pub fn with_hilo_hazards(mut self, cycles: usize) -> Self {
+        self.config.hilo_delay = cycles;
+        self
+    }
+
+    /// Model the load delay slot of MIPS I.
+    /// 
+    /// The result of a load into a general-purpose register is written back only after the next instruction
+    /// has executed, so that instruction reads the old value. A second load to the same register in the delay slot
+    /// replaces the first, and `LWL` and `LWR` merge with a load in progress.
+    /// By default loads are written back immediately.
+    pub fn with_load_delay(mut self) -> Self {
+        self.config.load_delay = true;
+        self
+    }
+
+    /// Detect when the CPU is stuck in a branch-to-self loop.
+    /// 
+    /// Once a branch to itself with a NOP in its delay slot has been executed `limit` times in a row,
+    /// `step` returns `StepResult::Stuck`.
+    pub fn with_stuck_limit(mut self, limit: usize) -> Self {
+        self.config.stuck_limit = Some(limit);
+        self
+    }
+
+    /// Check the consistency of the CPU state after every step, and panic if it is broken.
+    /// 
+    /// This is for finding bugs in the emulator, or in host code that modifies the CPU.
+    /// After each step, `$zero` must be 0, the instruction must have been fetched from a word-aligned address
+    /// (unless an address error was raised), and the instruction after the next one must follow it directly
+    /// (unless the next one is in a delay slot).
+    /// By default no checks are made.
+    pub fn with_invariant_checks(mut self) -> Self {
+        self.config.check_invariants = true;
+        self
+    }
+
+    /// Set which interrupt line to take when several are pending at once.
+    /// 
+    /// Defaults to `InterruptController::new()`, where higher-numbered lines have priority.
+    pub fn with_interrupt_controller(mut self, interrupts: InterruptController) -> Self {
+        self.config.interrupts = interrupts;
+        self
+    }
+
     /// Make the MIPS I processor.
     pub fn build(self) -> MIPSI<Mem, C0, C1, C2, C3> {
-        MIPSI::new(self.mem, self.coproc0, self.coproc1, self.coproc2, self.coproc3)
+        MIPSI::new(self.mem, self.coproc0, self.coproc1, self.coproc2, self.coproc3, self.syscall_handler, self.config)
+    }
+}
+
+impl<
+    Mem: Mem32,
+    C0: Coprocessor0,
+    C1: Coprocessor,
+    C2: Coprocessor,
+    C3: Coprocessor
+> MIPSI<Mem, C0, C1, C2, C3>
+    where MIPSI<Mem, C0, C1, C2, C3>: MIPSICore, <Mem as Memory>::Addr: From<u32> {
+    /// Read the instruction at the pc, without executing it.
+    /// 
+    /// No exception is triggered, coprocessor 0 isn't changed by a failed translation, and no cycles are counted.
+    /// If the memory exposes its bytes with `as_bytes`, the word is read from them directly.
+    /// Otherwise it is read with `fetch_word`, so memory wrappers and devices see the access,
+    /// for example as a cache access or a logged fetch.
+    /// Returns `None` if the pc is unaligned, can't be translated, or the fetch is a bus error.
+    pub fn peek_instruction(&mut self) -> Option<u32> {
+        if (self.pc & 3) != 0 {
+            return None;
+        }
+        let addr = self.probe_translate(self.pc, false).ok()?;
+        if let Some(word) = read_word_fast(self.mem.as_ref(), addr) {
+            return Some(word);
+        }
+        self.mem.fetch_word(addr.into()).ok()
+    }
+
+    /// Find the possible addresses executed after the instruction at the pc, without executing it.
+    /// 
+    /// Conditional branches give both the taken and fall-through addresses.
+    /// Register jumps read the register now. Exceptions the instruction might trigger are ignored.
+    /// If the pc is in a delay slot, the only successor is the pending branch target.
+    /// The instruction is read with `peek_instruction`.
+    pub fn successors(&mut self) -> SuccessorSet {
+        if self.delay_slot {
+            return SuccessorSet {
+                fall_through:   None,
+                target:         Some(self.pc_next),
+            };
+        }
+        let instr = match self.peek_instruction() {
+            Some(instr) => instr,
+            None => return SuccessorSet::default(),
+        };
+        let after_delay = self.pc.wrapping_add(8);
+        match instruction_class(instr) {
+            InstrClass::Branch => SuccessorSet {
+                fall_through:   Some(after_delay),
+                target:         Some(self.pc.wrapping_add(4).wrapping_add(branch_offset(instr as u16))),
+            },
+            InstrClass::Jump => {
+                let target = if (instr >> 26) == opcodes::SPECIAL {
+                    self.read_gp(((instr >> 21) & 0x1F) as usize)
+                } else {
+                    (self.pc.wrapping_add(4) & 0xF000_0000) | ((instr & 0x03FF_FFFF) << 2)
+                };
+                SuccessorSet {
+                    fall_through:   None,
+                    target:         Some(target),
+                }
+            },
+            InstrClass::Reserved => SuccessorSet::default(),
+            _ => SuccessorSet {
+                fall_through:   Some(self.pc.wrapping_add(4)),
+                target:         None,
+            },
+        }
+    }
+
+    /// Disassemble the instruction at the pc, without executing it.
+    /// 
+    /// The instruction is read with `peek_instruction`.
+    pub fn current_disassembly(&mut self) -> Option<String> {
+        let instr = self.peek_instruction()?;
+        disassemble(self.pc, instr)
+    }
+
+    // Trigger a reserved instruction exception, recording what couldn't be decoded.
+    fn reserved_instruction(&mut self, instr: u32, subop: Option<u32>) {
+        let decode = DecodeError {
+            instr,
+            opcode: instr >> 26,
+            subop,
+        };
+        self.take_exception(ExceptionCode::ReservedInstruction, None, Some(decode));
     }
 }
 
@@ -169,11 +839,40 @@ impl<
     type Coproc3 = C3;
 
     fn read_gp(&self, reg: usize) -> u32 {
-        self.gp_reg[reg]
+        if reg == 0 {
+            0
+        } else {
+            self.gp_reg[reg]
+        }
     }
     fn write_gp(&mut self, reg: usize, val: u32) {
         if reg != 0 {
             self.gp_reg[reg] = val;
+            if self.dirty_tracking {
+                self.dirty |= 1 << reg;
+            }
+            // An instruction in a load delay slot that writes the same register wins.
+            if self.load_in_flight.map(|(r, _)| r) == Some(reg) {
+                self.load_in_flight = None;
+            }
+        }
+    }
+
+    fn write_gp_loaded(&mut self, reg: usize, val: u32) {
+        if !self.config.load_delay {
+            self.write_gp(reg, val);
+        } else if reg != 0 {
+            if self.load_in_flight.map(|(r, _)| r) == Some(reg) {
+                self.load_in_flight = None;
+            }
+            self.load_pending = Some((reg, val));
+        }
+    }
+
+    fn read_gp_loading(&self, reg: usize) -> u32 {
+        match self.load_in_flight {
+            Some((r, val)) if r == reg => val,
+            _ => self.read_gp(reg),
         }
     }
 
@@ -182,6 +881,9 @@ impl<
     }
     fn write_hi(&mut self, val: u32) {
         self.hi = val;
+        if self.dirty_tracking {
+            self.dirty_special.hi = true;
+        }
     }
 
     fn read_lo(&self) -> u32 {
@@ -189,6 +891,28 @@ impl<
     }
     fn write_lo(&mut self, val: u32) {
         self.lo = val;
+        if self.dirty_tracking {
+            self.dirty_special.lo = true;
+        }
+    }
+
+    fn read_link(&self) -> Option<u32> {
+        self.link
+    }
+    fn write_link(&mut self, addr: Option<u32>) {
+        self.link = addr;
+    }
+
+    fn trap_mode(&self) -> TrapMode {
+        self.config.trap_mode
+    }
+
+    fn generation(&self) -> Generation {
+        self.config.generation
+    }
+
+    fn hilo_ready(&self) -> bool {
+        self.hilo_busy == 0
     }
 
     fn link_register(&mut self, reg: usize) {
@@ -197,35 +921,105 @@ impl<
 
     fn branch(&mut self, offset: u32) {
         self.pc_next = self.pc.wrapping_add(offset);
+        self.mark_pc_dirty();
     }
 
     fn jump(&mut self, segment_addr: u32) {
-        let hi = self.pc_next & 0xF000_0000;
+        // The segment comes from the delay slot, which is at the pc while executing.
+        let hi = self.pc & 0xF000_0000;
         self.pc_next = hi | segment_addr;
+        self.mark_pc_dirty();
+    }
+
+    fn jump_register(&mut self, addr: u32) {
+        self.pc_next = addr;
+        self.mark_pc_dirty();
     }
 
     fn trigger_exception(&mut self, exception: ExceptionCode) {
+        self.take_exception(exception, None, None);
+    }
 
+    fn trigger_memory_exception(&mut self, exception: ExceptionCode, vaddr: u32) {
+        self.take_exception(exception, Some(vaddr), None);
     }
 
-    fn mem<'a>(&'a mut self) -> &'a mut Self::Mem {
+    fn host_syscall(&mut self) -> bool {
+        let before = self.gp_reg;
+        let outcome = if let Some(handler) = self.syscall_handler.as_mut() {
+            handler.syscall(&mut self.gp_reg, &mut self.mem)
+        } else {
+            return false;
+        };
+        self.gp_reg[0] = 0;
+        if self.dirty_tracking {
+            for (reg, (old, new)) in before.iter().zip(self.gp_reg.iter()).enumerate() {
+                if old != new {
+                    self.dirty |= 1 << reg;
+                }
+            }
+        }
+        match outcome {
+            SyscallOutcome::Handled => true,
+            SyscallOutcome::Unhandled => false,
+            SyscallOutcome::Halt => {
+                self.halted = true;
+                true
+            },
+        }
+    }
+
+    fn wait_for_interrupt(&mut self) {
+        self.waiting = true;
+    }
+
+    fn observe_coproc_operation(&mut self, coproc: Coproc, cofun: u32) {
+        if let Some(observer) = self.coproc_observer.as_mut() {
+            observer(coproc as usize, cofun);
+        }
+    }
+
+    fn add_cycles(&mut self, cycles: u32) {
+        self.cycles += u64::from(cycles);
+    }
+
+    fn mem(&mut self) -> &mut Self::Mem {
         &mut self.mem
     }
 
-    fn coproc_0<'a>(&'a mut self) -> &'a mut Self::Coproc0 {
+    fn translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        match self.config.translation {
+            Translation::Flat => Ok(vaddr),
+            Translation::Kseg => match vaddr {
+                KSEG0..=0xBFFF_FFFF => Ok(vaddr & 0x1FFF_FFFF),
+                _ => Ok(vaddr),
+            },
+            Translation::Tlb => self.coproc0.translate(vaddr, is_write),
+        }
+    }
+
+    fn probe_translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        match self.config.translation {
+            Translation::Tlb => self.coproc0.probe_translate(vaddr, is_write),
+            // Fixed translations have no state to change.
+            _ => self.translate(vaddr, is_write),
+        }
+    }
+
+    fn coproc_0(&mut self) -> &mut Self::Coproc0 {
         &mut self.coproc0
     }
 
-    fn coproc_1<'a>(&'a mut self) -> Option<&'a mut Self::Coproc1> {
-        (&mut self.coproc1).as_mut()
+    fn coproc_1(&mut self) -> Option<&mut Self::Coproc1> {
+        self.coproc1.as_mut()
     }
 
-    fn coproc_2<'a>(&'a mut self) -> Option<&'a mut Self::Coproc2> {
-        (&mut self.coproc2).as_mut()
+    fn coproc_2(&mut self) -> Option<&mut Self::Coproc2> {
+        self.coproc2.as_mut()
     }
 
-    fn coproc_3<'a>(&'a mut self) -> Option<&'a mut Self::Coproc3> {
-        (&mut self.coproc3).as_mut()
+    fn coproc_3(&mut self) -> Option<&mut Self::Coproc3> {
+        self.coproc3.as_mut()
     }
 }
 
@@ -236,3 +1030,47 @@ impl<
     C2: Coprocessor,
     C3: Coprocessor
 > MIPSIInstructions<Mem> for MIPSI<Mem, C0, C1, C2, C3> {}
+
+impl<
+    Mem: Mem32,
+    C0: Coprocessor0,
+    C1: Coprocessor,
+    C2: Coprocessor,
+    C3: Coprocessor
+> fmt::Debug for MIPSI<Mem, C0, C1, C2, C3> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MIPSI")
+            .field("gp_reg", &self.gp_reg)
+            .field("hi", &self.hi)
+            .field("lo", &self.lo)
+            .field("link", &self.link)
+            .field("pc", &self.pc)
+            .field("pc_next", &self.pc_next)
+            .field("delay_slot", &self.delay_slot)
+            .field("last_exception", &self.last_exception)
+            .finish()
+    }
+}
+
+impl<
+    Mem: Mem32,
+    C0: Coprocessor0,
+    C1: Coprocessor,
+    C2: Coprocessor,
+    C3: Coprocessor
+> fmt::Display for MIPSI<Mem, C0, C1, C2, C3> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pc=0x{:08X} next=0x{:08X}{}", self.pc, self.pc_next, if self.delay_slot { " (delay slot)" } else { "" })?;
+        for (names, regs) in REG_NAMES.chunks(4).zip(self.gp_reg.chunks(4)) {
+            let row = names.iter().zip(regs.iter())
+                .map(|(name, reg)| format!("{:>4}=0x{:08X}", name, reg))
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        writeln!(f, "  hi=0x{:08X}   lo=0x{:08X}", self.hi, self.lo)?;
+        if let Some(exception) = self.last_exception {
+            writeln!(f, "exception={:?} at 0x{:08X}", exception.code, exception.pc)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+// Instruction encoding values, shared by the decoder and disassembler.
+
+// Primary opcodes, in bits 26-31.
+pub const SPECIAL: u32  = 0x00;
+pub const REGIMM: u32   = 0x01;
+pub const J: u32        = 0x02;
+pub const JAL: u32      = 0x03;
+pub const BEQ: u32      = 0x04;
+pub const BNE: u32      = 0x05;
+pub const BLEZ: u32     = 0x06;
+pub const BGTZ: u32     = 0x07;
+
+pub const ADDI: u32     = 0x08;
+pub const ADDIU: u32    = 0x09;
+pub const SLTI: u32     = 0x0A;
+pub const SLTIU: u32    = 0x0B;
+pub const ANDI: u32     = 0x0C;
+pub const ORI: u32      = 0x0D;
+pub const XORI: u32     = 0x0E;
+pub const LUI: u32      = 0x0F;
+
+pub const COP0: u32     = 0x10;
+pub const COP1: u32     = 0x11;
+pub const COP2: u32     = 0x12;
+pub const COP3: u32     = 0x13;
+
+pub const SPECIAL2: u32 = 0x1C;
+pub const SPECIAL3: u32 = 0x1F;
+
+pub const LB: u32       = 0x20;
+pub const LH: u32       = 0x21;
+pub const LWL: u32      = 0x22;
+pub const LW: u32       = 0x23;
+pub const LBU: u32      = 0x24;
+pub const LHU: u32      = 0x25;
+pub const LWR: u32      = 0x26;
+
+pub const SB: u32       = 0x28;
+pub const SH: u32       = 0x29;
+pub const SWL: u32      = 0x2A;
+pub const SW: u32       = 0x2B;
+pub const SWR: u32      = 0x2E;
+/// MIPS32 only.
+pub const CACHE: u32    = 0x2F;
+
+pub const LL: u32       = 0x30;
+pub const LWC1: u32     = 0x31;
+pub const LWC2: u32     = 0x32;
+/// `LWC3` in MIPS I, `PREF` in MIPS32.
+pub const LWC3: u32     = 0x33;
+pub const PREF: u32     = 0x33;
+
+pub const SC: u32       = 0x38;
+pub const SWC1: u32     = 0x39;
+pub const SWC2: u32     = 0x3A;
+pub const SWC3: u32     = 0x3B;
+
+/// Function codes for `SPECIAL` instructions, in bits 0-5.
+pub mod funct {
+    pub const SLL: u32      = 0x00;
+    pub const SRL: u32      = 0x02;
+    pub const SRA: u32      = 0x03;
+    pub const SLLV: u32     = 0x04;
+    pub const SRLV: u32     = 0x06;
+    pub const SRAV: u32     = 0x07;
+
+    pub const JR: u32       = 0x08;
+    pub const JALR: u32     = 0x09;
+    pub const MOVZ: u32     = 0x0A;
+    pub const MOVN: u32     = 0x0B;
+    pub const SYSCALL: u32  = 0x0C;
+    pub const BREAK: u32    = 0x0D;
+    pub const SYNC: u32     = 0x0F;
+
+    pub const MFHI: u32     = 0x10;
+    pub const MTHI: u32     = 0x11;
+    pub const MFLO: u32     = 0x12;
+    pub const MTLO: u32     = 0x13;
+
+    pub const MULT: u32     = 0x18;
+    pub const MULTU: u32    = 0x19;
+    pub const DIV: u32      = 0x1A;
+    pub const DIVU: u32     = 0x1B;
+
+    pub const ADD: u32      = 0x20;
+    pub const ADDU: u32     = 0x21;
+    pub const SUB: u32      = 0x22;
+    pub const SUBU: u32     = 0x23;
+    pub const AND: u32      = 0x24;
+    pub const OR: u32       = 0x25;
+    pub const XOR: u32      = 0x26;
+    pub const NOR: u32      = 0x27;
+    pub const SLT: u32      = 0x2A;
+    pub const SLTU: u32     = 0x2B;
+
+    pub const TGE: u32      = 0x30;
+    pub const TGEU: u32     = 0x31;
+    pub const TLT: u32      = 0x32;
+    pub const TLTU: u32     = 0x33;
+    pub const TEQ: u32      = 0x34;
+    pub const TNE: u32      = 0x36;
+}
+
+/// Function codes for `SPECIAL2` instructions, in bits 0-5.
+pub mod special2 {
+    pub const MADD: u32     = 0x00;
+    pub const MADDU: u32    = 0x01;
+    pub const MUL: u32      = 0x02;
+    pub const MSUB: u32     = 0x04;
+    pub const MSUBU: u32    = 0x05;
+    pub const CLZ: u32      = 0x20;
+    pub const CLO: u32      = 0x21;
+    pub const SDBBP: u32    = 0x3F;
+}
+
+/// Function codes for `SPECIAL3` instructions, in bits 0-5.
+pub mod special3 {
+    pub const EXT: u32      = 0x00;
+    pub const INS: u32      = 0x04;
+    /// Byte shuffles. The sa field selects the operation.
+    pub const BSHFL: u32    = 0x20;
+
+    /// `BSHFL` sa field: swap bytes within halfwords.
+    pub const WSBH: u32     = 0x02;
+    /// `BSHFL` sa field: sign-extend byte.
+    pub const SEB: u32      = 0x10;
+    /// `BSHFL` sa field: sign-extend halfword.
+    pub const SEH: u32      = 0x18;
+}
+
+/// Codes for `REGIMM` instructions, in the rt field (bits 16-20).
+pub mod regimm {
+    pub const BLTZ: u32     = 0x00;
+    pub const BGEZ: u32     = 0x01;
+    pub const TGEI: u32     = 0x08;
+    pub const TGEIU: u32    = 0x09;
+    pub const TLTI: u32     = 0x0A;
+    pub const TLTIU: u32    = 0x0B;
+    pub const TEQI: u32     = 0x0C;
+    pub const TNEI: u32     = 0x0E;
+    pub const BLTZAL: u32   = 0x10;
+    pub const BGEZAL: u32   = 0x11;
+}
+
+/// Codes for coprocessor instructions, in the rs field (bits 21-25).
+pub mod cop {
+    pub const MF: u32       = 0x00;
+    pub const CF: u32       = 0x02;
+    pub const MT: u32       = 0x04;
+    pub const CT: u32       = 0x06;
+    /// Branch on condition. The rt field selects the condition.
+    pub const BC: u32       = 0x08;
+    /// Set for coprocessor operations. The remaining bits are passed to the coprocessor.
+    pub const CO: u32       = 0x10;
+
+    /// `BC` rt field: branch on false.
+    pub const BCF: u32      = 0x00;
+    /// `BC` rt field: branch on true.
+    pub const BCT: u32      = 0x01;
+}
+
+/// Function codes for coprocessor 0 operations, in bits 0-5.
+pub mod cop0 {
+    pub const TLBR: u32     = 0x01;
+    pub const TLBWI: u32    = 0x02;
+    pub const TLBWR: u32    = 0x06;
+    pub const TLBP: u32     = 0x08;
+    pub const RFE: u32      = 0x10;
+    /// MIPS32 only. In MIPS I this is passed to the coprocessor.
+    pub const WAIT: u32     = 0x20;
+}
@@ -0,0 +1,130 @@
+; Test program for the end-to-end test in mips1_test.rs.
+;
+; This is the LLVM IR for the C below, with a small startup routine.
+; It computes fib(15), the CRC-32 of 64 generated bytes, and gcd(1071, 462),
+; leaving the results in $s0, $s1 and $s2, then executes `syscall`.
+;
+; Rebuild program.bin with:
+;   llc -march=mipsel -mcpu=mips32 -relocation-model=static -mattr=+noabicalls -O2 -filetype=obj program.ll -o program.o
+;   llvm-objcopy -O binary -j .text program.o program.bin
+;
+; The text is linked at address 0, so it must stay in a single section with no data.
+;
+;   unsigned fib(unsigned n) {
+;       return n < 2 ? n : fib(n - 1) + fib(n - 2);
+;   }
+;
+;   unsigned crc32(unsigned len) {
+;       volatile unsigned char buf[256];
+;       for (unsigned i = 0; i < len; i++)
+;           buf[i] = i * 7 + 1;
+;       unsigned crc = 0xFFFFFFFF;
+;       for (unsigned j = 0; j < len; j++) {
+;           crc ^= buf[j];
+;           for (unsigned k = 0; k < 8; k++)
+;               crc = (crc >> 1) ^ (-(crc & 1) & 0xEDB88320);
+;       }
+;       return ~crc;
+;   }
+;
+;   unsigned gcd(unsigned a, unsigned b) {
+;       while (b != 0) {
+;           unsigned r = a % b;
+;           a = b;
+;           b = r;
+;       }
+;       return a;
+;   }
+
+target triple = "mipsel-unknown-none-elf"
+
+module asm ".set noreorder"
+module asm ".globl _start"
+module asm "_start:"
+module asm "  lui $sp, 0"
+module asm "  ori $sp, $sp, 0x1000"
+module asm "  jal fib"
+module asm "  addiu $a0, $zero, 15"
+module asm "  move $s0, $v0"
+module asm "  jal crc32"
+module asm "  addiu $a0, $zero, 64"
+module asm "  move $s1, $v0"
+module asm "  addiu $a0, $zero, 1071"
+module asm "  jal gcd"
+module asm "  addiu $a1, $zero, 462"
+module asm "  move $s2, $v0"
+module asm "  syscall"
+module asm ".set reorder"
+
+define internal i32 @fib(i32 %n) noinline {
+entry:
+  %small = icmp ult i32 %n, 2
+  br i1 %small, label %base, label %rec
+base:
+  ret i32 %n
+rec:
+  %n1 = sub i32 %n, 1
+  %f1 = call i32 @fib(i32 %n1)
+  %n2 = sub i32 %n, 2
+  %f2 = call i32 @fib(i32 %n2)
+  %r = add i32 %f1, %f2
+  ret i32 %r
+}
+
+define internal i32 @crc32(i32 %len) noinline {
+entry:
+  %buf = alloca [256 x i8], align 4
+  br label %fill
+fill:
+  %i = phi i32 [0, %entry], [%i.next, %fill]
+  %v = mul i32 %i, 7
+  %v1 = add i32 %v, 1
+  %b = trunc i32 %v1 to i8
+  %p = getelementptr [256 x i8], [256 x i8]* %buf, i32 0, i32 %i
+  store volatile i8 %b, i8* %p
+  %i.next = add i32 %i, 1
+  %fill.done = icmp eq i32 %i.next, %len
+  br i1 %fill.done, label %bytes, label %fill
+bytes:
+  %j = phi i32 [0, %fill], [%j.next, %bytes.next]
+  %crc = phi i32 [-1, %fill], [%crc.bits, %bytes.next]
+  %q = getelementptr [256 x i8], [256 x i8]* %buf, i32 0, i32 %j
+  %byte = load volatile i8, i8* %q
+  %byte32 = zext i8 %byte to i32
+  %crc.in = xor i32 %crc, %byte32
+  br label %bits
+bits:
+  %k = phi i32 [0, %bytes], [%k.next, %bits]
+  %c = phi i32 [%crc.in, %bytes], [%c.next, %bits]
+  %lsb = and i32 %c, 1
+  %shifted = lshr i32 %c, 1
+  %mask = sub i32 0, %lsb
+  %poly = and i32 %mask, 3988292384
+  %c.next = xor i32 %shifted, %poly
+  %k.next = add i32 %k, 1
+  %bits.done = icmp eq i32 %k.next, 8
+  br i1 %bits.done, label %bytes.next, label %bits
+bytes.next:
+  %crc.bits = phi i32 [%c.next, %bits]
+  %j.next = add i32 %j, 1
+  %bytes.done = icmp eq i32 %j.next, %len
+  br i1 %bytes.done, label %done, label %bytes
+done:
+  %res = xor i32 %crc.bits, -1
+  ret i32 %res
+}
+
+define internal i32 @gcd(i32 %a, i32 %b) noinline {
+entry:
+  %zero = icmp eq i32 %b, 0
+  br i1 %zero, label %done, label %loop
+loop:
+  %x = phi i32 [%a, %entry], [%y, %loop]
+  %y = phi i32 [%b, %entry], [%r, %loop]
+  %r = urem i32 %x, %y
+  %rz = icmp eq i32 %r, 0
+  br i1 %rz, label %done, label %loop
+done:
+  %res = phi i32 [%a, %entry], [%y, %loop]
+  ret i32 %res
+}
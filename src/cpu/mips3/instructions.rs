@@ -0,0 +1,129 @@
+use super::*;
+
+use crate::common::sign_extend_16;
+
+/// The loads and stores added in MIPS III.
+/// 
+/// The arguments must have been decoded prior to calling these.
+/// If a register number argument has a value greater than 31, the result is undefined.
+pub trait MIPSIIIInstructions<Mem>: MIPSIIICore + MIPSICore<Mem = Mem>
+    where Mem: Mem64, <Mem as Memory>::Addr: From<u32> {
+    /// Find the virtual address of a load or store.
+    fn effective_addr(&self, base_reg: usize, offset: u16) -> u32 {
+        let base = self.read_gp64(base_reg) as u32;
+        base.wrapping_add(sign_extend_16(offset))
+    }
+
+    /// Load word, sign-extended to 64 bits
+    fn lw(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+        if let Some(word) = self.load_word(addr) {
+            self.write_gp64(tgt_reg, (word as i32) as u64);
+        }
+    }
+
+    /// Load word unsigned
+    fn lwu(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+        if let Some(word) = self.load_word(addr) {
+            self.write_gp64(tgt_reg, word as u64);
+        }
+    }
+
+    /// Load doubleword
+    fn ld(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+        if let Some(doubleword) = self.load_doubleword(addr) {
+            self.write_gp64(tgt_reg, doubleword);
+        }
+    }
+
+    /// Load doubleword left
+    fn ldl(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+
+        let doubleword_addr = addr & 0xFFFF_FFF8;
+        let byte_addr = addr & 7;
+        let byte_offset = if self.mem().little_endian() { 7 - byte_addr } else { byte_addr };
+
+        let doubleword = if let Some(doubleword) = self.load_doubleword(doubleword_addr) { doubleword } else { return };
+        let old_doubleword = if byte_offset == 0 {
+            0
+        } else {
+            0xFFFF_FFFF_FFFF_FFFF >> (64 - byte_offset * 8)
+        } & self.read_gp64(tgt_reg);
+
+        let shift = byte_offset * 8;
+
+        self.write_gp64(tgt_reg, old_doubleword | (doubleword << shift));
+    }
+
+    /// Load doubleword right
+    fn ldr(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+
+        let doubleword_addr = addr & 0xFFFF_FFF8;
+        let byte_addr = addr & 7;
+        let byte_offset = if self.mem().little_endian() { byte_addr } else { 7 - byte_addr };
+
+        let doubleword = if let Some(doubleword) = self.load_doubleword(doubleword_addr) { doubleword } else { return };
+        let old_doubleword = if byte_offset == 0 {
+            0
+        } else {
+            0xFFFF_FFFF_FFFF_FFFF << (64 - byte_offset * 8)
+        } & self.read_gp64(tgt_reg);
+
+        let shift = byte_offset * 8;
+
+        self.write_gp64(tgt_reg, old_doubleword | (doubleword >> shift));
+    }
+
+    /// Store doubleword
+    fn sd(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+        let data = self.read_gp64(tgt_reg);
+        self.store_doubleword(addr, data);
+    }
+
+    /// Store doubleword left
+    fn sdl(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+
+        let doubleword_addr = addr & 0xFFFF_FFF8;
+        let byte_addr = addr & 7;
+        let byte_offset = if self.mem().little_endian() { 7 - byte_addr } else { byte_addr };
+
+        let doubleword = self.read_gp64(tgt_reg);
+        let mem_doubleword = if let Some(doubleword) = self.load_doubleword(doubleword_addr) { doubleword } else { return };
+        let old_doubleword = if byte_offset == 0 {
+            0
+        } else {
+            0xFFFF_FFFF_FFFF_FFFF << (64 - byte_offset * 8)
+        } & mem_doubleword;
+
+        let shift = byte_offset * 8;
+
+        self.store_doubleword(doubleword_addr, old_doubleword | (doubleword >> shift));
+    }
+
+    /// Store doubleword right
+    fn sdr(&mut self, base_reg: usize, tgt_reg: usize, offset: u16) {
+        let addr = self.effective_addr(base_reg, offset);
+
+        let doubleword_addr = addr & 0xFFFF_FFF8;
+        let byte_addr = addr & 7;
+        let byte_offset = if self.mem().little_endian() { byte_addr } else { 7 - byte_addr };
+
+        let doubleword = self.read_gp64(tgt_reg);
+        let mem_doubleword = if let Some(doubleword) = self.load_doubleword(doubleword_addr) { doubleword } else { return };
+        let old_doubleword = if byte_offset == 0 {
+            0
+        } else {
+            0xFFFF_FFFF_FFFF_FFFF >> (64 - byte_offset * 8)
+        } & mem_doubleword;
+
+        let shift = byte_offset * 8;
+
+        self.store_doubleword(doubleword_addr, old_doubleword | (doubleword << shift));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    coproc::*,
+    mem::*,
+    cpu::ExceptionCode,
+    cpu::Generation,
+    cpu::MIPSICore,
+    cpu::TrapMode,
+    cpu::mips3::*
+};
+
+struct SwitchableMemTest {
+    bytes: [u8; 0x100],
+    little_endian: bool
+}
+
+impl Memory for SwitchableMemTest {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.bytes[addr as usize]
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.bytes[addr as usize] = data;
+    }
+}
+
+impl_mem_64_little!{ SwitchableMemTest, little_endian }
+
+struct MIPSIIITest {
+    gp_reg: [u64; 32],
+    mem: SwitchableMemTest,
+    coproc0: EmptyCoproc0,
+    exception: Option<(ExceptionCode, u32)>
+}
+
+impl MIPSIIITest {
+    fn new(little_endian: bool) -> Self {
+        Self {
+            gp_reg: [0; 32],
+            mem: SwitchableMemTest {
+                bytes: [0; 0x100],
+                little_endian
+            },
+            coproc0: EmptyCoproc0 {},
+            exception: None
+        }
+    }
+}
+
+// Only what the loads and stores use is implemented.
+impl MIPSICore for MIPSIIITest {
+    type Mem = SwitchableMemTest;
+    type Coproc0 = EmptyCoproc0;
+    type Coproc1 = EmptyCoproc;
+    type Coproc2 = EmptyCoproc;
+    type Coproc3 = EmptyCoproc;
+
+    fn read_gp(&self, reg: usize) -> u32 {
+        self.gp_reg[reg] as u32
+    }
+
+    fn write_gp(&mut self, reg: usize, val: u32) {
+        self.write_gp64(reg, (val as i32) as u64);
+    }
+
+    fn read_hi(&self) -> u32 {
+        0
+    }
+    fn write_hi(&mut self, _: u32) {}
+
+    fn read_lo(&self) -> u32 {
+        0
+    }
+    fn write_lo(&mut self, _: u32) {}
+
+    fn read_link(&self) -> Option<u32> {
+        None
+    }
+    fn write_link(&mut self, _: Option<u32>) {}
+
+    fn trap_mode(&self) -> TrapMode {
+        TrapMode::Strict
+    }
+
+    fn generation(&self) -> Generation {
+        Generation::MIPSI
+    }
+
+    fn link_register(&mut self, _: usize) {}
+    fn branch(&mut self, _: u32) {}
+    fn jump(&mut self, _: u32) {}
+
+    fn trigger_exception(&mut self, exception: ExceptionCode) {
+        self.exception = Some((exception, 0));
+    }
+
+    fn trigger_memory_exception(&mut self, exception: ExceptionCode, vaddr: u32) {
+        self.exception = Some((exception, vaddr));
+    }
+
+    fn wait_for_interrupt(&mut self) {}
+
+    fn mem(&mut self) -> &mut Self::Mem {
+        &mut self.mem
+    }
+
+    fn coproc_0(&mut self) -> &mut Self::Coproc0 {
+        &mut self.coproc0
+    }
+    fn coproc_1(&mut self) -> Option<&mut Self::Coproc1> {
+        None
+    }
+    fn coproc_2(&mut self) -> Option<&mut Self::Coproc2> {
+        None
+    }
+    fn coproc_3(&mut self) -> Option<&mut Self::Coproc3> {
+        None
+    }
+}
+
+impl MIPSIIICore for MIPSIIITest {
+    fn read_gp64(&self, reg: usize) -> u64 {
+        self.gp_reg[reg]
+    }
+
+    fn write_gp64(&mut self, reg: usize, val: u64) {
+        if reg != 0 {
+            self.gp_reg[reg] = val;
+        }
+    }
+}
+
+impl MIPSIIIInstructions<SwitchableMemTest> for MIPSIIITest {}
+
+#[test]
+fn lwu() {
+    let mut cpu = MIPSIIITest::new(true);
+    cpu.mem().write_word(0x10, 0x8000_1234);
+    cpu.write_gp64(1, 0x10);
+
+    cpu.lw(1, 2, 0);
+    assert_eq!(cpu.read_gp64(2), 0xFFFF_FFFF_8000_1234);
+
+    cpu.lwu(1, 3, 0);
+    assert_eq!(cpu.read_gp64(3), 0x0000_0000_8000_1234);
+
+    // The upper half of the base is ignored.
+    cpu.write_gp64(1, 0xFFFF_FFFF_0000_0010);
+    cpu.lwu(1, 4, 0);
+    assert_eq!(cpu.read_gp64(4), 0x8000_1234);
+}
+
+#[test]
+fn ld_sd() {
+    for little_endian in [true, false] {
+        let mut cpu = MIPSIIITest::new(little_endian);
+        cpu.write_gp64(1, 0x20);
+        cpu.write_gp64(2, 0x0123_4567_89AB_CDEF);
+
+        cpu.sd(1, 2, 8);
+        assert_eq!(cpu.mem().read_doubleword(0x28), 0x0123_4567_89AB_CDEF);
+        cpu.ld(1, 3, 8);
+        assert_eq!(cpu.read_gp64(3), 0x0123_4567_89AB_CDEF);
+        assert_eq!(cpu.exception, None);
+    }
+}
+
+#[test]
+fn doubleword_alignment() {
+    let mut cpu = MIPSIIITest::new(true);
+    cpu.write_gp64(1, 0x24);
+    cpu.write_gp64(2, 0x1234);
+
+    cpu.ld(1, 2, 0);
+    assert_eq!(cpu.exception, Some((ExceptionCode::AddrErrorLoad, 0x24)));
+    assert_eq!(cpu.read_gp64(2), 0x1234);
+
+    cpu.sd(1, 2, 0);
+    assert_eq!(cpu.exception, Some((ExceptionCode::AddrErrorStore, 0x24)));
+    assert_eq!(cpu.mem().read_doubleword(0x20), 0);
+}
+
+#[test]
+fn ldl_ldr() {
+    for little_endian in [true, false] {
+        let mut cpu = MIPSIIITest::new(little_endian);
+        for i in 0..16 {
+            cpu.mem().write_byte(0x10 + i, i as u8);
+        }
+        let expected = cpu.mem().read_doubleword(0x13);
+
+        // An unaligned load of 8 bytes from 0x13.
+        cpu.write_gp64(1, 0x13);
+        if little_endian {
+            cpu.ldr(1, 2, 0);
+            cpu.ldl(1, 2, 7);
+        } else {
+            cpu.ldl(1, 2, 0);
+            cpu.ldr(1, 2, 7);
+        }
+        assert_eq!(cpu.read_gp64(2), expected);
+
+        // An aligned pair loads the whole doubleword once.
+        cpu.write_gp64(1, 0x10);
+        cpu.write_gp64(3, 0);
+        if little_endian {
+            cpu.ldr(1, 3, 0);
+        } else {
+            cpu.ldl(1, 3, 0);
+        }
+        assert_eq!(cpu.read_gp64(3), cpu.mem().read_doubleword(0x10));
+    }
+}
+
+#[test]
+fn ldl_merge() {
+    let mut cpu = MIPSIIITest::new(true);
+    cpu.mem().write_doubleword(0x10, 0x0807_0605_0403_0201);
+    cpu.write_gp64(1, 0x11);
+    cpu.write_gp64(2, 0xFFFF_FFFF_FFFF_FFFF);
+
+    // Little endian, byte 1: the low two bytes of memory go to the top of the register.
+    cpu.ldl(1, 2, 0);
+    assert_eq!(cpu.read_gp64(2), 0x0201_FFFF_FFFF_FFFF);
+
+    cpu.write_gp64(3, 0xFFFF_FFFF_FFFF_FFFF);
+    cpu.ldr(1, 3, 0);
+    assert_eq!(cpu.read_gp64(3), 0xFF08_0706_0504_0302);
+}
+
+#[test]
+fn sdl_sdr() {
+    for little_endian in [true, false] {
+        let mut cpu = MIPSIIITest::new(little_endian);
+        cpu.write_gp64(1, 0x13);
+        cpu.write_gp64(2, 0x0123_4567_89AB_CDEF);
+
+        // An unaligned store of 8 bytes to 0x13.
+        if little_endian {
+            cpu.sdr(1, 2, 0);
+            cpu.sdl(1, 2, 7);
+        } else {
+            cpu.sdl(1, 2, 0);
+            cpu.sdr(1, 2, 7);
+        }
+        assert_eq!(cpu.mem().read_doubleword(0x13), 0x0123_4567_89AB_CDEF);
+        // Bytes either side are untouched.
+        assert_eq!(cpu.mem().read_byte(0x12), 0);
+        assert_eq!(cpu.mem().read_byte(0x1B), 0);
+        assert_eq!(cpu.exception, None);
+    }
+}
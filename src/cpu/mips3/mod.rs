@@ -0,0 +1,64 @@
+mod instructions;
+#[cfg(test)]
+mod mips3_test;
+
+use super::{
+    ExceptionCode,
+    MIPSICore
+};
+
+use crate::mem::{
+    Memory,
+    Mem64
+};
+
+pub use instructions::*;
+
+/// The core set of traits for the MIPS III instruction set.
+/// 
+/// General-purpose registers are 64 bits wide.
+/// Addresses are 32 bits: only the lower half of the base register is used,
+/// as in 32-bit addressing mode.
+/// 
+/// A MIPS III core is also a MIPS I core, so translation, exceptions and the 32-bit accesses come from `MIPSICore`.
+/// Its `read_gp` and `write_gp` should access the lower half of each register,
+/// sign-extending writes to 64 bits.
+/// 
+/// There is no MIPS III CPU yet: this covers what the 64-bit loads and stores need.
+pub trait MIPSIIICore: MIPSICore {
+    /// Read all 64 bits of a general-purpose register.
+    /// 
+    /// If a read is attempted on a register that is not value 0-31,
+    /// the results are undefined.
+    fn read_gp64(&self, reg: usize) -> u64;
+
+    /// Write all 64 bits of a general-purpose register.
+    /// 
+    /// If a write is attempted on a register that is not value 0-31,
+    /// the results are undefined.
+    fn write_gp64(&mut self, reg: usize, val: u64);
+
+    /// Load a doubleword from a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn load_doubleword(&mut self, vaddr: u32) -> Option<u64>
+        where Self::Mem: Mem64, <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 8, ExceptionCode::AddrErrorLoad)?;
+        let addr = self.translate_or_trigger(vaddr, false)?;
+        self.charge_access(addr, false);
+        let result = self.mem().try_read_doubleword(addr.into());
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Store a doubleword to a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn store_doubleword(&mut self, vaddr: u32, data: u64) -> Option<()>
+        where Self::Mem: Mem64, <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 8, ExceptionCode::AddrErrorStore)?;
+        let addr = self.translate_or_trigger(vaddr, true)?;
+        self.charge_access(addr, true);
+        let result = self.mem().try_write_doubleword(addr.into(), data);
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+}
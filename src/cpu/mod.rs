@@ -1,13 +1,33 @@
 /// The MIPS I instruction set.
 pub mod mips1;
+/// The MIPS III instruction set.
+pub mod mips3;
+/// Interrupt line priority.
+mod interrupt;
 
-use crate::mem::Mem32;
+use alloc::vec::Vec;
+
+use crate::common::{
+    hi64,
+    lo64
+};
+use crate::mem::{
+    Memory,
+    MemError,
+    Mem16,
+    Mem32,
+    read_word_fast
+};
 use crate::coproc::{
     Coprocessor0,
-    Coprocessor
+    Coprocessor,
+    cp0_reg
 };
 
+pub use interrupt::*;
+
 /// Exception codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ExceptionCode {
     Interrupt           = 0,
     TLBMod              = 1,
@@ -21,12 +41,101 @@ pub enum ExceptionCode {
     Breakpoint          = 9,
     ReservedInstruction = 10,
     CoProcUnusable      = 11,
-    ArithmeticOverflow  = 12
+    ArithmeticOverflow  = 12,
+    Trap                = 13,
+    /// EJTAG debug breakpoint, from `SDBBP`.
+    /// 
+    /// Debug exceptions have no ExcCode and don't go through coprocessor 0:
+    /// they are left to the host to handle.
+    DebugBreak          = 14
+}
+
+/// Details of the last exception triggered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExceptionInfo {
+    /// The exception triggered.
+    pub code:       ExceptionCode,
+    /// The address of the instruction that caused it.
+    pub pc:         u32,
+    /// The virtual address accessed, if it was caused by a memory access.
+    pub bad_addr:   Option<u32>,
+    /// The undecodable instruction, if it was a `ReservedInstruction` exception raised by the CPU.
+    pub decode:     Option<DecodeError>,
+}
+
+/// Details of an instruction that couldn't be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The instruction word.
+    pub instr:      u32,
+    /// The primary opcode, in bits 26-31.
+    pub opcode:     u32,
+    /// The field that selects the instruction within the opcode, if the primary opcode was valid.
+    /// 
+    /// This is funct for `SPECIAL` and `SPECIAL2`, rt for `REGIMM`,
+    /// and rs for coprocessor instructions (or rt for coprocessor branches).
+    pub subop:      Option<u32>,
+}
+
+/// How the trapping arithmetic instructions (`add`, `addi`, `sub`) treat overflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TrapMode {
+    /// Trigger an `ArithmeticOverflow` exception, as per the spec.
+    #[default]
+    Strict,
+    /// Silently wrap the result, as the unsigned variants do.
+    Lenient
+}
+
+/// How virtual addresses are translated into physical addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Translation {
+    /// Virtual addresses are used as physical addresses.
+    Flat,
+    /// kseg0 and kseg1 are mapped down to physical memory, other addresses are used as-is.
+    Kseg,
+    /// Coprocessor 0 translates addresses.
+    #[default]
+    Tlb
+}
+
+/// The generation of the MIPS architecture to decode instructions for.
+/// 
+/// Later generations reuse some opcodes, so this decides how they are decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Generation {
+    /// MIPS I: opcode 0x33 is `LWC3`.
+    #[default]
+    MIPSI,
+    /// MIPS32: opcode 0x33 is `PREF`.
+    MIPS32
+}
+
+/// The revision of the instruction set to allow.
+/// 
+/// Levels are ordered, so each one includes the instructions of the levels before it.
+/// `Generation` decides how opcodes that were reused are decoded; this decides which instructions are allowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum IsaLevel {
+    /// MIPS I, as on the R3000.
+    MipsI,
+    /// MIPS II: adds `LL`, `SC`, `SYNC` and the trap instructions.
+    MipsII,
+    /// MIPS III: adds `CACHE`.
+    MipsIII,
+    /// MIPS32: adds `SPECIAL2` instructions, `MOVZ`, `MOVN`, `PREF` and `WAIT`.
+    Mips32,
+    /// MIPS32 release 2: adds `ROTR`, `ROTRV`, `SEB`, `SEH`, `WSBH`, `EXT` and `INS`.
+    /// 
+    /// This allows every instruction that is implemented.
+    #[default]
+    Mips32R2
 }
 
 /// Coprocessor number.
 /// 
 /// Used for internal coprocessor indexing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Coproc {
     _0,
     _1,
@@ -34,12 +143,202 @@ pub enum Coproc {
     _3
 }
 
+/// The outcome of a single step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// An instruction was executed.
+    Executed,
+    /// The PC reached a breakpoint. The instruction was not executed.
+    Breakpoint,
+    /// The CPU is stuck in a branch-to-self loop, and will make no further progress.
+    Stuck,
+    /// A software debug breakpoint (`SDBBP`) was executed.
+    DebugBreak,
+    /// The fetched word was `0xFFFF_FFFF`, as read from erased flash or uninitialised memory.
+    /// The instruction was not executed.
+    /// 
+    /// This is only reported if the fetch guard is enabled.
+    GarbageFetch,
+    /// The CPU is idle after a `WAIT` instruction, and will make no progress until an interrupt is pending.
+    /// Coprocessor 0 is still ticked, so its timer can wake the CPU.
+    Waiting,
+    /// A syscall handler halted the CPU. It will make no further progress until the PC is written.
+    Halted
+}
+
+/// The outcome of running with an instruction budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// A step returned something other than `StepResult::Executed`.
+    Stopped(StepResult),
+    /// The budget ran out.
+    BudgetExhausted
+}
+
+/// What a `SyscallHandler` did with a syscall.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyscallOutcome {
+    /// The syscall was handled. Execution continues after the `SYSCALL` instruction.
+    Handled,
+    /// The syscall wasn't handled, so the `Syscall` exception is triggered as normal.
+    Unhandled,
+    /// The program is finished, and the CPU halts.
+    Halt
+}
+
+/// Handles `SYSCALL` instructions on the host, instead of in the guest.
+/// 
+/// This lets user-mode programs run with a host ABI, without an OS.
+pub trait SyscallHandler<Mem> {
+    /// Handle a syscall, with access to the general-purpose registers and memory.
+    /// 
+    /// Memory is accessed with physical addresses. Writes to register 0 are discarded.
+    fn syscall(&mut self, gp_reg: &mut [u32; 32], mem: &mut Mem) -> SyscallOutcome;
+}
+
+/// The broad category of an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstrClass {
+    /// A conditional branch, including coprocessor branches.
+    Branch,
+    /// An unconditional jump, to an immediate target or a register.
+    Jump,
+    /// A load from memory, including coprocessor loads, prefetches and cache operations.
+    Load,
+    /// A store to memory, including coprocessor stores.
+    Store,
+    /// An integer instruction that doesn't access memory or change control flow.
+    Alu,
+    /// A coprocessor move or operation.
+    Coprocessor,
+    /// A trap, system call, or breakpoint.
+    Trap,
+    /// An encoding that isn't a valid instruction.
+    Reserved
+}
+
+/// The addresses control can go to after an instruction.
+/// 
+/// For branches and jumps, these are the addresses after the delay slot.
+/// Both are `None` if the successors can't be worked out, for example if the instruction can't be fetched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SuccessorSet {
+    /// The next instruction in sequence: after the delay slot for a branch that isn't taken.
+    /// `None` for jumps.
+    pub fall_through:   Option<u32>,
+    /// The destination of a taken branch or jump.
+    pub target:         Option<u32>,
+}
+
+impl SuccessorSet {
+    /// Check if `addr` is one of the successors.
+    pub fn contains(&self, addr: u32) -> bool {
+        self.fall_through == Some(addr) || self.target == Some(addr)
+    }
+
+    /// Iterate over the successors, fall-through first.
+    pub fn iter(&self) -> impl Iterator<Item = u32> {
+        self.fall_through.into_iter().chain(self.target)
+    }
+}
+
 /// The core set of traits for a MIPS processor.
 /// 
 /// This set of traits deals with the public interface.
 pub trait MIPSCore {
     /// Fetch, decode, and execute an instruction.
-    fn step(&mut self);
+    /// 
+    /// If the PC is at a breakpoint, the instruction is not executed
+    /// and `StepResult::Breakpoint` is returned. The next call to `step`
+    /// will then execute the instruction.
+    /// 
+    /// If coprocessor 0 has an interrupt pending, the step takes the interrupt
+    /// and jumps to its handler instead of executing an instruction.
+    /// After a `WAIT` instruction, steps return `StepResult::Waiting` until an interrupt is taken.
+    fn step(&mut self) -> StepResult;
+
+    /// Decode and execute the instruction provided, instead of fetching one from memory.
+    /// 
+    /// The PC advances as though the instruction had been fetched from it,
+    /// and branches and jumps take effect as normal.
+    fn execute(&mut self, instr: u32);
+
+    /// Step over an instruction, including its delay slot if it is a branch or jump.
+    /// 
+    /// After a taken branch this leaves the PC at the branch target.
+    /// If the delay slot is at a breakpoint, it is not executed.
+    fn step_branch_aware(&mut self) -> StepResult {
+        let result = self.step();
+        if result == StepResult::Executed && self.in_delay_slot() {
+            self.step()
+        } else {
+            result
+        }
+    }
+
+    /// Step until something other than a plain instruction execution happens.
+    fn run(&mut self) -> StepResult {
+        loop {
+            match self.step() {
+                StepResult::Executed => {},
+                result => return result,
+            }
+        }
+    }
+
+    /// Like `run`, but stop after at most `max_instructions` steps.
+    /// 
+    /// Use this to bound the time spent running untrusted code.
+    fn run_with_budget(&mut self, max_instructions: usize) -> RunOutcome {
+        for _ in 0..max_instructions {
+            match self.step() {
+                StepResult::Executed => {},
+                result => return RunOutcome::Stopped(result),
+            }
+        }
+        RunOutcome::BudgetExhausted
+    }
+
+    /// Read the address of the next instruction to be executed.
+    fn read_pc(&self) -> u32;
+
+    /// Set the address of the next instruction to be executed.
+    /// 
+    /// This cancels any pending branch.
+    fn write_pc(&mut self, addr: u32);
+
+    /// Get the exception triggered by the last step, if there was one.
+    fn last_exception(&self) -> Option<ExceptionInfo>;
+
+    /// Check if the next instruction to be executed is in the delay slot of a branch or jump.
+    fn in_delay_slot(&self) -> bool;
+
+    /// If the next instruction is in a delay slot, get the address to go to after it.
+    /// 
+    /// For a branch that isn't taken, this is the instruction after the delay slot.
+    fn pending_branch_target(&self) -> Option<u32>;
+
+    /// Install a breakpoint at the address provided.
+    fn add_breakpoint(&mut self, addr: u32);
+
+    /// Remove the breakpoint at the address provided, if one was installed.
+    fn remove_breakpoint(&mut self, addr: u32);
+
+    /// List the breakpoints installed, in address order.
+    fn breakpoints(&self) -> Vec<u32>;
+
+    /// Assert an interrupt line, 0-7.
+    /// 
+    /// The line stays asserted until it is cleared.
+    fn raise_interrupt(&mut self, line: usize);
+
+    /// Deassert an interrupt line, 0-7.
+    fn clear_interrupt(&mut self, line: usize);
+
+    /// Get the interrupt lines that are pending, unmasked and enabled, one bit per line.
+    /// 
+    /// If any are set, the next step takes the one with the highest priority.
+    fn pending_interrupts(&self) -> u8;
 }
 
 /// The core set of traits for the MIPS I instruction set.
@@ -83,27 +382,298 @@ pub trait MIPSICore {
     /// Write the LO register.
     fn write_lo(&mut self, val: u32);
 
+    /// Read HI and LO together, as a 64-bit value with HI in the upper word.
+    fn read_hilo(&self) -> u64 {
+        ((self.read_hi() as u64) << 32) | (self.read_lo() as u64)
+    }
+
+    /// Write HI and LO together, from a 64-bit value with HI in the upper word.
+    fn write_hilo(&mut self, val: u64) {
+        self.write_hi(hi64(val));
+        self.write_lo(lo64(val));
+    }
+
+    /// Read the address linked by the last load linked instruction.
+    /// 
+    /// Returns `None` if there is no valid link.
+    fn read_link(&self) -> Option<u32>;
+
+    /// Set or clear the address linked for a store conditional instruction.
+    fn write_link(&mut self, addr: Option<u32>);
+
+    /// Get the overflow behaviour of the trapping arithmetic instructions.
+    fn trap_mode(&self) -> TrapMode;
+
+    /// Get the architecture generation that instructions are decoded for.
+    fn generation(&self) -> Generation;
+
+    /// Check if the result of the last multiply or divide can be read from HI and LO.
+    /// 
+    /// By default this is always `true`.
+    fn hilo_ready(&self) -> bool {
+        true
+    }
+
+    /// Write the result of a load to a general-purpose register.
+    /// 
+    /// Cores that model the load delay slot defer the write until after the next instruction.
+    /// By default this writes immediately.
+    fn write_gp_loaded(&mut self, reg: usize, val: u32) {
+        self.write_gp(reg, val);
+    }
+
+    /// Read a general-purpose register, including the result of a load still in its delay slot.
+    /// 
+    /// This is used by `LWL` and `LWR`, which merge with a load in progress.
+    /// By default this is the same as `read_gp`.
+    fn read_gp_loading(&self, reg: usize) -> u32 {
+        self.read_gp(reg)
+    }
+
     /// Link the specified register with the return address.
     fn link_register(&mut self, reg: usize);
 
     /// Modify the next PC (in the case of a branch).
+    /// 
+    /// The offset is relative to the address of the delay slot,
+    /// which is the PC while the branch instruction executes.
+    /// So the target is `branch address + 4 + offset`.
     fn branch(&mut self, offset: u32);
 
     /// Modify the next PC (in the case of a jump).
+    /// 
+    /// The top 4 bits of the target come from the address of the delay slot.
     fn jump(&mut self, segment_addr: u32);
 
+    /// Modify the next PC to an address read from a register.
+    /// 
+    /// Unlike `jump`, the whole address is used.
+    /// By default this calls `jump` with the lower 28 bits of the address, which only reaches the segment
+    /// of the delay slot. Cores should override it so register jumps can leave the segment.
+    fn jump_register(&mut self, addr: u32) {
+        self.jump(addr & 0x0FFF_FFFF);
+    }
+
     /// Trigger an exception.
     fn trigger_exception(&mut self, exception: ExceptionCode);
 
+    /// Trigger an exception caused by accessing a virtual address.
+    /// 
+    /// By default this is the same as `trigger_exception`.
+    fn trigger_memory_exception(&mut self, exception: ExceptionCode, _vaddr: u32) {
+        self.trigger_exception(exception);
+    }
+
+    /// Pass a syscall to the host handler, if there is one.
+    /// 
+    /// Returns `true` if the handler dealt with it, so no exception should be triggered.
+    /// By default there is no handler, and this returns `false`.
+    fn host_syscall(&mut self) -> bool {
+        false
+    }
+
+    /// Stop executing instructions until an interrupt is taken.
+    fn wait_for_interrupt(&mut self);
+
+    /// Report a coprocessor operation, just before it is passed to the coprocessor.
+    /// 
+    /// By default this does nothing.
+    fn observe_coproc_operation(&mut self, _coproc: Coproc, _cofun: u32) {}
+
     /// Borrow the memory bus.
-    fn mem<'a>(&'a mut self) -> &'a mut Self::Mem;
+    fn mem(&mut self) -> &mut Self::Mem;
+
+    /// Translate a virtual address into a physical address.
+    /// 
+    /// By default this defers to coprocessor 0.
+    fn translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        self.coproc_0().translate(vaddr, is_write)
+    }
+
+    /// Translate a virtual address in the same way as `translate`, without changing any state.
+    /// 
+    /// By default this defers to coprocessor 0.
+    fn probe_translate(&mut self, vaddr: u32, is_write: bool) -> Result<u32, ExceptionCode> {
+        self.coproc_0().probe_translate(vaddr, is_write)
+    }
+
+    /// Translate a virtual address into a physical address, triggering an exception on failure.
+    fn translate_or_trigger(&mut self, vaddr: u32, is_write: bool) -> Option<u32> {
+        match self.translate(vaddr, is_write) {
+            Ok(addr) => Some(addr),
+            Err(exception) => {
+                self.trigger_memory_exception(exception, vaddr);
+                None
+            }
+        }
+    }
+
+    /// Trigger an exception if a memory access at a virtual address failed.
+    fn check_bus<T>(&mut self, result: Result<T, MemError>, exception: ExceptionCode, vaddr: u32) -> Option<T> {
+        match result {
+            Ok(data) => Some(data),
+            Err(_) => {
+                self.trigger_memory_exception(exception, vaddr);
+                None
+            }
+        }
+    }
+
+    /// Trigger an exception if a virtual address isn't aligned to `size` bytes.
+    fn check_alignment(&mut self, vaddr: u32, size: u32, exception: ExceptionCode) -> Option<()> {
+        if (vaddr & (size - 1)) == 0 {
+            Some(())
+        } else {
+            self.trigger_memory_exception(exception, vaddr);
+            None
+        }
+    }
+
+    /// Add to the count of cycles taken.
+    /// 
+    /// By default cycles are not counted.
+    fn add_cycles(&mut self, _cycles: u32) {}
+
+    /// Count the cycles taken to access a physical address.
+    fn charge_access(&mut self, addr: u32, is_write: bool)
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        let cycles = self.mem().access_cycles(addr.into(), is_write);
+        self.add_cycles(cycles);
+    }
+
+    /// Fetch an instruction from a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn fetch_word(&mut self, vaddr: u32) -> Option<u32>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 4, ExceptionCode::AddrErrorLoad)?;
+        let addr = self.translate_or_trigger(vaddr, false)?;
+        self.charge_access(addr, false);
+        if let Some(word) = read_word_fast(self.mem(), addr) {
+            return Some(word);
+        }
+        let result = self.mem().fetch_word(addr.into());
+        self.check_bus(result, ExceptionCode::InstructionBusError, vaddr)
+    }
+
+    /// Load a byte from a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn load_byte(&mut self, vaddr: u32) -> Option<u8>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        let addr = self.translate_or_trigger(vaddr, false)?;
+        self.charge_access(addr, false);
+        let result = self.mem().try_read_byte(addr.into());
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Load a halfword from a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn load_halfword(&mut self, vaddr: u32) -> Option<u16>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 2, ExceptionCode::AddrErrorLoad)?;
+        let addr = self.translate_or_trigger(vaddr, false)?;
+        self.charge_access(addr, false);
+        let result = self.mem().try_read_halfword(addr.into());
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Load a word from a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn load_word(&mut self, vaddr: u32) -> Option<u32>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 4, ExceptionCode::AddrErrorLoad)?;
+        let addr = self.translate_or_trigger(vaddr, false)?;
+        self.charge_access(addr, false);
+        let result = self.mem().try_read_word(addr.into());
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Clear the link if a store to a virtual address overlaps it.
+    fn break_link(&mut self, vaddr: u32) {
+        if self.read_link() == Some(vaddr & 0xFFFF_FFFC) {
+            self.write_link(None);
+        }
+    }
+
+    /// Store a byte to a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn store_byte(&mut self, vaddr: u32, data: u8) -> Option<()>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        let addr = self.translate_or_trigger(vaddr, true)?;
+        self.charge_access(addr, true);
+        self.break_link(vaddr);
+        let result = self.mem().try_write_byte(addr.into(), data);
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Store a halfword to a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn store_halfword(&mut self, vaddr: u32, data: u16) -> Option<()>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 2, ExceptionCode::AddrErrorStore)?;
+        let addr = self.translate_or_trigger(vaddr, true)?;
+        self.charge_access(addr, true);
+        self.break_link(vaddr);
+        let result = self.mem().try_write_halfword(addr.into(), data);
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
+
+    /// Store a word to a virtual address.
+    /// 
+    /// Returns `None` if an exception was triggered.
+    fn store_word(&mut self, vaddr: u32, data: u32) -> Option<()>
+        where <Self::Mem as Memory>::Addr: From<u32> {
+        self.check_alignment(vaddr, 4, ExceptionCode::AddrErrorStore)?;
+        let addr = self.translate_or_trigger(vaddr, true)?;
+        self.charge_access(addr, true);
+        self.break_link(vaddr);
+        let result = self.mem().try_write_word(addr.into(), data);
+        self.check_bus(result, ExceptionCode::DataBusError, vaddr)
+    }
 
     /// Borrow coprocessor 0.
-    fn coproc_0<'a>(&'a mut self) -> &'a mut Self::Coproc0;
+    fn coproc_0(&mut self) -> &mut Self::Coproc0;
     /// Borrow coprocessor 1.
-    fn coproc_1<'a>(&'a mut self) -> Option<&'a mut Self::Coproc1>;
+    fn coproc_1(&mut self) -> Option<&mut Self::Coproc1>;
     /// Borrow coprocessor 2.
-    fn coproc_2<'a>(&'a mut self) -> Option<&'a mut Self::Coproc2>;
+    fn coproc_2(&mut self) -> Option<&mut Self::Coproc2>;
     /// Borrow coprocessor 3.
-    fn coproc_3<'a>(&'a mut self) -> Option<&'a mut Self::Coproc3>;
+    fn coproc_3(&mut self) -> Option<&mut Self::Coproc3>;
+
+    /// Read the coprocessor 0 Status register.
+    fn status(&mut self) -> u32 {
+        self.coproc_0().move_from_reg(cp0_reg::STATUS)
+    }
+
+    /// Write the coprocessor 0 Status register.
+    fn set_status(&mut self, val: u32) {
+        self.coproc_0().move_to_reg(cp0_reg::STATUS, val);
+    }
+
+    /// Read the coprocessor 0 Cause register.
+    fn cause(&mut self) -> u32 {
+        self.coproc_0().move_from_reg(cp0_reg::CAUSE)
+    }
+
+    /// Write the coprocessor 0 Cause register.
+    /// 
+    /// As with `mtc0`, only the bits the coprocessor allows to be written are changed.
+    fn set_cause(&mut self, val: u32) {
+        self.coproc_0().move_to_reg(cp0_reg::CAUSE, val);
+    }
+
+    /// Read the coprocessor 0 EPC register.
+    fn epc(&mut self) -> u32 {
+        self.coproc_0().move_from_reg(cp0_reg::EPC)
+    }
+
+    /// Write the coprocessor 0 EPC register.
+    fn set_epc(&mut self, val: u32) {
+        self.coproc_0().move_to_reg(cp0_reg::EPC, val);
+    }
 }
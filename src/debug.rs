@@ -0,0 +1,396 @@
+// A controller for driving a core from an interactive debugger.
+
+use alloc::{
+    string::String,
+    vec::Vec
+};
+
+use crate::cpu::{
+    MIPSCore,
+    MIPSICore,
+    StepResult,
+    ExceptionInfo,
+    mips1::{
+        REG_NAMES,
+        disassemble_range
+    }
+};
+use crate::mem::{
+    Memory,
+    Mem32
+};
+
+/// A register that can be accessed by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    /// A general-purpose register, 0-31.
+    Gp(usize),
+    Hi,
+    Lo,
+    Pc
+}
+
+impl Register {
+    /// Find a register from its name.
+    ///
+    /// Accepts conventional names (`t0`, `sp`), numbers (`8`, `r8`),
+    /// and `hi`, `lo`, `pc`. A leading `$` is ignored.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.strip_prefix('$').unwrap_or(name);
+        match name {
+            "hi" => return Some(Register::Hi),
+            "lo" => return Some(Register::Lo),
+            "pc" => return Some(Register::Pc),
+            "s8" => return Some(Register::Gp(30)),
+            _ => {},
+        }
+        if let Some(reg) = REG_NAMES.iter().position(|n| *n == name) {
+            return Some(Register::Gp(reg));
+        }
+        let num = name.strip_prefix('r').unwrap_or(name);
+        match num.parse::<usize>() {
+            Ok(reg) if reg < 32 => Some(Register::Gp(reg)),
+            _ => None,
+        }
+    }
+}
+
+/// An error from a debugger command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebugError {
+    /// No register has the name given.
+    UnknownRegister(String)
+}
+
+/// Why a debugger command stopped running the core.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// All the steps requested were executed.
+    Done,
+    /// The instruction budget ran out before the core stopped.
+    BudgetExhausted,
+    /// The PC reached a breakpoint, at the address given.
+    Breakpoint(u32),
+    /// The core is stuck in a branch-to-self loop.
+    Stuck,
+    /// A software debug breakpoint was executed, at the address given.
+    DebugBreak(u32),
+    /// The core fetched a word of `0xFFFF_FFFF`, at the address given.
+    GarbageFetch(u32),
+    /// The core is idle, waiting for an interrupt.
+    Waiting,
+    /// The core was halted by its syscall handler.
+    Halted
+}
+
+/// The result of running the core.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stop {
+    /// The number of instructions executed.
+    pub executed:   usize,
+    /// Why the core stopped.
+    pub reason:     StopReason,
+    /// The exception triggered by the last instruction executed, if there was one.
+    pub exception:  Option<ExceptionInfo>,
+}
+
+/// A snapshot of the register state of the core.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub pc:             u32,
+    pub gp:             [u32; 32],
+    pub hi:             u32,
+    pub lo:             u32,
+    pub in_delay_slot:  bool,
+    pub last_exception: Option<ExceptionInfo>,
+}
+
+/// Drives a core with debugger commands.
+///
+/// Each command returns its result rather than printing it, so any frontend can present it.
+pub struct Debugger<C> {
+    core:   C,
+}
+
+impl<C> Debugger<C>
+    where C: MIPSCore + MIPSICore, C::Mem: Mem32<Addr = u32>
+{
+    /// Wrap a core in a debugger.
+    pub fn new(core: C) -> Self {
+        Self {
+            core,
+        }
+    }
+
+    /// Get the core.
+    pub fn core(&mut self) -> &mut C {
+        &mut self.core
+    }
+
+    /// Unwrap the debugger, returning the core.
+    pub fn into_inner(self) -> C {
+        self.core
+    }
+
+    /// Execute up to `count` instructions, stopping early at a breakpoint.
+    pub fn step(&mut self, count: usize) -> Stop {
+        self.run(count, StopReason::Done)
+    }
+
+    /// Execute instructions until a breakpoint is reached or the core gets stuck,
+    /// giving up after `max_instructions`.
+    ///
+    /// If the PC is already at a breakpoint, that instruction is executed first.
+    /// If the budget runs out, the reason is `StopReason::BudgetExhausted`,
+    /// for example when the program has run off into empty memory.
+    pub fn resume(&mut self, max_instructions: usize) -> Stop {
+        self.run(max_instructions, StopReason::BudgetExhausted)
+    }
+
+    /// Install a breakpoint.
+    pub fn add_breakpoint(&mut self, addr: u32) {
+        self.core.add_breakpoint(addr);
+    }
+
+    /// Remove a breakpoint, if it was installed.
+    pub fn remove_breakpoint(&mut self, addr: u32) {
+        self.core.remove_breakpoint(addr);
+    }
+
+    /// List the breakpoints installed in the core, in address order.
+    /// 
+    /// This includes breakpoints installed directly on the core.
+    pub fn breakpoints(&self) -> Vec<u32> {
+        self.core.breakpoints()
+    }
+
+    /// Read a register by name.
+    pub fn read_register(&self, name: &str) -> Result<u32, DebugError> {
+        Ok(match Self::register(name)? {
+            Register::Gp(reg) => self.core.read_gp(reg),
+            Register::Hi => self.core.read_hi(),
+            Register::Lo => self.core.read_lo(),
+            Register::Pc => self.core.read_pc(),
+        })
+    }
+
+    /// Write a register by name.
+    ///
+    /// Writing the PC cancels any pending branch.
+    pub fn write_register(&mut self, name: &str, val: u32) -> Result<(), DebugError> {
+        match Self::register(name)? {
+            Register::Gp(reg) => self.core.write_gp(reg, val),
+            Register::Hi => self.core.write_hi(val),
+            Register::Lo => self.core.write_lo(val),
+            Register::Pc => self.core.write_pc(val),
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes of memory.
+    ///
+    /// Addresses are physical: they are not translated, so reading has no effect on the core.
+    pub fn read_memory(&mut self, addr: u32, len: usize) -> Vec<u8> {
+        let mem = self.core.mem();
+        (0..len).map(|i| mem.read_byte(addr.wrapping_add(i as u32))).collect()
+    }
+
+    /// Write bytes to memory, at a physical address.
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) {
+        let mem = self.core.mem();
+        for (i, byte) in data.iter().enumerate() {
+            mem.write_byte(addr.wrapping_add(i as u32), *byte);
+        }
+    }
+
+    /// Disassemble `count` instructions, starting at a physical address.
+    ///
+    /// Returns the address, raw word, and text of each instruction.
+    pub fn disassemble(&mut self, addr: u32, count: usize) -> Vec<(u32, u32, String)> {
+        disassemble_range(self.core.mem(), addr, count)
+    }
+
+    /// Take a snapshot of the register state.
+    pub fn state(&self) -> State {
+        let mut gp = [0; 32];
+        for (reg, val) in gp.iter_mut().enumerate() {
+            *val = self.core.read_gp(reg);
+        }
+        State {
+            pc:             self.core.read_pc(),
+            gp,
+            hi:             self.core.read_hi(),
+            lo:             self.core.read_lo(),
+            in_delay_slot:  self.core.in_delay_slot(),
+            last_exception: self.core.last_exception(),
+        }
+    }
+
+    fn register(name: &str) -> Result<Register, DebugError> {
+        Register::from_name(name).ok_or_else(|| DebugError::UnknownRegister(String::from(name)))
+    }
+
+    // Execute up to `count` instructions, stopping with `reason` if nothing else stops the core first.
+    fn run(&mut self, count: usize, reason: StopReason) -> Stop {
+        let mut executed = 0;
+        while executed < count {
+            if let Some(reason) = self.step_once(&mut executed) {
+                return self.stop(executed, reason);
+            }
+        }
+        self.stop(executed, reason)
+    }
+
+    // Step once, counting the instruction if one was executed.
+    // Returns why the core stopped, if it should stop.
+    fn step_once(&mut self, executed: &mut usize) -> Option<StopReason> {
+        let result = self.core.step();
+        if !matches!(result, StepResult::Breakpoint | StepResult::GarbageFetch | StepResult::Waiting | StepResult::Halted) {
+            *executed += 1;
+        }
+        match result {
+            StepResult::Executed => None,
+            StepResult::Breakpoint => Some(StopReason::Breakpoint(self.core.read_pc())),
+            StepResult::Stuck => Some(StopReason::Stuck),
+            StepResult::DebugBreak => Some(StopReason::DebugBreak(self.core.last_exception().map_or(0, |e| e.pc))),
+            StepResult::GarbageFetch => Some(StopReason::GarbageFetch(self.core.read_pc())),
+            StepResult::Waiting => Some(StopReason::Waiting),
+            StepResult::Halted => Some(StopReason::Halted),
+        }
+    }
+
+    fn stop(&self, executed: usize, reason: StopReason) -> Stop {
+        Stop {
+            executed,
+            reason,
+            exception: self.core.last_exception(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use crate::cpu::mips1::MIPSI;
+    use crate::mem::RamMemory;
+
+    fn debugger(program: &[u32]) -> Debugger<MIPSI<RamMemory<0x100>>> {
+        let mut mem = RamMemory::<0x100>::new();
+        for (i, word) in program.iter().enumerate() {
+            mem.write_word((i * 4) as u32, *word);
+        }
+        Debugger::new(MIPSI::<RamMemory<0x100>>::with_memory(Box::new(mem)).build())
+    }
+
+    #[test]
+    fn step() {
+        let mut dbg = debugger(&[
+            0x2408_0001,    // addiu $t0, $zero, 1
+            0x2508_0001,    // addiu $t0, $t0, 1
+            0x2508_0001,    // addiu $t0, $t0, 1
+        ]);
+
+        assert_eq!(dbg.step(2), Stop { executed: 2, reason: StopReason::Done, exception: None });
+        assert_eq!(dbg.read_register("$t0"), Ok(2));
+        assert_eq!(dbg.read_register("pc"), Ok(0x8));
+
+        let state = dbg.state();
+        assert_eq!(state.gp[8], 2);
+        assert_eq!(state.pc, 0x8);
+        assert!(!state.in_delay_slot);
+    }
+
+    #[test]
+    fn breakpoint_continue() {
+        let mut dbg = debugger(&[
+            0x2408_0003,    // addiu $t0, $zero, 3
+            0x2508_FFFF,    // addiu $t0, $t0, -1
+            0x1500_FFFE,    // bne $t0, $zero, -2
+            0x0000_0000,    // nop
+            0x2409_0001,    // addiu $t1, $zero, 1
+        ]);
+        dbg.add_breakpoint(0x4);
+        assert_eq!(dbg.breakpoints(), [0x4]);
+
+        let stop = dbg.resume(1000);
+        assert_eq!(stop.reason, StopReason::Breakpoint(0x4));
+        assert_eq!(stop.executed, 1);
+        assert_eq!(dbg.read_register("t0"), Ok(3));
+
+        let stop = dbg.resume(1000);
+        assert_eq!(stop.reason, StopReason::Breakpoint(0x4));
+        assert_eq!(stop.executed, 3);
+        assert_eq!(dbg.read_register("t0"), Ok(2));
+
+        dbg.remove_breakpoint(0x4);
+        assert!(dbg.breakpoints().is_empty());
+        assert_eq!(dbg.step(8), Stop { executed: 8, reason: StopReason::Done, exception: None });
+        assert_eq!(dbg.read_register("t0"), Ok(0));
+        assert_eq!(dbg.read_register("t1"), Ok(1));
+    }
+
+    #[test]
+    fn resume_budget() {
+        // Zeroed memory is all NOPs, and reads past its end return 0 too, so this never stops by itself.
+        let mut dbg = debugger(&[]);
+
+        let stop = dbg.resume(500);
+        assert_eq!(stop.reason, StopReason::BudgetExhausted);
+        assert_eq!(stop.executed, 500);
+    }
+
+    #[test]
+    fn core_breakpoints() {
+        let mut dbg = debugger(&[]);
+
+        dbg.add_breakpoint(0x20);
+        dbg.core().add_breakpoint(0x10);
+        assert_eq!(dbg.breakpoints(), [0x10, 0x20]);
+        dbg.core().remove_breakpoint(0x20);
+        assert_eq!(dbg.breakpoints(), [0x10]);
+    }
+
+    #[test]
+    fn debug_break() {
+        let mut dbg = debugger(&[
+            0x2408_0001,    // addiu $t0, $zero, 1
+            0x7000_003F,    // sdbbp
+            0x2409_0001,    // addiu $t1, $zero, 1
+        ]);
+
+        let stop = dbg.resume(1000);
+        assert_eq!(stop.reason, StopReason::DebugBreak(0x4));
+        assert_eq!(stop.executed, 2);
+        assert_eq!(dbg.read_register("pc"), Ok(0x8));
+
+        assert_eq!(dbg.step(1).reason, StopReason::Done);
+        assert_eq!(dbg.read_register("t1"), Ok(1));
+    }
+
+    #[test]
+    fn registers_by_name() {
+        let mut dbg = debugger(&[]);
+
+        dbg.write_register("$sp", 0x1234).unwrap();
+        dbg.write_register("r4", 5).unwrap();
+        dbg.write_register("hi", 6).unwrap();
+        dbg.write_register("pc", 0x40).unwrap();
+        assert_eq!(dbg.read_register("29"), Ok(0x1234));
+        assert_eq!(dbg.read_register("a0"), Ok(5));
+        assert_eq!(dbg.read_register("$hi"), Ok(6));
+        assert_eq!(dbg.read_register("pc"), Ok(0x40));
+        assert_eq!(dbg.read_register("$zero"), Ok(0));
+        assert_eq!(dbg.read_register("r32"), Err(DebugError::UnknownRegister(String::from("r32"))));
+        assert_eq!(dbg.write_register("foo", 0), Err(DebugError::UnknownRegister(String::from("foo"))));
+    }
+
+    #[test]
+    fn memory() {
+        let mut dbg = debugger(&[0x2408_0001]);
+
+        dbg.write_memory(0x10, &[1, 2, 3, 4]);
+        assert_eq!(dbg.read_memory(0x10, 4), [1, 2, 3, 4]);
+        assert_eq!(dbg.disassemble(0, 1), [(0, 0x2408_0001, String::from("addiu $t0, $zero, 1"))]);
+    }
+}
@@ -1,4 +1,10 @@
-mod common;
+// Tests always link std. The no_std build is checked by building for a bare-metal target.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+/// Common helper ops, including the kseg address helpers and the byte ops used by the exported memory macros.
+pub mod common;
 
 /// Memory bus attachment traits.
 #[macro_use]
@@ -8,4 +14,21 @@ pub mod mem;
 pub mod cpu;
 
 /// Coprocessors, including coprocessor 0.
-pub mod coproc;
\ No newline at end of file
+pub mod coproc;
+
+/// A controller for debugging a core.
+pub mod debug;
+
+/// A scheduler for running a CPU alongside other devices.
+pub mod sched;
+
+/// Recording and replaying external inputs.
+pub mod replay;
+
+/// An entry point for fuzzing.
+#[cfg(any(feature = "fuzz", test))]
+pub mod fuzz;
+
+/// Utilities for testing short programs.
+#[cfg(any(feature = "test-utils", test))]
+pub mod test_utils;
@@ -0,0 +1,254 @@
+// A memory bus that routes accesses to devices.
+
+use alloc::{
+    boxed::Box,
+    string::String,
+    vec::Vec
+};
+use core::{
+    fmt,
+    ops::Range
+};
+
+use super::*;
+
+/// A device mapped onto a bus.
+pub type Device = dyn Mem32<Addr = u32>;
+
+/// An error from mapping a device onto a bus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapError {
+    /// The range doesn't cover any addresses.
+    Empty,
+    /// The range overlaps a device that is already mapped, with the name given.
+    Overlap(String)
+}
+
+struct Mapping {
+    range:  Range<u32>,
+    name:   String,
+    device: Box<Device>,
+}
+
+/// A 32-bit memory bus, made of devices mapped to address ranges.
+///
+/// Each device sees addresses relative to the start of its range.
+/// Accessing an address that isn't mapped is a bus error: reads return 0 and writes are discarded.
+pub struct Bus {
+    // Sorted by start address.
+    mappings:       Vec<Mapping>,
+    little_endian:  bool,
+}
+
+impl Bus {
+    /// Make a new little-endian bus with nothing mapped.
+    pub fn new() -> Self {
+        Self {
+            mappings:       Vec::new(),
+            little_endian:  true,
+        }
+    }
+
+    /// Map a device to the range of addresses provided.
+    ///
+    /// The range must not overlap any device that is already mapped.
+    pub fn map_device<D: Mem32<Addr = u32> + 'static>(&mut self, name: &str, range: Range<u32>, device: D) -> Result<(), MapError> {
+        if range.is_empty() {
+            return Err(MapError::Empty);
+        }
+        if let Some(mapping) = self.mappings.iter().find(|m| m.range.start < range.end && range.start < m.range.end) {
+            return Err(MapError::Overlap(mapping.name.clone()));
+        }
+
+        let mut device = Box::new(device);
+        device.set_endianness(self.little_endian);
+        let index = self.mappings.partition_point(|m| m.range.start < range.start);
+        self.mappings.insert(index, Mapping {
+            range,
+            name:   String::from(name),
+            device,
+        });
+        Ok(())
+    }
+
+    /// List the range and name of each device, in address order.
+    pub fn map(&self) -> Vec<(Range<u32>, &str)> {
+        self.mappings.iter()
+            .map(|m| (m.range.clone(), m.name.as_str()))
+            .collect()
+    }
+
+    // Find the device at an address, and the address relative to it.
+    fn device(&mut self, addr: u32) -> Result<(&mut Device, u32), MemError> {
+        let index = self.mappings.partition_point(|m| m.range.start <= addr);
+        match self.mappings[..index].last_mut() {
+            Some(m) if addr < m.range.end => Ok((m.device.as_mut(), addr - m.range.start)),
+            _ => Err(MemError::BusError),
+        }
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for mapping in &self.mappings {
+            writeln!(f, "0x{:08X}-0x{:08X}  {}", mapping.range.start, mapping.range.end - 1, mapping.name)?;
+        }
+        Ok(())
+    }
+}
+
+impl Memory for Bus {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.try_read_byte(addr).unwrap_or(0)
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        let _ = self.try_write_byte(addr, data);
+    }
+
+    fn access_cycles(&self, addr: Self::Addr, is_write: bool) -> u32 {
+        let index = self.mappings.partition_point(|m| m.range.start <= addr);
+        match self.mappings[..index].last() {
+            Some(m) if addr < m.range.end => m.device.access_cycles(addr - m.range.start, is_write),
+            _ => 1,
+        }
+    }
+
+    fn on_sync(&mut self) {
+        for mapping in self.mappings.iter_mut() {
+            mapping.device.on_sync();
+        }
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        for mapping in self.mappings.iter_mut() {
+            mapping.device.on_interrupt_acknowledged(line);
+        }
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        if let Ok((device, addr)) = self.device(addr) {
+            device.on_prefetch(addr);
+        }
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        if let Ok((device, addr)) = self.device(addr) {
+            device.on_cache(op, addr);
+        }
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_read_byte(addr)
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_write_byte(addr, data)
+    }
+}
+
+impl Mem16 for Bus {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.try_read_halfword(addr).unwrap_or(0)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        let _ = self.try_write_halfword(addr, data);
+    }
+
+    fn little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    fn set_endianness(&mut self, little_endian: bool) {
+        self.little_endian = little_endian;
+        for mapping in self.mappings.iter_mut() {
+            mapping.device.set_endianness(little_endian);
+        }
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_read_halfword(addr)
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_write_halfword(addr, data)
+    }
+}
+
+impl Mem32 for Bus {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.try_read_word(addr).unwrap_or(0)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        let _ = self.try_write_word(addr, data);
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_read_word(addr)
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.try_write_word(addr, data)
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        let (device, addr) = self.device(addr)?;
+        device.fetch_word(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn routing() {
+        let mut bus = Bus::new();
+        bus.map_device("ram", 0x0000_0000..0x0000_1000, RamMemory::<0x1000>::new()).unwrap();
+        bus.map_device("rom", 0x1FC0_0000..0x1FC0_0100, RamMemory::<0x100>::new()).unwrap();
+
+        bus.write_word(0x0000_0010, 0x1234_5678);
+        bus.write_word(0x1FC0_0010, 0x9ABC_DEF0);
+        assert_eq!(bus.read_word(0x0000_0010), 0x1234_5678);
+        assert_eq!(bus.read_halfword(0x1FC0_0012), 0x9ABC);
+        assert_eq!(bus.try_read_word(0x0000_1000), Err(MemError::BusError));
+        assert_eq!(bus.try_write_byte(0x1FBF_FFFF, 0), Err(MemError::BusError));
+    }
+
+    #[test]
+    fn map() {
+        let mut bus = Bus::new();
+        bus.map_device("rom", 0x1FC0_0000..0x1FC8_0000, DynRam::new(0x8_0000)).unwrap();
+        bus.map_device("ram", 0x0000_0000..0x0020_0000, DynRam::new(0x20_0000)).unwrap();
+
+        assert_eq!(bus.map(), vec![
+            (0x0000_0000..0x0020_0000, "ram"),
+            (0x1FC0_0000..0x1FC8_0000, "rom"),
+        ]);
+        assert_eq!(bus.to_string(), "0x00000000-0x001FFFFF  ram\n0x1FC00000-0x1FC7FFFF  rom\n");
+
+        assert_eq!(
+            bus.map_device("io", 0x001F_0000..0x0021_0000, DynRam::new(0x2_0000)),
+            Err(MapError::Overlap(String::from("ram")))
+        );
+        assert_eq!(bus.map_device("empty", 0x100..0x100, DynRam::new(0)), Err(MapError::Empty));
+        assert_eq!(bus.map().len(), 2);
+    }
+}
@@ -0,0 +1,366 @@
+// A memory wrapper that models direct-mapped caches.
+
+use alloc::{
+    vec,
+    vec::Vec
+};
+
+use super::*;
+
+/// Hit and miss counts for the caches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub instruction_hits:   u64,
+    pub instruction_misses: u64,
+    pub data_hits:          u64,
+    pub data_misses:        u64,
+}
+
+impl CacheStats {
+    /// The fraction of instruction fetches that hit, or 0 if there were none.
+    pub fn instruction_hit_rate(&self) -> f32 {
+        hit_rate(self.instruction_hits, self.instruction_misses)
+    }
+
+    /// The fraction of data reads that hit, or 0 if there were none.
+    pub fn data_hit_rate(&self) -> f32 {
+        hit_rate(self.data_hits, self.data_misses)
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f32 {
+    let total = hits + misses;
+    if total == 0 {
+        0.0
+    } else {
+        hits as f32 / total as f32
+    }
+}
+
+// Cache operations, from bits 2-4 of the CACHE op field.
+const INDEX_INVALIDATE: u32 = 0;
+const HIT_INVALIDATE: u32 = 4;
+const HIT_WRITEBACK_INVALIDATE: u32 = 5;
+
+// Caches, from bits 0-1 of the CACHE op field.
+const INSTRUCTION_CACHE: u32 = 0;
+const DATA_CACHE: u32 = 1;
+
+// The tags of a single direct-mapped cache.
+struct Cache {
+    line_shift: u32,
+    tags:       Vec<Option<u32>>,
+    hits:       u64,
+    misses:     u64,
+}
+
+impl Cache {
+    fn new(line_size: u32, sets: usize) -> Self {
+        Self {
+            line_shift: line_size.trailing_zeros(),
+            tags:       vec![None; sets],
+            hits:       0,
+            misses:     0,
+        }
+    }
+
+    fn index(&self, addr: u32) -> (usize, u32) {
+        let line = addr >> self.line_shift;
+        ((line as usize) & (self.tags.len() - 1), line)
+    }
+
+    fn contains(&self, addr: u32) -> bool {
+        let (set, line) = self.index(addr);
+        self.tags[set] == Some(line)
+    }
+
+    // Look up an address, filling the line on a miss.
+    fn access(&mut self, addr: u32) {
+        let (set, line) = self.index(addr);
+        if self.tags[set] == Some(line) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            self.tags[set] = Some(line);
+        }
+    }
+
+    fn invalidate_set(&mut self, addr: u32) {
+        let (set, _) = self.index(addr);
+        self.tags[set] = None;
+    }
+
+    fn invalidate_line(&mut self, addr: u32) {
+        if self.contains(addr) {
+            self.invalidate_set(addr);
+        }
+    }
+
+    fn invalidate_all(&mut self) {
+        for tag in self.tags.iter_mut() {
+            *tag = None;
+        }
+    }
+}
+
+/// Memory with direct-mapped instruction and data caches in front of it.
+///
+/// The caches only track which lines are present, to count hits and misses:
+/// all accesses still go to the inner memory, so caching never changes what is read.
+/// Instruction fetches use the instruction cache, and reads use the data cache.
+/// Writes pass straight through and don't allocate lines.
+///
+/// `CACHE` instructions on the primary caches invalidate lines.
+/// Other operations, and the secondary caches, are ignored.
+pub struct CachedMem<M: Mem32<Addr = u32>> {
+    mem:            M,
+    instruction:    Cache,
+    data:           Cache,
+}
+
+impl<M: Mem32<Addr = u32>> CachedMem<M> {
+    /// Wrap memory with caches of `sets` lines of `line_size` bytes each.
+    ///
+    /// # Panics
+    /// If `line_size` or `sets` is not a power of two.
+    pub fn new(mem: M, line_size: u32, sets: usize) -> Self {
+        assert!(line_size.is_power_of_two(), "cache line size must be a power of two");
+        assert!(sets.is_power_of_two(), "cache set count must be a power of two");
+        Self {
+            mem,
+            instruction:    Cache::new(line_size, sets),
+            data:           Cache::new(line_size, sets),
+        }
+    }
+
+    /// Get the hit and miss counts so far.
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            instruction_hits:   self.instruction.hits,
+            instruction_misses: self.instruction.misses,
+            data_hits:          self.data.hits,
+            data_misses:        self.data.misses,
+        }
+    }
+
+    /// Reset the hit and miss counts, keeping cached lines.
+    pub fn clear_stats(&mut self) {
+        self.instruction.hits = 0;
+        self.instruction.misses = 0;
+        self.data.hits = 0;
+        self.data.misses = 0;
+    }
+
+    /// Invalidate every line in both caches.
+    pub fn invalidate_all(&mut self) {
+        self.instruction.invalidate_all();
+        self.data.invalidate_all();
+    }
+
+    /// Get the inner memory.
+    pub fn inner(&mut self) -> &mut M {
+        &mut self.mem
+    }
+
+    /// Unwrap the memory, discarding the caches.
+    pub fn into_inner(self) -> M {
+        self.mem
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Memory for CachedMem<M> {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.data.access(addr);
+        self.mem.read_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.mem.write_byte(addr, data);
+    }
+
+    fn access_cycles(&self, addr: Self::Addr, is_write: bool) -> u32 {
+        self.mem.access_cycles(addr, is_write)
+    }
+
+    fn on_sync(&mut self) {
+        self.mem.on_sync();
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        self.mem.on_prefetch(addr);
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        let cache = match op & 3 {
+            INSTRUCTION_CACHE => Some(&mut self.instruction),
+            DATA_CACHE => Some(&mut self.data),
+            _ => None
+        };
+        if let Some(cache) = cache {
+            match op >> 2 {
+                INDEX_INVALIDATE => cache.invalidate_set(addr),
+                HIT_INVALIDATE | HIT_WRITEBACK_INVALIDATE => cache.invalidate_line(addr),
+                _ => {}
+            }
+        }
+        self.mem.on_cache(op, addr);
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        self.mem.on_interrupt_acknowledged(line);
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        self.data.access(addr);
+        self.mem.try_read_byte(addr)
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        self.mem.try_write_byte(addr, data)
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem16 for CachedMem<M> {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.data.access(addr);
+        self.mem.read_halfword(addr)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.mem.write_halfword(addr, data);
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        self.data.access(addr);
+        self.mem.try_read_halfword(addr)
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        self.mem.try_write_halfword(addr, data)
+    }
+
+    fn little_endian(&self) -> bool {
+        self.mem.little_endian()
+    }
+
+    fn set_endianness(&mut self, little_endian: bool) {
+        self.mem.set_endianness(little_endian);
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem32 for CachedMem<M> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.data.access(addr);
+        self.mem.read_word(addr)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.mem.write_word(addr, data);
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.data.access(addr);
+        self.mem.try_read_word(addr)
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        self.mem.try_write_word(addr, data)
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.instruction.access(addr);
+        self.mem.fetch_word(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use crate::cpu::{MIPSCore, MIPSICore};
+    use crate::cpu::mips1::MIPSI;
+
+    #[test]
+    fn loop_hit_rate() {
+        let mut ram = RamMemory::<0x1000>::new();
+        // ori $1, $0, 0x100; ori $3, $0, 4
+        ram.write_word(0x00, 0x3401_0100);
+        ram.write_word(0x04, 0x3403_0004);
+        // loop: lw $2, 0($1); lw $2, 0x10($1); lw $2, 0x20($1)
+        ram.write_word(0x08, 0x8C22_0000);
+        ram.write_word(0x0C, 0x8C22_0010);
+        ram.write_word(0x10, 0x8C22_0020);
+        // addiu $3, $3, -1; bne $3, $0, loop; nop
+        ram.write_word(0x14, 0x2463_FFFF);
+        ram.write_word(0x18, 0x1460_FFFB);
+
+        let mem = CachedMem::new(ram, 16, 16);
+        let mut cpu = MIPSI::<CachedMem<RamMemory<0x1000>>>::with_memory(Box::new(mem)).build();
+
+        // The setup and the first pass.
+        for _ in 0..8 {
+            cpu.step();
+        }
+        let first = cpu.mem().cache_stats();
+        assert_eq!(first.data_misses, 3);
+        assert_eq!(first.data_hits, 0);
+        assert_eq!(first.instruction_misses, 2);
+
+        // The working set fits, so later passes only hit.
+        for _ in 0..18 {
+            cpu.step();
+        }
+        let stats = cpu.mem().cache_stats();
+        assert_eq!(stats.data_misses, 3);
+        assert_eq!(stats.data_hits, 9);
+        assert_eq!(stats.instruction_misses, 2);
+        assert!(stats.data_hit_rate() > first.data_hit_rate());
+        assert!(stats.instruction_hit_rate() > first.instruction_hit_rate());
+        assert_eq!(cpu.read_gp(3), 0);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut mem = CachedMem::new(RamMemory::<0x1000>::new(), 16, 4);
+        // 0x000 and 0x040 map to the same set.
+        mem.read_word(0x000);
+        mem.read_word(0x040);
+        mem.read_word(0x000);
+        mem.read_word(0x004);
+        assert_eq!(mem.cache_stats(), CacheStats { data_hits: 1, data_misses: 3, ..Default::default() });
+
+        // Writes pass through without allocating.
+        mem.write_word(0x080, 0x1234);
+        assert_eq!(mem.read_word(0x080), 0x1234);
+        assert_eq!(mem.cache_stats().data_misses, 4);
+    }
+
+    #[test]
+    fn cache_op() {
+        let mut mem = CachedMem::new(RamMemory::<0x1000>::new(), 16, 4);
+        mem.read_word(0x000);
+        mem.fetch_word(0x010).unwrap();
+
+        // Hit invalidate on the data cache.
+        mem.on_cache((HIT_INVALIDATE << 2) | DATA_CACHE, 0x008);
+        mem.read_word(0x000);
+        assert_eq!(mem.cache_stats().data_misses, 2);
+
+        // A hit invalidate for a different line in the same set does nothing.
+        mem.on_cache((HIT_INVALIDATE << 2) | DATA_CACHE, 0x040);
+        mem.read_word(0x000);
+        assert_eq!(mem.cache_stats().data_hits, 1);
+
+        // Index invalidate on the instruction cache.
+        mem.on_cache((INDEX_INVALIDATE << 2) | INSTRUCTION_CACHE, 0x050);
+        mem.fetch_word(0x010).unwrap();
+        assert_eq!(mem.cache_stats().instruction_misses, 2);
+
+        mem.clear_stats();
+        mem.invalidate_all();
+        mem.read_word(0x000);
+        assert_eq!(mem.cache_stats(), CacheStats { data_misses: 1, ..Default::default() });
+    }
+}
@@ -0,0 +1,172 @@
+// A memory-mapped console, for simple guest output.
+
+use alloc::{
+    borrow::Cow,
+    string::String,
+    vec::Vec
+};
+use core::str;
+
+use super::*;
+
+/// A console that captures the bytes a guest writes to it.
+///
+/// Any write to offset 0 appends the low byte of the value written, so guests can print with `SB`.
+/// Writes to other offsets are discarded, and reads return 0.
+///
+/// Map it onto a `Bus` through a `SharedMem`, and keep a handle to read the output.
+#[derive(Default)]
+pub struct ConsoleMmio {
+    bytes: Vec<u8>,
+}
+
+impl ConsoleMmio {
+    /// Make a new console with no output.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the output as text.
+    ///
+    /// Bytes that aren't valid UTF-8 are replaced with U+FFFD.
+    /// A character at the end that has only been partly written isn't included yet.
+    pub fn output(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes[..self.complete_len()])
+    }
+
+    // Get the length of the output, without an incomplete character at the end.
+    fn complete_len(&self) -> usize {
+        // Find where the last character starts. A character is at most 4 bytes.
+        let len = self.bytes.len();
+        let last = (len.saturating_sub(4)..len).rev()
+            .find(|&i| (self.bytes[i] & 0xC0) != 0x80);
+        match last.map(|i| (i, str::from_utf8(&self.bytes[i..]))) {
+            Some((i, Err(e))) if e.error_len().is_none() => i,
+            _ => len,
+        }
+    }
+
+    /// Get the raw bytes written.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Discard the output so far.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    fn write(&mut self, addr: u32, data: u8) {
+        if addr == 0 {
+            self.bytes.push(data);
+        }
+    }
+}
+
+impl Memory for ConsoleMmio {
+    type Addr = u32;
+
+    fn read_byte(&mut self, _addr: Self::Addr) -> u8 {
+        0
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.write(addr, data);
+    }
+}
+
+impl Mem16 for ConsoleMmio {
+    fn read_halfword(&mut self, _addr: Self::Addr) -> u16 {
+        0
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.write(addr, data as u8);
+    }
+
+    fn little_endian(&self) -> bool {
+        true
+    }
+}
+
+impl Mem32 for ConsoleMmio {
+    fn read_word(&mut self, _addr: Self::Addr) -> u32 {
+        0
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.write(addr, data as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, string::String};
+    use crate::cpu::MIPSCore;
+    use crate::cpu::mips1::MIPSI;
+
+    #[test]
+    fn guest_output() {
+        let console = SharedMem::new(ConsoleMmio::new());
+        let mut ram = RamMemory::<0x1000>::new();
+        // lui $1, 0x1000
+        ram.write_word(0x00, 0x3C01_1000);
+        // Store "Hi!\n" a byte at a time.
+        for (i, c) in b"Hi!\n".iter().enumerate() {
+            // ori $2, $0, c; sb $2, 0($1)
+            ram.write_word(0x04 + (i as u32 * 8), 0x3402_0000 | (*c as u32));
+            ram.write_word(0x08 + (i as u32 * 8), 0xA022_0000);
+        }
+        // sw $2, 4($1): not the output register.
+        ram.write_word(0x24, 0xAC22_0004);
+
+        let mut bus = Bus::new();
+        bus.map_device("ram", 0x0000_0000..0x0000_1000, ram).unwrap();
+        bus.map_device("console", 0x1000_0000..0x1000_0010, console.clone()).unwrap();
+        let mut cpu = MIPSI::<Bus>::with_memory(Box::new(bus)).build();
+        for _ in 0..10 {
+            cpu.step();
+        }
+
+        assert_eq!(console.with(|c| String::from(c.output())), "Hi!\n");
+        console.with(|c| c.clear());
+        assert_eq!(console.with(|c| c.bytes().len()), 0);
+    }
+
+    #[test]
+    fn partial_character() {
+        let mut console = ConsoleMmio::new();
+
+        // "é" is 0xC3 0xA9 in UTF-8.
+        console.write_byte(0, b'a');
+        console.write_byte(0, 0xC3);
+        assert_eq!(console.output(), "a");
+        console.write_word(0, 0xA9);
+        assert_eq!(console.output(), "aé");
+        assert_eq!(console.read_word(0), 0);
+
+        // Only the incomplete character at the end is held back.
+        console.write_byte(0, 0xC3);
+        assert_eq!(console.output(), "aé");
+    }
+
+    #[test]
+    fn invalid_byte() {
+        let mut console = ConsoleMmio::new();
+
+        // A stray byte doesn't hide the output after it.
+        for b in b"ab\xFFcd" {
+            console.write_byte(0, *b);
+        }
+        assert_eq!(console.output(), "ab\u{FFFD}cd");
+        assert_eq!(console.bytes().len(), 5);
+
+        // "€" is 0xE2 0x82 0xAC in UTF-8.
+        console.write_byte(0, 0xE2);
+        console.write_byte(0, 0x82);
+        assert_eq!(console.output(), "ab\u{FFFD}cd");
+        console.write_byte(0, 0xAC);
+        assert_eq!(console.output(), "ab\u{FFFD}cd€");
+    }
+}
@@ -1,34 +1,64 @@
-/// Little endian memory implementations.
+// Little endian memory implementations.
+//
+// Each macro can optionally take the name of a `bool` field in the struct.
+// If provided, the field stores the endianness of the memory (`true` for little-endian),
+// and `set_endianness` can be used to switch it at runtime.
+// Otherwise the memory is always little-endian.
+//
+// Multi-byte accesses wrap around at the top of the address space.
 
 /// This provides default implementations for the `Mem16` trait, however they are not very optimal
 /// and you might get better performance from implementing them yourself.
 #[macro_export]
 macro_rules! impl_mem_16_little {
-    {$struct:ident} => {
+    {@impl $struct:ident { $($endian:tt)* }} => {
         impl Mem16 for $struct {
             fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
-                let lo = self.read_byte(addr);
-                let hi = self.read_byte(addr + Self::Addr::one());
-                make16(lo, hi)
+                let b0 = self.read_byte(addr);
+                let b1 = self.read_byte($crate::mem::Addr::new(addr).wrapping_add(Self::Addr::one()).value());
+                if self.little_endian() {
+                    make16(b0, b1)
+                } else {
+                    make16_be(b0, b1)
+                }
             }
 
             fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
-                let lo = lo16(data);
-                let hi = hi16(data);
-                self.write_byte(addr, lo);
-                self.write_byte(addr + Self::Addr::one(), hi);
+                let (b0, b1) = if self.little_endian() {
+                    (lo16(data), hi16(data))
+                } else {
+                    (hi16(data), lo16(data))
+                };
+                self.write_byte(addr, b0);
+                self.write_byte($crate::mem::Addr::new(addr).wrapping_add(Self::Addr::one()).value(), b1);
             }
 
+            $($endian)*
+        }
+    };
+    {$struct:ident} => {
+        impl_mem_16_little!{ @impl $struct {
             fn little_endian(&self) -> bool {
                 true
             }
-        }
+        }}
+    };
+    {$struct:ident, $field:ident} => {
+        impl_mem_16_little!{ @impl $struct {
+            fn little_endian(&self) -> bool {
+                self.$field
+            }
+
+            fn set_endianness(&mut self, little_endian: bool) {
+                self.$field = little_endian;
+            }
+        }}
     };
 }
 
@@ -36,34 +66,40 @@ macro_rules! impl_mem_16_little {
 /// and you might get better performance from implementing them yourself.
 #[macro_export]
 macro_rules! impl_mem_32_little {
-    {$struct:ident} => {
-        impl_mem_16_little!{ $struct }
-
+    {@impl $struct:ident} => {
         impl Mem32 for $struct {
             fn read_word(&mut self, addr: Self::Addr) -> u32 {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
                 let addr0 = addr;
-                let addr1 = addr0 + Self::Addr::one();
-                let addr2 = addr1 + Self::Addr::one();
-                let addr3 = addr2 + Self::Addr::one();
+                let addr1 = $crate::mem::Addr::new(addr0).wrapping_add(Self::Addr::one()).value();
+                let addr2 = $crate::mem::Addr::new(addr1).wrapping_add(Self::Addr::one()).value();
+                let addr3 = $crate::mem::Addr::new(addr2).wrapping_add(Self::Addr::one()).value();
                 let b0 = self.read_byte(addr0);
                 let b1 = self.read_byte(addr1);
                 let b2 = self.read_byte(addr2);
                 let b3 = self.read_byte(addr3);
-                make32(b0, b1, b2, b3)
+                if self.little_endian() {
+                    make32(b0, b1, b2, b3)
+                } else {
+                    make32_be(b0, b1, b2, b3)
+                }
             }
 
             fn write_word(&mut self, addr: Self::Addr, data: u32) {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
-                let bytes = bytes32(data);
+                let bytes = if self.little_endian() {
+                    bytes32(data)
+                } else {
+                    bytes32_be(data)
+                };
                 let addr0 = addr;
-                let addr1 = addr0 + Self::Addr::one();
-                let addr2 = addr1 + Self::Addr::one();
-                let addr3 = addr2 + Self::Addr::one();
+                let addr1 = $crate::mem::Addr::new(addr0).wrapping_add(Self::Addr::one()).value();
+                let addr2 = $crate::mem::Addr::new(addr1).wrapping_add(Self::Addr::one()).value();
+                let addr3 = $crate::mem::Addr::new(addr2).wrapping_add(Self::Addr::one()).value();
                 self.write_byte(addr0, bytes.0);
                 self.write_byte(addr1, bytes.1);
                 self.write_byte(addr2, bytes.2);
@@ -71,28 +107,34 @@ macro_rules! impl_mem_32_little {
             }
         }
     };
+    {$struct:ident} => {
+        impl_mem_16_little!{ $struct }
+        impl_mem_32_little!{ @impl $struct }
+    };
+    {$struct:ident, $field:ident} => {
+        impl_mem_16_little!{ $struct, $field }
+        impl_mem_32_little!{ @impl $struct }
+    };
 }
 
 /// This provides default implementations for the `Mem64`, `Mem32` and `Mem16` traits, however they are not very optimal
 /// and you might get better performance from implementing them yourself.
 #[macro_export]
 macro_rules! impl_mem_64_little {
-    {$struct:ident} => {
-        impl_mem_32_little!{ $struct }
-
+    {@impl $struct:ident} => {
         impl Mem64 for $struct {
             fn read_doubleword(&mut self, addr: Self::Addr) -> u64 {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
                 let addr0 = addr;
-                let addr1 = addr0 + Self::Addr::one();
-                let addr2 = addr1 + Self::Addr::one();
-                let addr3 = addr2 + Self::Addr::one();
-                let addr4 = addr3 + Self::Addr::one();
-                let addr5 = addr4 + Self::Addr::one();
-                let addr6 = addr5 + Self::Addr::one();
-                let addr7 = addr6 + Self::Addr::one();
+                let addr1 = $crate::mem::Addr::new(addr0).wrapping_add(Self::Addr::one()).value();
+                let addr2 = $crate::mem::Addr::new(addr1).wrapping_add(Self::Addr::one()).value();
+                let addr3 = $crate::mem::Addr::new(addr2).wrapping_add(Self::Addr::one()).value();
+                let addr4 = $crate::mem::Addr::new(addr3).wrapping_add(Self::Addr::one()).value();
+                let addr5 = $crate::mem::Addr::new(addr4).wrapping_add(Self::Addr::one()).value();
+                let addr6 = $crate::mem::Addr::new(addr5).wrapping_add(Self::Addr::one()).value();
+                let addr7 = $crate::mem::Addr::new(addr6).wrapping_add(Self::Addr::one()).value();
                 let b0 = self.read_byte(addr0);
                 let b1 = self.read_byte(addr1);
                 let b2 = self.read_byte(addr2);
@@ -101,22 +143,30 @@ macro_rules! impl_mem_64_little {
                 let b5 = self.read_byte(addr5);
                 let b6 = self.read_byte(addr6);
                 let b7 = self.read_byte(addr7);
-                make64(b0, b1, b2, b3, b4, b5, b6, b7)
+                if self.little_endian() {
+                    make64(b0, b1, b2, b3, b4, b5, b6, b7)
+                } else {
+                    make64_be(b0, b1, b2, b3, b4, b5, b6, b7)
+                }
             }
 
             fn write_doubleword(&mut self, addr: Self::Addr, data: u64) {
                 use num_traits::identities::One;
-                use crate::common::*;
+                use $crate::common::*;
 
-                let bytes = bytes64(data);
+                let bytes = if self.little_endian() {
+                    bytes64(data)
+                } else {
+                    bytes64_be(data)
+                };
                 let addr0 = addr;
-                let addr1 = addr0 + Self::Addr::one();
-                let addr2 = addr1 + Self::Addr::one();
-                let addr3 = addr2 + Self::Addr::one();
-                let addr4 = addr3 + Self::Addr::one();
-                let addr5 = addr4 + Self::Addr::one();
-                let addr6 = addr5 + Self::Addr::one();
-                let addr7 = addr6 + Self::Addr::one();
+                let addr1 = $crate::mem::Addr::new(addr0).wrapping_add(Self::Addr::one()).value();
+                let addr2 = $crate::mem::Addr::new(addr1).wrapping_add(Self::Addr::one()).value();
+                let addr3 = $crate::mem::Addr::new(addr2).wrapping_add(Self::Addr::one()).value();
+                let addr4 = $crate::mem::Addr::new(addr3).wrapping_add(Self::Addr::one()).value();
+                let addr5 = $crate::mem::Addr::new(addr4).wrapping_add(Self::Addr::one()).value();
+                let addr6 = $crate::mem::Addr::new(addr5).wrapping_add(Self::Addr::one()).value();
+                let addr7 = $crate::mem::Addr::new(addr6).wrapping_add(Self::Addr::one()).value();
                 self.write_byte(addr0, bytes.0);
                 self.write_byte(addr1, bytes.1);
                 self.write_byte(addr2, bytes.2);
@@ -128,4 +178,12 @@ macro_rules! impl_mem_64_little {
             }
         }
     };
+    {$struct:ident} => {
+        impl_mem_32_little!{ $struct }
+        impl_mem_64_little!{ @impl $struct }
+    };
+    {$struct:ident, $field:ident} => {
+        impl_mem_32_little!{ $struct, $field }
+        impl_mem_64_little!{ @impl $struct }
+    };
 }
@@ -0,0 +1,263 @@
+// A memory wrapper that logs recent accesses.
+
+use alloc::vec::Vec;
+
+use super::*;
+
+/// The direction of a logged memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    /// An instruction fetch.
+    Fetch
+}
+
+/// A single logged memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemAccess {
+    pub kind:       AccessKind,
+    pub addr:       u32,
+    /// The size of the access in bytes.
+    pub size:       u8,
+    /// The value read or written. Failed reads log 0.
+    pub value:      u32,
+    /// If the access caused a bus error.
+    pub bus_error:  bool,
+}
+
+/// Memory that records the most recent accesses made to it.
+///
+/// This wraps any 32-bit memory and forwards all calls to it.
+/// Only the last `capacity` accesses are kept.
+///
+/// The inner memory's byte slice is not exposed,
+/// so that instruction fetches go through the wrapper and are logged.
+pub struct LoggedMem<M: Mem32<Addr = u32>> {
+    mem:        M,
+    capacity:   usize,
+    // Holds up to twice the capacity, so that the most recent accesses can
+    // be returned as one slice without shifting on every access.
+    log:        Vec<MemAccess>,
+}
+
+impl<M: Mem32<Addr = u32>> LoggedMem<M> {
+    /// Wrap memory, keeping the last `capacity` accesses.
+    pub fn new(mem: M, capacity: usize) -> Self {
+        Self {
+            mem,
+            capacity,
+            log:        Vec::with_capacity(capacity * 2),
+        }
+    }
+
+    /// Get the most recent accesses, oldest first.
+    pub fn recent(&self) -> &[MemAccess] {
+        let start = self.log.len().saturating_sub(self.capacity);
+        &self.log[start..]
+    }
+
+    /// Clear the log.
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    /// Get the inner memory.
+    pub fn inner(&mut self) -> &mut M {
+        &mut self.mem
+    }
+
+    /// Unwrap the memory, discarding the log.
+    pub fn into_inner(self) -> M {
+        self.mem
+    }
+
+    fn record(&mut self, kind: AccessKind, addr: u32, size: u8, value: u32, bus_error: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.log.len() == self.capacity * 2 {
+            self.log.drain(..self.capacity);
+        }
+        self.log.push(MemAccess {
+            kind,
+            addr,
+            size,
+            value,
+            bus_error,
+        });
+    }
+
+    fn record_read<T: Copy + Into<u32>>(&mut self, kind: AccessKind, addr: u32, size: u8, result: Result<T, MemError>) -> Result<T, MemError> {
+        match result {
+            Ok(data) => self.record(kind, addr, size, data.into(), false),
+            Err(_) => self.record(kind, addr, size, 0, true),
+        }
+        result
+    }
+
+    fn record_write(&mut self, addr: u32, size: u8, data: u32, result: Result<(), MemError>) -> Result<(), MemError> {
+        self.record(AccessKind::Write, addr, size, data, result.is_err());
+        result
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Memory for LoggedMem<M> {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        let data = self.mem.read_byte(addr);
+        self.record(AccessKind::Read, addr, 1, data.into(), false);
+        data
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.mem.write_byte(addr, data);
+        self.record(AccessKind::Write, addr, 1, data.into(), false);
+    }
+
+    fn access_cycles(&self, addr: Self::Addr, is_write: bool) -> u32 {
+        self.mem.access_cycles(addr, is_write)
+    }
+
+    fn on_sync(&mut self) {
+        self.mem.on_sync();
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        self.mem.on_prefetch(addr);
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        self.mem.on_cache(op, addr);
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        self.mem.on_interrupt_acknowledged(line);
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        let result = self.mem.try_read_byte(addr);
+        self.record_read(AccessKind::Read, addr, 1, result)
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        let result = self.mem.try_write_byte(addr, data);
+        self.record_write(addr, 1, data.into(), result)
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem16 for LoggedMem<M> {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        let data = self.mem.read_halfword(addr);
+        self.record(AccessKind::Read, addr, 2, data.into(), false);
+        data
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.mem.write_halfword(addr, data);
+        self.record(AccessKind::Write, addr, 2, data.into(), false);
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        let result = self.mem.try_read_halfword(addr);
+        self.record_read(AccessKind::Read, addr, 2, result)
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        let result = self.mem.try_write_halfword(addr, data);
+        self.record_write(addr, 2, data.into(), result)
+    }
+
+    fn little_endian(&self) -> bool {
+        self.mem.little_endian()
+    }
+
+    fn set_endianness(&mut self, little_endian: bool) {
+        self.mem.set_endianness(little_endian);
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem32 for LoggedMem<M> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        let data = self.mem.read_word(addr);
+        self.record(AccessKind::Read, addr, 4, data, false);
+        data
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.mem.write_word(addr, data);
+        self.record(AccessKind::Write, addr, 4, data, false);
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        let result = self.mem.try_read_word(addr);
+        self.record_read(AccessKind::Read, addr, 4, result)
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        let result = self.mem.try_write_word(addr, data);
+        self.record_write(addr, 4, data, result)
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        let result = self.mem.fetch_word(addr);
+        self.record_read(AccessKind::Fetch, addr, 4, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(addr: u32, size: u8, value: u32) -> MemAccess {
+        MemAccess { kind: AccessKind::Read, addr, size, value, bus_error: false }
+    }
+
+    fn write(addr: u32, size: u8, value: u32) -> MemAccess {
+        MemAccess { kind: AccessKind::Write, addr, size, value, bus_error: false }
+    }
+
+    #[test]
+    fn recent() {
+        let mut mem = LoggedMem::new(RamMemory::<0x100>::new(), 3);
+        assert!(mem.recent().is_empty());
+
+        mem.write_word(0x10, 0x1234_5678);
+        mem.write_halfword(0x20, 0xABCD);
+        assert_eq!(mem.recent(), [write(0x10, 4, 0x1234_5678), write(0x20, 2, 0xABCD)]);
+
+        mem.read_byte(0x10);
+        mem.read_word(0x10);
+        mem.try_read_halfword(0x20).unwrap();
+        assert_eq!(mem.recent(), [read(0x10, 1, 0x78), read(0x10, 4, 0x1234_5678), read(0x20, 2, 0xABCD)]);
+
+        // Keep going past the point where the buffer is compacted.
+        for i in 0..10 {
+            mem.write_byte(i, i as u8);
+        }
+        mem.fetch_word(0).unwrap();
+        assert_eq!(mem.recent(), [
+            write(8, 1, 8),
+            write(9, 1, 9),
+            MemAccess { kind: AccessKind::Fetch, addr: 0, size: 4, value: 0x0302_0100, bus_error: false },
+        ]);
+
+        mem.clear();
+        assert!(mem.recent().is_empty());
+    }
+
+    #[test]
+    fn bus_error() {
+        let mut bus = Bus::new();
+        bus.map_device("ram", 0..0x100, RamMemory::<0x100>::new()).unwrap();
+        let mut mem = LoggedMem::new(bus, 4);
+
+        assert_eq!(mem.try_read_word(0x1000), Err(MemError::BusError));
+        assert_eq!(mem.try_write_byte(0x1000, 5), Err(MemError::BusError));
+        assert_eq!(mem.recent(), [
+            MemAccess { kind: AccessKind::Read, addr: 0x1000, size: 4, value: 0, bus_error: true },
+            MemAccess { kind: AccessKind::Write, addr: 0x1000, size: 1, value: 5, bus_error: true },
+        ]);
+    }
+}
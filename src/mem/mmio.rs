@@ -0,0 +1,145 @@
+// Memory-mapped IO registers.
+
+use super::*;
+
+/// A device made of 32-bit registers.
+///
+/// Each register is read or written as a whole, so reads and writes can have side effects.
+pub trait Registers {
+    /// Read the register at `offset`, in bytes from the start of the device.
+    fn read_register(&mut self, offset: u32) -> u32;
+
+    /// Write the register at `offset`, in bytes from the start of the device.
+    fn write_register(&mut self, offset: u32, data: u32);
+}
+
+/// Memory that maps word accesses directly onto device registers.
+///
+/// Unlike memory implemented with `impl_mem_32_little`, a word access is a single
+/// register access here, rather than four byte accesses.
+/// Halfword and byte accesses are bus errors: the `try_*` methods report them,
+/// other reads return 0 and other writes are discarded.
+pub struct MmioDevice<D: Registers> {
+    device: D,
+}
+
+impl<D: Registers> MmioDevice<D> {
+    /// Wrap a device.
+    pub fn new(device: D) -> Self {
+        Self {
+            device
+        }
+    }
+
+    /// Get the device.
+    pub fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Registers> Memory for MmioDevice<D> {
+    type Addr = u32;
+
+    fn read_byte(&mut self, _addr: Self::Addr) -> u8 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: Self::Addr, _data: u8) {}
+
+    fn try_read_byte(&mut self, _addr: Self::Addr) -> Result<u8, MemError> {
+        Err(MemError::BusError)
+    }
+
+    fn try_write_byte(&mut self, _addr: Self::Addr, _data: u8) -> Result<(), MemError> {
+        Err(MemError::BusError)
+    }
+}
+
+impl<D: Registers> Mem16 for MmioDevice<D> {
+    fn read_halfword(&mut self, _addr: Self::Addr) -> u16 {
+        0
+    }
+
+    fn write_halfword(&mut self, _addr: Self::Addr, _data: u16) {}
+
+    fn try_read_halfword(&mut self, _addr: Self::Addr) -> Result<u16, MemError> {
+        Err(MemError::BusError)
+    }
+
+    fn try_write_halfword(&mut self, _addr: Self::Addr, _data: u16) -> Result<(), MemError> {
+        Err(MemError::BusError)
+    }
+
+    fn little_endian(&self) -> bool {
+        true
+    }
+}
+
+impl<D: Registers> Mem32 for MmioDevice<D> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.device.read_register(addr)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.device.write_register(addr, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A FIFO register at 0, which pops a value on each read, and a count of writes at 4.
+    #[derive(Default)]
+    struct Fifo {
+        next:   u32,
+        reads:  usize,
+        writes: u32,
+    }
+
+    impl Registers for Fifo {
+        fn read_register(&mut self, offset: u32) -> u32 {
+            self.reads += 1;
+            match offset {
+                0 => {
+                    self.next += 1;
+                    self.next
+                },
+                4 => self.writes,
+                _ => 0,
+            }
+        }
+
+        fn write_register(&mut self, _offset: u32, _data: u32) {
+            self.writes += 1;
+        }
+    }
+
+    #[test]
+    fn word_access() {
+        let mut mem = MmioDevice::new(Fifo::default());
+
+        assert_eq!(mem.read_word(0), 1);
+        assert_eq!(mem.try_read_word(0), Ok(2));
+        assert_eq!(mem.device().reads, 2);
+
+        mem.write_word(8, 0x1234_5678);
+        assert_eq!(mem.read_word(4), 1);
+        assert_eq!(mem.device().reads, 3);
+
+        assert_eq!(mem.try_read_byte(0), Err(MemError::BusError));
+        assert_eq!(mem.try_write_halfword(0, 0), Err(MemError::BusError));
+        assert_eq!(mem.device().reads, 3);
+        assert_eq!(mem.device().writes, 1);
+    }
+
+    #[test]
+    fn on_bus() {
+        let mut bus = Bus::new();
+        bus.map_device("fifo", 0x1F80_1000..0x1F80_1010, MmioDevice::new(Fifo::default())).unwrap();
+
+        assert_eq!(bus.read_word(0x1F80_1000), 1);
+        assert_eq!(bus.read_word(0x1F80_1000), 2);
+        assert_eq!(bus.try_read_byte(0x1F80_1000), Err(MemError::BusError));
+    }
+}
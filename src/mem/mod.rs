@@ -1,8 +1,38 @@
 /// Little-endian memory implementations.
 #[macro_use]
 mod little;
+/// Ready-made RAM implementations.
+mod ram;
+/// Memory bus with mapped devices.
+mod bus;
+/// Memory wrapper that logs recent accesses.
+mod logged;
+/// Memory-mapped IO registers.
+mod mmio;
+/// Memory shared between several CPUs.
+mod shared;
+/// Memory wrapper that models caches.
+mod cache;
+/// Console output through a memory-mapped register.
+mod console;
 
-use num_traits::sign::Unsigned;
+use num_traits::{
+    sign::Unsigned,
+    ops::wrapping::WrappingAdd
+};
+
+use crate::common::{
+    make32,
+    make32_be
+};
+
+pub use ram::*;
+pub use bus::*;
+pub use logged::*;
+pub use mmio::*;
+pub use shared::*;
+pub use cache::*;
+pub use console::*;
 
 #[derive(Default, Clone, Copy)]
 /// Address bus with variable width.
@@ -22,6 +52,27 @@ impl<Width: Unsigned> Addr<Width> {
             addr: self.addr + Width::one()
         }
     }
+
+    /// Get the address as a number.
+    pub fn value(self) -> Width {
+        self.addr
+    }
+}
+
+impl<Width: Unsigned + WrappingAdd> Addr<Width> {
+    /// Add an offset, wrapping around at the top of the address space.
+    pub fn wrapping_add(self, offset: Width) -> Self {
+        Addr {
+            addr: self.addr.wrapping_add(&offset)
+        }
+    }
+}
+
+/// An error from a memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemError {
+    /// Nothing responded at the address on the bus.
+    BusError
 }
 
 /// Base memory trait.
@@ -39,6 +90,64 @@ pub trait Memory {
 
     /// Write a single byte.
     fn write_byte(&mut self, addr: Self::Addr, data: u8);
+
+    /// Get the entire memory as a slice of bytes, indexed by address.
+    /// 
+    /// Memory backed by a plain buffer can provide this to speed up instruction fetches.
+    /// If it does, fetches read from the slice directly, bypassing `fetch_word`.
+    /// By default this returns `None`.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Get the number of cycles taken to access an address.
+    /// 
+    /// The CPU adds this to its cycle count for each instruction fetch, load and store.
+    /// Memory can use this to model wait states, such as slow ROM or MMIO.
+    /// By default every access takes 1 cycle.
+    fn access_cycles(&self, _addr: Self::Addr, _is_write: bool) -> u32 {
+        1
+    }
+
+    /// Called when a `SYNC` instruction is executed.
+    /// 
+    /// Memory that models ordering of loads and stores can use this to complete any pending accesses.
+    /// By default this does nothing.
+    fn on_sync(&mut self) {}
+
+    /// Called when a `PREF` instruction hints that an address will be accessed soon.
+    /// 
+    /// Memory that models caches can use this to fetch the data early.
+    /// By default this does nothing.
+    fn on_prefetch(&mut self, _addr: Self::Addr) {}
+
+    /// Called when a `CACHE` instruction performs operation `op` on the line containing an address.
+    /// 
+    /// `op` is the 5-bit operation field, which selects the cache and what to do with the line.
+    /// Memory that models caches can use this to invalidate or write back lines.
+    /// By default this does nothing.
+    fn on_cache(&mut self, _op: u32, _addr: Self::Addr) {}
+
+    /// Called when the CPU takes an interrupt for the line given, and jumps to the handler.
+    /// 
+    /// Peripherals can use this to deassert their interrupt.
+    /// By default this does nothing.
+    fn on_interrupt_acknowledged(&mut self, _line: usize) {}
+
+    /// Read a single byte, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        Ok(self.read_byte(addr))
+    }
+
+    /// Write a single byte, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        self.write_byte(addr, data);
+        Ok(())
+    }
 }
 
 /// Memory with a 16-bit data bus.
@@ -58,16 +167,40 @@ pub trait Mem16: Memory {
     /// Unaligned writes are undefined, and might panic.
     fn write_halfword(&mut self, addr: Self::Addr, data: u16);
 
+    /// Read a 16-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        Ok(self.read_halfword(addr))
+    }
+
+    /// Write a 16-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        self.write_halfword(addr, data);
+        Ok(())
+    }
+
     /// Check the endianness of this memory.
     /// 
     /// If this returns `true`, the memory is little-endian.
     /// If this returns `false`, the memory is big-endian.
     fn little_endian(&self) -> bool;
+
+    /// Set the endianness of this memory.
+    /// 
+    /// If `little_endian` is `true`, the memory becomes little-endian, otherwise it becomes big-endian.
+    /// Memory with a fixed byte order can ignore this, which is the default behaviour.
+    fn set_endianness(&mut self, _little_endian: bool) {}
 }
 
 /// Memory with a 32-bit data bus.
 /// 
 /// For default impls, see `impl_mem_32_little`.
+/// These build each word from four byte accesses, which suits RAM.
+/// Devices where a word access must be a single operation, such as IO registers,
+/// should implement the word methods directly instead: see `MmioDevice`.
 pub trait Mem32: Mem16 {
 
     /// Read a 32-bit value.
@@ -81,6 +214,29 @@ pub trait Mem32: Mem16 {
     /// Writes to this can be expected to be aligned (the bottom 2 addr bits should be 0).
     /// Unaligned writes are undefined, and might panic.
     fn write_word(&mut self, addr: Self::Addr, data: u32);
+
+    /// Read a 32-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        Ok(self.read_word(addr))
+    }
+
+    /// Write a 32-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        self.write_word(addr, data);
+        Ok(())
+    }
+
+    /// Fetch a 32-bit instruction, or report a bus error.
+    /// 
+    /// Memory with a separate instruction bus, or that treats fetches differently to data reads, can override this.
+    /// By default this is the same as `try_read_word`.
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.try_read_word(addr)
+    }
 }
 
 /// Memory with a 64-bit data bus.
@@ -99,6 +255,54 @@ pub trait Mem64: Mem32 {
     /// Writes to this can be expected to be aligned (the bottom 3 addr bits should be 0).
     /// Unaligned writes are undefined, and might panic.
     fn write_doubleword(&mut self, addr: Self::Addr, data: u64);
+
+    /// Read a 64-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_read_doubleword(&mut self, addr: Self::Addr) -> Result<u64, MemError> {
+        Ok(self.read_doubleword(addr))
+    }
+
+    /// Write a 64-bit value, or report a bus error.
+    /// 
+    /// By default this always succeeds.
+    fn try_write_doubleword(&mut self, addr: Self::Addr, data: u64) -> Result<(), MemError> {
+        self.write_doubleword(addr, data);
+        Ok(())
+    }
+}
+
+/// Write a flat binary image into memory, starting at `addr`.
+/// 
+/// The bytes are written in memory order. Whole words are written at once where the address is aligned,
+/// and any remaining bytes are written one at a time.
+pub fn load_flat<M: Mem32<Addr = u32>>(mem: &mut M, addr: u32, bytes: &[u8]) {
+    let words = if (addr & 3) == 0 { bytes.len() / 4 } else { 0 };
+    let little_endian = mem.little_endian();
+    for (i, b) in bytes.chunks_exact(4).take(words).enumerate() {
+        let word = if little_endian {
+            make32(b[0], b[1], b[2], b[3])
+        } else {
+            make32_be(b[0], b[1], b[2], b[3])
+        };
+        mem.write_word(addr.wrapping_add((i * 4) as u32), word);
+    }
+    for (i, byte) in bytes.iter().enumerate().skip(words * 4) {
+        mem.write_byte(addr.wrapping_add(i as u32), *byte);
+    }
+}
+
+/// Read a word directly from the slice given by `as_bytes`, in the memory's byte order.
+/// 
+/// Returns `None` if the memory doesn't provide a slice, or the word isn't inside it.
+pub fn read_word_fast<M: Mem32>(mem: &M, addr: u32) -> Option<u32> {
+    let start = addr as usize;
+    let b = mem.as_bytes()?.get(start..start.wrapping_add(4))?;
+    Some(if mem.little_endian() {
+        make32(b[0], b[1], b[2], b[3])
+    } else {
+        make32_be(b[0], b[1], b[2], b[3])
+    })
 }
 
 #[cfg(test)]
@@ -131,6 +335,44 @@ mod tests {
 
     impl_mem_32_little!{ LittleMemTest }
 
+    struct SwitchableMemTest {
+        bytes:          Vec<u8>,
+        little_endian:  bool,
+    }
+
+    impl Memory for SwitchableMemTest {
+        type Addr = u32;
+
+        fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+            self.bytes[addr as usize]
+        }
+
+        fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+            self.bytes[addr as usize] = data;
+        }
+    }
+
+    impl_mem_64_little!{ SwitchableMemTest, little_endian }
+
+    // 256 bytes, mirrored across the address space.
+    struct MirroredMemTest {
+        bytes: [u8; 0x100]
+    }
+
+    impl Memory for MirroredMemTest {
+        type Addr = u32;
+
+        fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+            self.bytes[(addr & 0xFF) as usize]
+        }
+
+        fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+            self.bytes[(addr & 0xFF) as usize] = data;
+        }
+    }
+
+    impl_mem_64_little!{ MirroredMemTest }
+
     #[test]
     fn memory() {
         let mut mem = LittleMemTest::new(0x100);
@@ -142,4 +384,74 @@ mod tests {
 
         assert_eq!(mem.read_word(0), 0x78563412);
     }
+
+    #[test]
+    fn wrap_around() {
+        let mut mem = MirroredMemTest {
+            bytes: [0; 0x100]
+        };
+        mem.bytes[0xFE] = 0x12;
+        mem.bytes[0xFF] = 0x34;
+        mem.bytes[0x00] = 0x56;
+        mem.bytes[0x01] = 0x78;
+
+        assert_eq!(mem.read_word(0xFFFF_FFFE), 0x7856_3412);
+        assert_eq!(mem.read_halfword(0xFFFF_FFFF), 0x5634);
+
+        mem.write_doubleword(0xFFFF_FFFC, 0x0102_0304_0506_0708);
+        assert_eq!(mem.read_word(0), 0x0102_0304);
+        assert_eq!(mem.bytes[0xFC], 0x08);
+
+        let addr = Addr::<u32>::new(0xFFFF_FFFEu32).wrapping_add(4);
+        assert_eq!(addr.value(), 2);
+    }
+
+    #[test]
+    fn flat_binary() {
+        let bytes = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC];
+
+        let mut mem = SwitchableMemTest {
+            bytes:          vec![0; 0x100],
+            little_endian:  false,
+        };
+        load_flat(&mut mem, 0x10, &bytes);
+        assert_eq!(mem.read_word(0x10), 0x1234_5678);
+        assert_eq!(mem.bytes[0x10..0x17], [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0]);
+
+        // Unaligned loads are written byte-wise.
+        let mut mem = LittleMemTest::new(0x100);
+        load_flat(&mut mem, 0x21, &bytes);
+        assert_eq!(mem.bytes[0x20..0x28], [0, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0]);
+    }
+
+    #[test]
+    fn switch_endianness() {
+        let mut mem = SwitchableMemTest {
+            bytes:          vec![0; 0x100],
+            little_endian:  true,
+        };
+
+        mem.write_byte(0, 0x12);
+        mem.write_byte(1, 0x34);
+        mem.write_byte(2, 0x56);
+        mem.write_byte(3, 0x78);
+
+        assert!(mem.little_endian());
+        assert_eq!(mem.read_word(0), 0x78563412);
+        assert_eq!(mem.read_halfword(0), 0x3412);
+
+        mem.set_endianness(false);
+        assert!(!mem.little_endian());
+        assert_eq!(mem.read_word(0), 0x12345678);
+        assert_eq!(mem.read_halfword(0), 0x1234);
+
+        mem.write_doubleword(8, 0x0102_0304_0506_0708);
+        assert_eq!(mem.read_byte(8), 0x01);
+        assert_eq!(mem.read_word(8), 0x0102_0304);
+
+        // Fixed-endianness memory ignores the setter.
+        let mut mem = LittleMemTest::new(0x100);
+        mem.set_endianness(false);
+        assert!(mem.little_endian());
+    }
 }
@@ -0,0 +1,300 @@
+// Ready-made RAM implementations.
+
+use alloc::{vec, vec::Vec};
+
+use super::*;
+use crate::common::*;
+
+/// RAM with a fixed size known at compile time.
+pub type RamMemory<const N: usize> = Ram<[u8; N]>;
+
+/// RAM with a size chosen at runtime.
+pub type DynRam = Ram<Vec<u8>>;
+
+/// RAM backed by a byte slice owned elsewhere.
+///
+/// The host can share the slice with other tools, or map it from a file.
+/// Word accesses inside the slice read and write it directly.
+pub type SliceMem<'a> = Ram<&'a mut [u8]>;
+
+/// What a RAM does with an access outside of its backing storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutOfRange {
+    /// Reads return 0, writes are discarded.
+    #[default]
+    Ignore,
+    /// Panic.
+    Panic,
+    /// Wrap around to the start of the RAM, as if it is mirrored across the address space.
+    Mirror
+}
+
+/// What a RAM is filled with before it is first written.
+///
+/// A recognisable fill makes it easier to spot reads of memory the guest never initialised.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Fill {
+    /// Every byte is 0.
+    #[default]
+    Zero,
+    /// Every byte has the same value.
+    Byte(u8),
+    /// Each byte is given by a function of its address.
+    Pattern(fn(u32) -> u8)
+}
+
+/// Little-endian RAM with a 32-bit address bus.
+///
+/// Use `RamMemory` or `DynRam` to construct one.
+pub struct Ram<B: AsRef<[u8]> + AsMut<[u8]>> {
+    bytes:          B,
+    out_of_range:   OutOfRange,
+}
+
+impl<const N: usize> Ram<[u8; N]> {
+    /// Make a new zeroed RAM of `N` bytes.
+    pub fn new() -> Self {
+        Self {
+            bytes:          [0; N],
+            out_of_range:   OutOfRange::default(),
+        }
+    }
+}
+
+impl<const N: usize> Default for Ram<[u8; N]> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ram<Vec<u8>> {
+    /// Make a new zeroed RAM of `size` bytes.
+    pub fn new(size: usize) -> Self {
+        Self {
+            bytes:          vec![0; size],
+            out_of_range:   OutOfRange::default(),
+        }
+    }
+}
+
+impl<'a> Ram<&'a mut [u8]> {
+    /// Use `bytes` as RAM. Address 0 is the start of the slice.
+    pub fn new(bytes: &'a mut [u8]) -> Self {
+        Self {
+            bytes,
+            out_of_range:   OutOfRange::default(),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Ram<B> {
+    /// Fill the whole RAM.
+    ///
+    /// RAMs are zeroed when made. This overwrites everything, including the contents of a `SliceMem`.
+    pub fn with_fill(mut self, fill: Fill) -> Self {
+        let bytes = self.bytes.as_mut();
+        match fill {
+            Fill::Zero => bytes.fill(0),
+            Fill::Byte(val) => bytes.fill(val),
+            Fill::Pattern(f) => for (addr, byte) in bytes.iter_mut().enumerate() {
+                *byte = f(addr as u32);
+            },
+        }
+        self
+    }
+
+    /// Set the behaviour for accesses outside of the RAM.
+    ///
+    /// Defaults to `OutOfRange::Ignore`.
+    pub fn with_out_of_range(mut self, out_of_range: OutOfRange) -> Self {
+        self.out_of_range = out_of_range;
+        self
+    }
+
+    /// The size of the RAM in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.as_ref().len()
+    }
+
+    /// Check if the RAM has no storage at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Find the index of the byte at an address.
+    fn index(&self, addr: u32) -> Option<usize> {
+        let index = addr as usize;
+        if index < self.len() {
+            return Some(index);
+        }
+        match self.out_of_range {
+            OutOfRange::Ignore => None,
+            OutOfRange::Panic => panic!("RAM access at 0x{:08X} is out of range (size 0x{:X})", addr, self.len()),
+            OutOfRange::Mirror => index.checked_rem(self.len()),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Memory for Ram<B> {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        if let Some(index) = self.index(addr) {
+            self.bytes.as_ref()[index]
+        } else {
+            0
+        }
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        if let Some(index) = self.index(addr) {
+            self.bytes.as_mut()[index] = data;
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self.bytes.as_ref())
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Mem16 for Ram<B> {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        let lo = self.read_byte(addr);
+        let hi = self.read_byte(addr.wrapping_add(1));
+        make16(lo, hi)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.write_byte(addr, lo16(data));
+        self.write_byte(addr.wrapping_add(1), hi16(data));
+    }
+
+    fn little_endian(&self) -> bool {
+        true
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Mem32 for Ram<B> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        let start = addr as usize;
+        if let Some(b) = self.bytes.as_ref().get(start..start.wrapping_add(4)) {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            let b0 = self.read_byte(addr);
+            let b1 = self.read_byte(addr.wrapping_add(1));
+            let b2 = self.read_byte(addr.wrapping_add(2));
+            let b3 = self.read_byte(addr.wrapping_add(3));
+            make32(b0, b1, b2, b3)
+        }
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        let bytes = bytes32(data);
+        let start = addr as usize;
+        if let Some(b) = self.bytes.as_mut().get_mut(start..start.wrapping_add(4)) {
+            b.copy_from_slice(&data.to_le_bytes());
+        } else {
+            self.write_byte(addr, bytes.0);
+            self.write_byte(addr.wrapping_add(1), bytes.1);
+            self.write_byte(addr.wrapping_add(2), bytes.2);
+            self.write_byte(addr.wrapping_add(3), bytes.3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use crate::cpu::{
+        MIPSCore,
+        MIPSICore,
+        mips1::MIPSI
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut mem = RamMemory::<0x1000>::new();
+
+        mem.write_word(0x100, 0x1234_5678);
+        assert_eq!(mem.read_word(0x100), 0x1234_5678);
+        assert_eq!(mem.read_halfword(0x102), 0x1234);
+        assert_eq!(mem.read_byte(0x100), 0x78);
+
+        let mut mem = DynRam::new(0x1000);
+
+        mem.write_word(0x100, 0x1234_5678);
+        assert_eq!(mem.read_word(0x100), 0x1234_5678);
+    }
+
+    #[test]
+    fn slice() {
+        let mut bytes = [0; 0x100];
+        {
+            let mut mem = SliceMem::new(&mut bytes);
+            mem.write_word(0x0, 0x2401_0005);  // addiu $1, $0, 5
+            mem.write_word(0x4, 0x2422_0003);  // addiu $2, $1, 3
+            mem.write_word(0x8, 0xAC02_0080);  // sw $2, 0x80($0)
+
+            let mut cpu = MIPSI::<SliceMem>::with_memory(Box::new(mem)).build();
+            for _ in 0..3 {
+                cpu.step();
+            }
+            assert_eq!(cpu.read_gp(2), 8);
+        }
+        // The CPU wrote straight into the slice.
+        assert_eq!(bytes[0x80..0x84], [8, 0, 0, 0]);
+    }
+
+    #[test]
+    fn top_of_range() {
+        let mut mem = RamMemory::<0x1000>::new();
+
+        mem.write_word(0xFFC, 0xAABB_CCDD);
+        assert_eq!(mem.read_word(0xFFC), 0xAABB_CCDD);
+
+        // Only the bytes inside the RAM are kept.
+        mem.write_word(0xFFE, 0x1122_3344);
+        assert_eq!(mem.read_word(0xFFE), 0x0000_3344);
+        assert_eq!(mem.read_word(0x1000), 0);
+        assert_eq!(mem.read_word(0xFFFF_FFFE), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_range_panic() {
+        let mut mem = RamMemory::<0x1000>::new().with_out_of_range(OutOfRange::Panic);
+
+        mem.read_word(0x1000);
+    }
+
+    #[test]
+    fn fill() {
+        let mut mem = DynRam::new(0x1000).with_fill(Fill::Byte(0xCD));
+
+        assert_eq!(mem.read_word(0x100), 0xCDCD_CDCD);
+        mem.write_byte(0x101, 0);
+        assert_eq!(mem.read_word(0x100), 0xCDCD_00CD);
+
+        let mut mem = RamMemory::<0x1000>::new().with_fill(Fill::Pattern(|addr| (addr >> 2) as u8));
+
+        assert_eq!(mem.read_word(0x10), 0x0404_0404);
+        assert_eq!(mem.read_word(0xFFC), 0xFFFF_FFFF);
+
+        assert_eq!(mem.with_fill(Fill::Zero).read_word(0x10), 0);
+    }
+
+    #[test]
+    fn mirror() {
+        let mut mem = DynRam::new(0x1000).with_out_of_range(OutOfRange::Mirror);
+
+        mem.write_word(0x3010, 0x1234_5678);
+        assert_eq!(mem.read_word(0x10), 0x1234_5678);
+        assert_eq!(mem.read_word(0x8000_1010), 0x1234_5678);
+
+        // A word across the end of the RAM wraps to the start.
+        mem.write_word(0xFFE, 0xAABB_CCDD);
+        assert_eq!(mem.read_halfword(0x0), 0xAABB);
+        assert_eq!(mem.read_word(0x1FFE), 0xAABB_CCDD);
+    }
+}
@@ -0,0 +1,182 @@
+// Memory shared between several CPUs or devices.
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use super::*;
+
+/// A handle to memory that can be shared, for example between two CPUs.
+///
+/// Cloning the handle gives another handle to the same memory.
+/// Each access borrows the memory for the duration of that access only,
+/// so CPUs can be stepped in any order.
+///
+/// Accesses must not be reentrant: if a device behind the handle accesses the same
+/// shared memory while handling an access (or a hook such as `on_sync`), the borrow panics.
+/// Use `with` to reach the memory outside of a CPU, and don't step a CPU inside it.
+pub struct SharedMem<M: Memory> {
+    mem: Rc<RefCell<M>>,
+}
+
+impl<M: Memory> SharedMem<M> {
+    /// Wrap memory so it can be shared.
+    pub fn new(mem: M) -> Self {
+        Self {
+            mem: Rc::new(RefCell::new(mem)),
+        }
+    }
+
+    /// Access the memory directly.
+    pub fn with<T>(&self, f: impl FnOnce(&mut M) -> T) -> T {
+        f(&mut self.mem.borrow_mut())
+    }
+}
+
+impl<M: Memory> Clone for SharedMem<M> {
+    fn clone(&self) -> Self {
+        Self {
+            mem: self.mem.clone(),
+        }
+    }
+}
+
+impl<M: Memory> Memory for SharedMem<M> {
+    type Addr = M::Addr;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.mem.borrow_mut().read_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.mem.borrow_mut().write_byte(addr, data);
+    }
+
+    fn access_cycles(&self, addr: Self::Addr, is_write: bool) -> u32 {
+        self.mem.borrow().access_cycles(addr, is_write)
+    }
+
+    fn on_sync(&mut self) {
+        self.mem.borrow_mut().on_sync();
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        self.mem.borrow_mut().on_prefetch(addr);
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        self.mem.borrow_mut().on_cache(op, addr);
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        self.mem.borrow_mut().on_interrupt_acknowledged(line);
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        self.mem.borrow_mut().try_read_byte(addr)
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        self.mem.borrow_mut().try_write_byte(addr, data)
+    }
+}
+
+impl<M: Mem16> Mem16 for SharedMem<M> {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.mem.borrow_mut().read_halfword(addr)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.mem.borrow_mut().write_halfword(addr, data);
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        self.mem.borrow_mut().try_read_halfword(addr)
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        self.mem.borrow_mut().try_write_halfword(addr, data)
+    }
+
+    fn little_endian(&self) -> bool {
+        self.mem.borrow().little_endian()
+    }
+
+    fn set_endianness(&mut self, little_endian: bool) {
+        self.mem.borrow_mut().set_endianness(little_endian);
+    }
+}
+
+impl<M: Mem32> Mem32 for SharedMem<M> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.mem.borrow_mut().read_word(addr)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.mem.borrow_mut().write_word(addr, data);
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.mem.borrow_mut().try_read_word(addr)
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        self.mem.borrow_mut().try_write_word(addr, data)
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.mem.borrow_mut().fetch_word(addr)
+    }
+}
+
+impl<M: Mem64> Mem64 for SharedMem<M> {
+    fn read_doubleword(&mut self, addr: Self::Addr) -> u64 {
+        self.mem.borrow_mut().read_doubleword(addr)
+    }
+
+    fn write_doubleword(&mut self, addr: Self::Addr, data: u64) {
+        self.mem.borrow_mut().write_doubleword(addr, data);
+    }
+
+    fn try_read_doubleword(&mut self, addr: Self::Addr) -> Result<u64, MemError> {
+        self.mem.borrow_mut().try_read_doubleword(addr)
+    }
+
+    fn try_write_doubleword(&mut self, addr: Self::Addr, data: u64) -> Result<(), MemError> {
+        self.mem.borrow_mut().try_write_doubleword(addr, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use crate::cpu::{MIPSCore, MIPSICore};
+    use crate::cpu::mips1::MIPSI;
+
+    #[test]
+    fn two_cpus() {
+        let mem = SharedMem::new(RamMemory::<0x1000>::new());
+        mem.with(|ram| {
+            // ori $1, $0, 0x1234; sw $1, 0x800($0)
+            ram.write_word(0x000, 0x3401_1234);
+            ram.write_word(0x004, 0xAC01_0800);
+            // lw $2, 0x800($0); nop
+            ram.write_word(0x100, 0x8C02_0800);
+        });
+
+        let mut writer = MIPSI::<SharedMem<RamMemory<0x1000>>>::with_memory(Box::new(mem.clone())).build();
+        let mut reader = MIPSI::<SharedMem<RamMemory<0x1000>>>::with_memory(Box::new(mem.clone()))
+            .with_entry(0x100)
+            .build();
+
+        writer.step();
+        writer.step();
+        reader.step();
+        reader.step();
+        assert_eq!(reader.read_gp(2), 0x1234);
+
+        // Writes from outside are seen by both.
+        mem.with(|ram| ram.write_word(0x800, 0x5678));
+        assert_eq!(writer.mem().read_word(0x800), 0x5678);
+    }
+}
@@ -0,0 +1,450 @@
+// Recording and replaying external inputs, for deterministic debugging.
+
+use alloc::{
+    collections::VecDeque,
+    vec::Vec
+};
+use core::ops::Range;
+
+use crate::cpu::{
+    MIPSCore,
+    MIPSICore,
+    StepResult
+};
+use crate::mem::*;
+
+/// An input from outside the CPU, that can't be reproduced by executing again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// An interrupt line was asserted or deasserted.
+    Interrupt {
+        line:       usize,
+        asserted:   bool,
+    },
+    /// A read from a recorded range, and what it returned.
+    /// Byte and halfword reads are zero-extended.
+    Read {
+        addr:       u32,
+        value:      Result<u32, MemError>,
+    },
+}
+
+/// An input, and the step it arrived on.
+///
+/// Interrupts are injected before the step runs, reads happen during it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub step:   u64,
+    pub input:  Input,
+}
+
+/// Memory that records reads from a range of addresses, or plays them back.
+///
+/// Wrap the memory of a CPU in this to use a `Recorder` or `Player`.
+/// The range should cover any devices whose reads can't be reproduced,
+/// such as MMIO registers fed from the host.
+///
+/// When playing back, reads in the range return the recorded values without
+/// reaching the inner memory. Writes and instruction fetches always go through.
+pub struct ReplayMem<M: Mem32<Addr = u32>> {
+    mem:        M,
+    range:      Range<u32>,
+    playing:    bool,
+    // Reads made on this step when recording, or still to be made when playing.
+    reads:      VecDeque<(u32, Result<u32, MemError>)>,
+    diverged:   bool,
+}
+
+impl<M: Mem32<Addr = u32>> ReplayMem<M> {
+    /// Wrap memory, recording reads in `range`.
+    pub fn new(mem: M, range: Range<u32>) -> Self {
+        Self {
+            mem,
+            range,
+            playing:    false,
+            reads:      VecDeque::new(),
+            diverged:   false,
+        }
+    }
+
+    /// Check if a read was made during playback that differs from the recording.
+    ///
+    /// After this, execution is no longer following the recording.
+    /// Reads that don't match go to the inner memory.
+    pub fn diverged(&self) -> bool {
+        self.diverged
+    }
+
+    /// Get the inner memory.
+    pub fn inner(&mut self) -> &mut M {
+        &mut self.mem
+    }
+
+    /// Unwrap the memory.
+    pub fn into_inner(self) -> M {
+        self.mem
+    }
+
+    fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+        self.reads.clear();
+        self.diverged = false;
+    }
+
+    fn read<T: Copy + Into<u32>>(&mut self, addr: u32, truncate: fn(u32) -> T, read: impl FnOnce(&mut M) -> Result<T, MemError>) -> Result<T, MemError> {
+        if !self.range.contains(&addr) {
+            read(&mut self.mem)
+        } else if self.playing {
+            match self.reads.pop_front() {
+                Some((read_addr, value)) if read_addr == addr => value.map(truncate),
+                _ => {
+                    self.diverged = true;
+                    read(&mut self.mem)
+                }
+            }
+        } else {
+            let result = read(&mut self.mem);
+            self.reads.push_back((addr, result.map(Into::into)));
+            result
+        }
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Memory for ReplayMem<M> {
+    type Addr = u32;
+
+    fn read_byte(&mut self, addr: Self::Addr) -> u8 {
+        self.read(addr, |data| data as u8, |mem| Ok(mem.read_byte(addr))).unwrap_or(0)
+    }
+
+    fn write_byte(&mut self, addr: Self::Addr, data: u8) {
+        self.mem.write_byte(addr, data);
+    }
+
+    fn access_cycles(&self, addr: Self::Addr, is_write: bool) -> u32 {
+        self.mem.access_cycles(addr, is_write)
+    }
+
+    fn on_sync(&mut self) {
+        self.mem.on_sync();
+    }
+
+    fn on_prefetch(&mut self, addr: Self::Addr) {
+        self.mem.on_prefetch(addr);
+    }
+
+    fn on_cache(&mut self, op: u32, addr: Self::Addr) {
+        self.mem.on_cache(op, addr);
+    }
+
+    fn on_interrupt_acknowledged(&mut self, line: usize) {
+        self.mem.on_interrupt_acknowledged(line);
+    }
+
+    fn try_read_byte(&mut self, addr: Self::Addr) -> Result<u8, MemError> {
+        self.read(addr, |data| data as u8, |mem| mem.try_read_byte(addr))
+    }
+
+    fn try_write_byte(&mut self, addr: Self::Addr, data: u8) -> Result<(), MemError> {
+        self.mem.try_write_byte(addr, data)
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem16 for ReplayMem<M> {
+    fn read_halfword(&mut self, addr: Self::Addr) -> u16 {
+        self.read(addr, |data| data as u16, |mem| Ok(mem.read_halfword(addr))).unwrap_or(0)
+    }
+
+    fn write_halfword(&mut self, addr: Self::Addr, data: u16) {
+        self.mem.write_halfword(addr, data);
+    }
+
+    fn try_read_halfword(&mut self, addr: Self::Addr) -> Result<u16, MemError> {
+        self.read(addr, |data| data as u16, |mem| mem.try_read_halfword(addr))
+    }
+
+    fn try_write_halfword(&mut self, addr: Self::Addr, data: u16) -> Result<(), MemError> {
+        self.mem.try_write_halfword(addr, data)
+    }
+
+    fn little_endian(&self) -> bool {
+        self.mem.little_endian()
+    }
+
+    fn set_endianness(&mut self, little_endian: bool) {
+        self.mem.set_endianness(little_endian);
+    }
+}
+
+impl<M: Mem32<Addr = u32>> Mem32 for ReplayMem<M> {
+    fn read_word(&mut self, addr: Self::Addr) -> u32 {
+        self.read(addr, |data| data, |mem| Ok(mem.read_word(addr))).unwrap_or(0)
+    }
+
+    fn write_word(&mut self, addr: Self::Addr, data: u32) {
+        self.mem.write_word(addr, data);
+    }
+
+    fn try_read_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.read(addr, |data| data, |mem| mem.try_read_word(addr))
+    }
+
+    fn try_write_word(&mut self, addr: Self::Addr, data: u32) -> Result<(), MemError> {
+        self.mem.try_write_word(addr, data)
+    }
+
+    fn fetch_word(&mut self, addr: Self::Addr) -> Result<u32, MemError> {
+        self.mem.fetch_word(addr)
+    }
+}
+
+/// Runs a CPU and records its external inputs.
+///
+/// Raise and clear interrupts through the recorder rather than the CPU, so they are logged.
+/// Reads are logged by the CPU's `ReplayMem`.
+pub struct Recorder<C> {
+    cpu:    C,
+    steps:  u64,
+    log:    Vec<Event>,
+}
+
+impl<M, C> Recorder<C>
+    where M: Mem32<Addr = u32>, C: MIPSCore + MIPSICore<Mem = ReplayMem<M>>
+{
+    /// Start recording a CPU.
+    pub fn new(mut cpu: C) -> Self {
+        cpu.mem().set_playing(false);
+        Self {
+            cpu,
+            steps:  0,
+            log:    Vec::new(),
+        }
+    }
+
+    /// Get the CPU.
+    pub fn cpu(&mut self) -> &mut C {
+        &mut self.cpu
+    }
+
+    /// Get the number of steps recorded.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Get the events recorded so far.
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    /// Step the CPU, logging any reads it makes.
+    pub fn step(&mut self) -> StepResult {
+        let result = self.cpu.step();
+        let step = self.steps;
+        let reads = self.cpu.mem().reads.drain(..).map(|(addr, value)| Event {
+            step,
+            input:  Input::Read { addr, value },
+        });
+        self.log.extend(reads);
+        self.steps += 1;
+        result
+    }
+
+    /// Raise an interrupt before the next step.
+    pub fn raise_interrupt(&mut self, line: usize) {
+        self.record(Input::Interrupt { line, asserted: true });
+        self.cpu.raise_interrupt(line);
+    }
+
+    /// Clear an interrupt before the next step.
+    pub fn clear_interrupt(&mut self, line: usize) {
+        self.record(Input::Interrupt { line, asserted: false });
+        self.cpu.clear_interrupt(line);
+    }
+
+    /// Stop recording, returning the CPU and the log.
+    pub fn finish(self) -> (C, Vec<Event>) {
+        (self.cpu, self.log)
+    }
+
+    fn record(&mut self, input: Input) {
+        self.log.push(Event {
+            step:   self.steps,
+            input,
+        });
+    }
+}
+
+/// Runs a CPU, feeding it inputs recorded by a `Recorder`.
+///
+/// The CPU should be restored to the state it was in when recording began,
+/// including its memory and coprocessors.
+pub struct Player<C> {
+    cpu:    C,
+    steps:  u64,
+    log:    Vec<Event>,
+    next:   usize,
+}
+
+impl<M, C> Player<C>
+    where M: Mem32<Addr = u32>, C: MIPSCore + MIPSICore<Mem = ReplayMem<M>>
+{
+    /// Start playing back `log` on a CPU.
+    pub fn new(mut cpu: C, log: Vec<Event>) -> Self {
+        cpu.mem().set_playing(true);
+        Self {
+            cpu,
+            steps:  0,
+            log,
+            next:   0,
+        }
+    }
+
+    /// Get the CPU.
+    pub fn cpu(&mut self) -> &mut C {
+        &mut self.cpu
+    }
+
+    /// Get the number of steps played.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Check if every event in the log has been played.
+    pub fn finished(&mut self) -> bool {
+        self.next == self.log.len() && self.cpu.mem().reads.is_empty()
+    }
+
+    /// Check if the CPU has stopped following the recording.
+    pub fn diverged(&mut self) -> bool {
+        self.cpu.mem().diverged()
+    }
+
+    /// Step the CPU, after injecting the inputs recorded for this step.
+    pub fn step(&mut self) -> StepResult {
+        while let Some(event) = self.log.get(self.next).filter(|event| event.step == self.steps) {
+            match event.input {
+                Input::Interrupt { line, asserted: true } => self.cpu.raise_interrupt(line),
+                Input::Interrupt { line, asserted: false } => self.cpu.clear_interrupt(line),
+                Input::Read { addr, value } => self.cpu.mem().reads.push_back((addr, value)),
+            }
+            self.next += 1;
+        }
+        let result = self.cpu.step();
+        self.steps += 1;
+        result
+    }
+
+    /// Stop playing, returning the CPU.
+    pub fn into_cpu(self) -> C {
+        self.cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use crate::coproc::*;
+    use crate::cpu::mips1::MIPSI;
+
+    // A register which counts up on each read.
+    struct Counter(u32);
+
+    impl Registers for Counter {
+        fn read_register(&mut self, _offset: u32) -> u32 {
+            self.0 += 1;
+            self.0
+        }
+
+        fn write_register(&mut self, _offset: u32, _data: u32) {}
+    }
+
+    type Cpu = MIPSI<ReplayMem<Bus>, StandardCoproc0>;
+
+    fn make_cpu(counter: u32) -> Cpu {
+        let mut bus = Bus::new();
+        bus.map_device("ram", 0..0x1000, RamMemory::<0x1000>::new()).unwrap();
+        bus.map_device("counter", 0x1000..0x1010, MmioDevice::new(Counter(counter))).unwrap();
+
+        let mut cpu = MIPSI::<ReplayMem<Bus>>::with_memory(Box::new(ReplayMem::new(bus, 0x1000..0x1010)))
+            .add_coproc0(StandardCoproc0::new())
+            .with_exception_vectoring()
+            .with_entry(0x8000_0200)
+            .build();
+
+        // Interrupt handler: count interrupts in $11 and return.
+        let handler = [
+            0x256B_0001,    // addiu $11, $11, 1
+            0x401A_7000,    // mfc0 $26, EPC
+            0x0340_0008,    // jr $26
+            0x4200_0010,    // rfe
+        ];
+        // Sum reads of the counter into $10.
+        let main = [
+            0x3C08_8000,    // lui $8, 0x8000
+            0x3508_1000,    // ori $8, $8, 0x1000
+            0x8D09_0000,    // lw $9, 0($8)
+            0x0149_5021,    // addu $10, $10, $9
+            0x0800_0082,    // j 0x80000208
+            0x0000_0000,    // nop
+        ];
+        for (i, instr) in handler.iter().enumerate() {
+            cpu.mem().write_word(0x80 + (i as u32) * 4, *instr);
+        }
+        for (i, instr) in main.iter().enumerate() {
+            cpu.mem().write_word(0x200 + (i as u32) * 4, *instr);
+        }
+        cpu.set_status(STATUS_IEC | STATUS_IM);
+        cpu
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let cpu = make_cpu(0);
+        let start = cpu.save_state();
+
+        let mut recorder = Recorder::new(cpu);
+        for step in 0..50 {
+            match step {
+                20 => recorder.raise_interrupt(2),
+                22 => recorder.clear_interrupt(2),
+                _ => {},
+            }
+            assert_eq!(recorder.step(), StepResult::Executed);
+        }
+        assert!(recorder.log().contains(&Event { step: 20, input: Input::Interrupt { line: 2, asserted: true } }));
+        assert!(recorder.log().contains(&Event { step: 2, input: Input::Read { addr: 0x1000, value: Ok(1) } }));
+        let (cpu, log) = recorder.finish();
+        assert_eq!(cpu.read_gp(11), 1);
+        let end = cpu.save_state();
+
+        // The counter starts somewhere else, but the recorded reads are used instead.
+        let mut cpu = make_cpu(1000);
+        cpu.load_state(&start);
+        let mut player = Player::new(cpu, log);
+        for _ in 0..50 {
+            assert_eq!(player.step(), StepResult::Executed);
+        }
+        assert!(player.finished());
+        assert!(!player.diverged());
+        assert_eq!(player.into_cpu().save_state(), end);
+    }
+
+    #[test]
+    fn diverge() {
+        let mut recorder = Recorder::new(make_cpu(0));
+        for _ in 0..10 {
+            recorder.step();
+        }
+        let (_, log) = recorder.finish();
+
+        // Reading from a different register than the recording did.
+        let mut cpu = make_cpu(0);
+        cpu.mem().write_word(0x208, 0x8D09_0004);   // lw $9, 4($8)
+        let mut player = Player::new(cpu, log);
+        for _ in 0..10 {
+            player.step();
+        }
+        assert!(player.diverged());
+    }
+}
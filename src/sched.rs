@@ -0,0 +1,135 @@
+// Interleaving CPU steps with periodic device updates.
+
+use alloc::{
+    boxed::Box,
+    vec::Vec
+};
+
+use crate::cpu::{
+    MIPSCore,
+    StepResult
+};
+
+struct Event<'a> {
+    period:     u64,
+    next:       u64,
+    callback:   Box<dyn FnMut() + 'a>,
+}
+
+/// Runs a CPU, and calls back periodically to update other devices.
+///
+/// Time is measured in steps of the CPU, so the system runs deterministically.
+pub struct Scheduler<'a> {
+    events:     Vec<Event<'a>>,
+    steps:      u64,
+}
+
+impl<'a> Scheduler<'a> {
+    /// Make a new scheduler with no callbacks.
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            steps:  0,
+        }
+    }
+
+    /// Call `callback` every `period` steps.
+    ///
+    /// A period of 0 is treated as 1.
+    pub fn add_callback(&mut self, period: u64, callback: Box<dyn FnMut() + 'a>) {
+        let period = period.max(1);
+        self.events.push(Event {
+            period,
+            next:   self.steps + period,
+            callback,
+        });
+    }
+
+    /// Get the total number of steps run.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Step the CPU `count` times, calling back each time a period elapses.
+    ///
+    /// Callbacks due on the same step are called in the order they were added.
+    /// If a step returns anything other than `StepResult::Executed` or `StepResult::Waiting`,
+    /// the scheduler stops and returns it. Otherwise this returns `StepResult::Executed`.
+    /// 
+    /// Steps where the CPU is waiting for an interrupt still count, so devices can raise one.
+    /// Steps that execute no instruction (a breakpoint, a garbage fetch, or a halted CPU) take no time,
+    /// so resuming after a breakpoint doesn't count the instruction twice.
+    pub fn run<C: MIPSCore>(&mut self, cpu: &mut C, count: u64) -> StepResult {
+        for _ in 0..count {
+            let result = cpu.step();
+            if matches!(result, StepResult::Breakpoint | StepResult::GarbageFetch | StepResult::Halted) {
+                return result;
+            }
+            self.steps += 1;
+            for event in self.events.iter_mut() {
+                if self.steps == event.next {
+                    (event.callback)();
+                    event.next += event.period;
+                }
+            }
+            if !matches!(result, StepResult::Executed | StepResult::Waiting) {
+                return result;
+            }
+        }
+        StepResult::Executed
+    }
+}
+
+impl<'a> Default for Scheduler<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use crate::cpu::mips1::MIPSI;
+    use crate::mem::RamMemory;
+
+    #[test]
+    fn periods() {
+        let mut cpu = MIPSI::<RamMemory<0x100>>::with_memory(Box::new(RamMemory::new())).build();
+        let timer = Cell::new(0);
+        let uart = Cell::new(0);
+
+        let mut sched = Scheduler::new();
+        sched.add_callback(3, Box::new(|| timer.set(timer.get() + 1)));
+        sched.add_callback(10, Box::new(|| uart.set(uart.get() + 1)));
+
+        assert_eq!(sched.run(&mut cpu, 20), StepResult::Executed);
+        assert_eq!(sched.steps(), 20);
+        assert_eq!(timer.get(), 6);
+        assert_eq!(uart.get(), 2);
+
+        sched.run(&mut cpu, 1);
+        assert_eq!(timer.get(), 7);
+        assert_eq!(uart.get(), 2);
+    }
+
+    #[test]
+    fn stops_at_breakpoint() {
+        let mut cpu = MIPSI::<RamMemory<0x100>>::with_memory(Box::new(RamMemory::new())).build();
+        cpu.add_breakpoint(0x10);
+
+        let ticks = Cell::new(0);
+        let mut sched = Scheduler::new();
+        sched.add_callback(1, Box::new(|| ticks.set(ticks.get() + 1)));
+        assert_eq!(sched.run(&mut cpu, 100), StepResult::Breakpoint);
+        assert_eq!(sched.steps(), 4);
+        assert_eq!(ticks.get(), 4);
+        assert_eq!(cpu.read_pc(), 0x10);
+
+        // Resuming executes the instruction at the breakpoint, counted once.
+        assert_eq!(sched.run(&mut cpu, 1), StepResult::Executed);
+        assert_eq!(sched.steps(), 5);
+        assert_eq!(ticks.get(), 5);
+        assert_eq!(cpu.read_pc(), 0x14);
+    }
+}
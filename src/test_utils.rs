@@ -0,0 +1,124 @@
+// Running short instruction sequences, for tests.
+
+use alloc::boxed::Box;
+
+use crate::cpu::{
+    MIPSCore,
+    MIPSICore,
+    ExceptionInfo,
+    StepResult
+};
+use crate::cpu::mips1::MIPSI;
+use crate::mem::{
+    Mem32,
+    RamMemory
+};
+
+// The size of the RAM the program runs in. The program is loaded at address 0.
+// Accesses outside of it read 0 and discard writes.
+const RAM_SIZE: usize = 0x1000;
+
+// The most instructions run before giving up, in case the program loops forever.
+const MAX_STEPS: usize = 0x10000;
+
+/// The CPU made by `test_cpu`.
+pub type TestCpu = MIPSI<RamMemory<RAM_SIZE>>;
+
+/// Make a fresh CPU over a small zeroed RAM, with the registers given and no coprocessors.
+/// 
+/// Exceptions are recorded but not vectored to.
+pub fn test_cpu(regs: [u32; 32]) -> TestCpu {
+    let mut cpu = TestCpu::with_memory(Box::new(RamMemory::new())).build();
+    for (reg, val) in regs.iter().enumerate() {
+        cpu.write_gp(reg, *val);
+    }
+    cpu
+}
+
+/// Get the general-purpose registers of a CPU.
+pub fn registers(cpu: &TestCpu) -> [u32; 32] {
+    let mut regs = [0; 32];
+    for (reg, val) in regs.iter_mut().enumerate() {
+        *val = cpu.read_gp(reg);
+    }
+    regs
+}
+
+/// The state left after running a program with `run_program`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramResult {
+    /// The general-purpose registers.
+    pub regs:       [u32; 32],
+    /// The exception that stopped the program, if any.
+    pub exception:  Option<ExceptionInfo>,
+    /// The number of steps run.
+    pub steps:      usize,
+}
+
+/// Run a sequence of instructions on a fresh CPU, and get the registers afterwards.
+///
+/// The instructions are loaded contiguously from address 0 of a small zeroed RAM,
+/// and the CPU starts there with the registers given and no coprocessors.
+/// The program stops when the PC leaves it (normally by running off the end),
+/// when an exception is triggered (for example by `BREAK`), or when the CPU stops making progress.
+///
+/// Panics if the program doesn't fit in the RAM.
+pub fn run_program(instrs: &[u32], regs: [u32; 32]) -> ProgramResult {
+    let end = instrs.len() * 4;
+    assert!(end <= RAM_SIZE, "program of {} instructions doesn't fit in RAM", instrs.len());
+
+    let mut cpu = test_cpu(regs);
+    for (i, instr) in instrs.iter().enumerate() {
+        cpu.mem().write_word((i * 4) as u32, *instr);
+    }
+
+    let mut steps = 0;
+    while (cpu.read_pc() as usize) < end && steps < MAX_STEPS {
+        let result = cpu.step();
+        steps += 1;
+        if result != StepResult::Executed || cpu.last_exception().is_some() {
+            break;
+        }
+    }
+
+    ProgramResult {
+        regs: registers(&cpu),
+        exception: cpu.last_exception(),
+        steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::ExceptionCode;
+
+    #[test]
+    fn three_instructions() {
+        let mut regs = [0; 32];
+        regs[4] = 10;
+        let result = run_program(&[
+            0x3401_0005,    // ori $1, $0, 5
+            0x2422_0003,    // addiu $2, $1, 3
+            0x0082_1821,    // addu $3, $4, $2
+        ], regs);
+        assert_eq!(result.exception, None);
+        assert_eq!(result.steps, 3);
+        assert_eq!(result.regs[1], 5);
+        assert_eq!(result.regs[2], 8);
+        assert_eq!(result.regs[3], 18);
+        assert_eq!(result.regs[4], 10);
+    }
+
+    #[test]
+    fn stops_at_exception() {
+        let result = run_program(&[
+            0x3401_0005,    // ori $1, $0, 5
+            0x0000_000D,    // break
+            0x3402_0005,    // ori $2, $0, 5
+        ], [0; 32]);
+        assert_eq!(result.exception.map(|e| (e.code, e.pc)), Some((ExceptionCode::Breakpoint, 4)));
+        assert_eq!(result.steps, 2);
+        assert_eq!(result.regs[2], 0);
+    }
+}